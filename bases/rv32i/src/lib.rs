@@ -6,8 +6,9 @@ use rvcore::{
         TypeAuiPc, TypeBranch, TypeJal, TypeJalR, TypeLoad, TypeLui, TypeMiscMem, TypeOp,
         TypeOpImm, TypeStore, TypeSystem, OPCODE_AUIPC, OPCODE_BRANCH, OPCODE_JAL, OPCODE_JALR,
         OPCODE_LOAD, OPCODE_LUI, OPCODE_MASK, OPCODE_MISCMEM, OPCODE_OP, OPCODE_OPIMM,
-        OPCODE_STORE,
+        OPCODE_STORE, OPCODE_SYSTEM,
     },
+    snapshot::{Snapshot, SnapshotError},
     Base, EResult, Volatile,
 };
 
@@ -21,7 +22,8 @@ pub struct RV32I {
 impl RV32I {
     pub fn new(bus: Bus) -> Self {
         let mut registers = [0i32; 32];
-        registers[2] = bus.dram.size() as i32;
+        // the stack grows down from the end of memory
+        registers[2] = (bus.base() + bus.dram.size()) as i32;
 
         Self {
             registers,
@@ -43,6 +45,50 @@ impl RV32I {
     pub fn pc(&self) -> &i32 {
         &self.pc
     }
+
+    pub fn set_pc(&mut self, pc: i32) {
+        self.pc = pc;
+    }
+
+    /// Whether the branch `ins` is taken with the current registers, None if it isn't a branch
+    ///
+    /// The outcome can't be told from the next pc, a branch to the next instruction goes there
+    /// either way.
+    pub fn branch_taken(&self, ins: u32) -> Option<bool> {
+        if ins & OPCODE_MASK != OPCODE_BRANCH {
+            return None;
+        }
+
+        let data = TypeBranch::decode(ins);
+        let rs1 = self.get(data.rs1 as usize);
+        let rs2 = self.get(data.rs2 as usize);
+        Some(match data.funct3 {
+            0 => rs1 == rs2,                   // beq
+            1 => rs1 != rs2,                   // bne
+            4 => rs1 < rs2,                    // blt
+            5 => rs1 >= rs2,                   // bge
+            6 => (rs1 as u32) < (rs2 as u32),  // bltu
+            7 => (rs1 as u32) >= (rs2 as u32), // bgeu
+
+            _ => return None,
+        })
+    }
+
+    /// Saves the registers, pc, memory and devices
+    pub fn save(&self, snapshot: &mut Snapshot) {
+        snapshot.pc = self.pc as u32;
+        snapshot.registers = self.registers.map(|value| value as u32);
+        self.bus.save(snapshot);
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), SnapshotError> {
+        self.bus.restore(snapshot)?;
+        self.pc = snapshot.pc as i32;
+        self.registers = snapshot.registers.map(|value| value as i32);
+        // `x0` is hardwired
+        self.registers[0] = 0;
+        Ok(())
+    }
 }
 
 impl Volatile<i32> for RV32I {
@@ -62,27 +108,29 @@ impl Volatile<i32> for RV32I {
 impl Base<i32> for RV32I {
     // ---- Fetch ----
     fn fetch(&mut self) -> i32 {
-        let value = self.bus.load(self.pc as usize, 32);
-        self.pc += 4;
+        let value = self.bus.fetch(self.pc as u32 as usize);
+        self.pc = self.pc.wrapping_add(4);
         value as i32
     }
 
     // ---- Execution ----
     fn execute(&mut self, ins: u32) -> EResult {
         match ins & OPCODE_MASK {
-            0 => return EResult::NotFound, // If this isn't here `OPCODE_SYSTEM` is matched instead
-
             OPCODE_OPIMM => {
                 let data = TypeOpImm::decode(ins);
                 let rs1 = self.get(data.rs1 as usize);
                 let imm11_0 = data.imm;
-                let value = match data.funct3 {
-                    0 => rs1 + imm11_0,                            // addi
-                    2 => (rs1 < imm11_0) as i32,                   // slti
-                    3 => ((rs1 as u32) < (imm11_0 as u32)) as i32, // sltiu
-                    4 => rs1 ^ imm11_0,                            // xori
-                    6 => rs1 | imm11_0,                            // ori
-                    7 => rs1 & imm11_0,                            // andi
+                let shamt = (imm11_0 & 0b11111) as u32;
+                let value = match (data.funct3, imm11_0 >> 5) {
+                    (0, _) => rs1.wrapping_add(imm11_0),                // addi
+                    (2, _) => (rs1 < imm11_0) as i32,                   // slti
+                    (3, _) => ((rs1 as u32) < (imm11_0 as u32)) as i32, // sltiu
+                    (4, _) => rs1 ^ imm11_0,                            // xori
+                    (6, _) => rs1 | imm11_0,                            // ori
+                    (7, _) => rs1 & imm11_0,                            // andi
+                    (1, 0) => rs1 << shamt,                             // slli
+                    (5, 0) => ((rs1 as u32) >> shamt) as i32,           // srli
+                    (5, 32) => rs1 >> shamt,                            // srai
 
                     _ => return EResult::NotFound,
                 };
@@ -95,24 +143,25 @@ impl Base<i32> for RV32I {
             }
             OPCODE_AUIPC => {
                 let data = TypeAuiPc::decode(ins);
-                let value = (data.imm << 12) + self.pc - 4;
+                let value = (data.imm << 12).wrapping_add(self.pc.wrapping_sub(4));
                 self.set(data.rd as usize, value);
             }
             OPCODE_OP => {
                 let data = TypeOp::decode(ins);
                 let rs1 = self.get(data.rs1 as usize);
                 let rs2 = self.get(data.rs2 as usize);
+                let shamt = rs2 as u32 & 0b11111;
                 let value = match (data.funct7, data.funct3) {
-                    (0, 0) => rs1 + rs2,                             // add
-                    (32, 0) => rs1 - rs2,                            // sub
-                    (0, 1) => ((rs1 as u32) << (rs2 as u32)) as i32, // sll
-                    (0, 2) => (rs1 < rs2) as i32,                    // slt
-                    (0, 3) => ((rs1 as u32) < (rs2 as u32)) as i32,  // sltu
-                    (0, 4) => rs1 ^ rs2,                             // xor
-                    (0, 5) => ((rs1 as u32) >> (rs2 as u32)) as i32, // srl
-                    (32, 5) => rs1 >> rs2,                           // sra
-                    (0, 6) => rs1 | rs2,                             // or
-                    (0, 7) => rs1 & rs2,                             // and
+                    (0, 0) => rs1.wrapping_add(rs2),                // add
+                    (32, 0) => rs1.wrapping_sub(rs2),               // sub
+                    (0, 1) => rs1 << shamt,                         // sll
+                    (0, 2) => (rs1 < rs2) as i32,                   // slt
+                    (0, 3) => ((rs1 as u32) < (rs2 as u32)) as i32, // sltu
+                    (0, 4) => rs1 ^ rs2,                            // xor
+                    (0, 5) => ((rs1 as u32) >> shamt) as i32,       // srl
+                    (32, 5) => rs1 >> shamt,                        // sra
+                    (0, 6) => rs1 | rs2,                            // or
+                    (0, 7) => rs1 & rs2,                            // and
 
                     _ => return EResult::NotFound,
                 };
@@ -122,38 +171,30 @@ impl Base<i32> for RV32I {
             OPCODE_JAL => {
                 let data = TypeJal::decode(ins);
                 self.set(data.rd as usize, self.pc);
-                self.pc = self.pc - 4 + data.imm;
+                self.pc = self.pc.wrapping_sub(4).wrapping_add(data.imm);
             }
             OPCODE_JALR => {
                 let data = TypeJalR::decode(ins);
-                let mut rs1 = self.get(data.rs1 as usize) + data.imm;
+                let mut rs1 = self.get(data.rs1 as usize).wrapping_add(data.imm);
                 rs1 = (rs1 >> 1) << 1; // set least-significant bit to `0`
 
+                // the target is absolute, not relative to the pc
                 self.set(data.rd as usize, self.pc);
-                self.pc = self.pc - 4 + rs1;
+                self.pc = rs1;
             }
             OPCODE_BRANCH => {
-                let data = TypeBranch::decode(ins);
-                let rs1 = self.get(data.rs1 as usize);
-                let rs2 = self.get(data.rs2 as usize);
-                let result = match data.funct3 {
-                    0 => rs1 == rs2,                   // beq
-                    1 => rs1 != rs2,                   // bne
-                    4 => rs1 < rs2,                    // blt
-                    5 => rs1 >= rs2,                   // bge
-                    6 => (rs1 as u32) < (rs2 as u32),  // bltu
-                    7 => (rs1 as u32) >= (rs2 as u32), // bgeu
-
-                    _ => return EResult::NotFound,
+                let Some(taken) = self.branch_taken(ins) else {
+                    return EResult::NotFound;
                 };
 
-                if result {
-                    self.pc = self.pc - 4 + data.imm;
+                if taken {
+                    let data = TypeBranch::decode(ins);
+                    self.pc = self.pc.wrapping_sub(4).wrapping_add(data.imm);
                 }
             }
             OPCODE_LOAD => {
                 let data = TypeLoad::decode(ins);
-                let rs1 = (self.get(data.rs1 as usize) + data.imm) as usize;
+                let rs1 = self.get(data.rs1 as usize).wrapping_add(data.imm) as u32 as usize;
 
                 let bus = self.bus();
                 let value = match data.funct3 {
@@ -170,7 +211,7 @@ impl Base<i32> for RV32I {
             }
             OPCODE_STORE => {
                 let data = TypeStore::decode(ins);
-                let rs1 = (self.get(data.rs1 as usize) + data.imm) as usize;
+                let rs1 = self.get(data.rs1 as usize).wrapping_add(data.imm) as u32 as usize;
                 let rs2 = self.get(data.rs2 as usize);
 
                 let bus = self.bus();
@@ -194,32 +235,10 @@ impl Base<i32> for RV32I {
             }
             OPCODE_MISCMEM => {
                 let data = TypeMiscMem::decode(ins);
-                let fm = data.imm >> 27;
-                let pi = (data.imm >> 26) & 1;
-                let po = (data.imm >> 25) & 1;
-                let pr = (data.imm >> 24) & 1;
-                let pw = (data.imm >> 23) & 1;
-                let si = (data.imm >> 22) & 1;
-                let so = (data.imm >> 21) & 1;
-                let sr = (data.imm >> 20) & 1;
-                let sw = (data.imm >> 19) & 1;
                 match data.funct3 {
-                    0 => {
-                        if fm == 8
-                            && ((pi << 3) | (po << 2) | (pr << 1) | pw) == 3
-                            && ((si << 3) | (so << 2) | (sr << 1) | sw) == 3
-                        {
-                            todo!("fence.tso"); // fence.tso
-                        } else if fm == 0
-                            && ((pi << 3) | (po << 2) | (pr << 1) | pw) == 1
-                            && ((si << 3) | (so << 2) | (sr << 1) | sw) == 0
-                            && data.rs1 == 0
-                        {
-                            todo!("pause"); // pause
-                        } else {
-                            todo!("fence"); // fence
-                        }
-                    }
+                    // fence, fence.tso and pause
+                    // a single in-order hart never observes reordered memory accesses
+                    0 => (),
 
                     _ => return EResult::NotFound,
                 }
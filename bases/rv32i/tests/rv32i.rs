@@ -0,0 +1,135 @@
+use rv32i::RV32I;
+use rvcore::{bus::Bus, Base, DRam, EResult, Volatile};
+
+fn i_type(imm: i32, rs1: u32, funct3: u32, rd: u32, opcode: u32) -> u32 {
+    ((imm as u32 & 0xfff) << 20) | (rs1 << 15) | (funct3 << 12) | (rd << 7) | opcode
+}
+
+fn r_type(funct7: u32, rs2: u32, rs1: u32, funct3: u32, rd: u32) -> u32 {
+    (funct7 << 25) | (rs2 << 20) | (rs1 << 15) | (funct3 << 12) | (rd << 7) | 0b0110011
+}
+
+/// Runs `ins` from the pc, the way the emulator fetches before it executes
+fn step(base: &mut RV32I, ins: u32) {
+    let pc = *base.pc() as u32 as usize;
+    base.bus().store(pc, 32, ins);
+    let fetched = base.fetch() as u32;
+    assert!(matches!(base.execute(fetched), EResult::Found));
+}
+
+fn machine() -> RV32I {
+    RV32I::new(Bus::new(DRam::new(0x100)))
+}
+
+#[test]
+fn jalr_target_and_link() {
+    let mut base = machine();
+    base.set_pc(0x40);
+    base.set(5, 0x2001);
+
+    // jalr ra, 3(t0) jumps to the absolute address with the lowest bit cleared
+    step(&mut base, i_type(3, 5, 0, 1, 0b1100111));
+    assert_eq!(*base.pc(), 0x2004);
+    assert_eq!(base.get(1), 0x44);
+
+    // jalr x0, -4(ra) links nothing
+    base.set_pc(0x80);
+    step(&mut base, i_type(-4, 1, 0, 0, 0b1100111));
+    assert_eq!(*base.pc(), 0x40);
+    assert_eq!(base.get(0), 0);
+}
+
+#[test]
+fn shift_amounts_are_masked() {
+    let mut base = machine();
+    base.set(5, 3);
+    base.set(6, 33);
+
+    // sll x7, t0, t1 shifts by 33 & 31
+    step(&mut base, r_type(0, 6, 5, 1, 7));
+    assert_eq!(base.get(7), 6);
+
+    // srl and sra by 36 shift by 4
+    base.set(5, -32);
+    base.set(6, 36);
+    step(&mut base, r_type(0, 6, 5, 5, 7));
+    assert_eq!(base.get(7) as u32, 0x0fff_fffe);
+    step(&mut base, r_type(32, 6, 5, 5, 7));
+    assert_eq!(base.get(7), -2);
+}
+
+#[test]
+fn immediate_shifts() {
+    let mut base = machine();
+    base.set(5, -16);
+
+    // slli x7, t0, 4
+    step(&mut base, i_type(4, 5, 1, 7, 0b0010011));
+    assert_eq!(base.get(7), -256);
+    // srli x7, t0, 2 fills with zeros
+    step(&mut base, i_type(2, 5, 5, 7, 0b0010011));
+    assert_eq!(base.get(7) as u32, 0x3fff_fffc);
+    // srai x7, t0, 2 fills with the sign
+    step(&mut base, i_type(0x400 | 2, 5, 5, 7, 0b0010011));
+    assert_eq!(base.get(7), -4);
+
+    // a shift amount of 32 or more is illegal on RV32
+    let slli = i_type(32, 5, 1, 7, 0b0010011);
+    assert!(matches!(base.execute(slli), EResult::NotFound));
+}
+
+#[test]
+fn arithmetic_wraps() {
+    let mut base = machine();
+    base.set(5, i32::MAX);
+    base.set(6, 1);
+
+    // add x7, t0, t1
+    step(&mut base, r_type(0, 6, 5, 0, 7));
+    assert_eq!(base.get(7), i32::MIN);
+    // sub x7, x7, t1
+    step(&mut base, r_type(32, 6, 7, 0, 7));
+    assert_eq!(base.get(7), i32::MAX);
+    // addi x7, t0, 1
+    step(&mut base, i_type(1, 5, 0, 7, 0b0010011));
+    assert_eq!(base.get(7), i32::MIN);
+
+    // j -8 from the bottom of memory wraps around the address space
+    base.set_pc(0);
+    step(&mut base, 0xff9f_f06f);
+    assert_eq!(*base.pc() as u32, 0xffff_fff8);
+}
+
+/// `funct3 rs1, rs2, 4`, a branch to the next instruction
+fn branch(funct3: u32, rs1: u32, rs2: u32) -> u32 {
+    (rs2 << 20) | (rs1 << 15) | (funct3 << 12) | (0b0010 << 8) | 0b1100011
+}
+
+#[test]
+fn branch_outcomes() {
+    let mut base = machine();
+    base.set(5, -1);
+    base.set(6, 1);
+
+    // beq, bne, blt, bge, bltu and bgeu on t0 and t1, signed -1 < 1 but unsigned it's the largest
+    let outcomes = [
+        (0, false),
+        (1, true),
+        (4, true),
+        (5, false),
+        (6, false),
+        (7, true),
+    ];
+    for (funct3, taken) in outcomes {
+        let ins = branch(funct3, 5, 6);
+        assert_eq!(base.branch_taken(ins), Some(taken), "funct3 {}", funct3);
+
+        // either way it goes on to the next instruction
+        base.set_pc(0x40);
+        step(&mut base, ins);
+        assert_eq!(*base.pc(), 0x44);
+    }
+
+    assert_eq!(base.branch_taken(branch(2, 5, 6)), None);
+    assert_eq!(base.branch_taken(i_type(1, 5, 0, 7, 0b0010011)), None);
+}
@@ -0,0 +1,88 @@
+use std::{error::Error, path::PathBuf, process::ExitCode};
+
+use clap::{Parser, ValueEnum};
+use rvasm::{AsmError, Assembler};
+use rvcore::{isa::Isa, util::parse_u32};
+
+/// Assembles RISC-V source into an ELF executable or a raw binary
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    /// Assembly source file
+    input: PathBuf,
+
+    /// Output file, defaults to the input with an `.elf` or `.bin` extension
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = Format::Elf)]
+    format: Format,
+
+    /// Extensions instructions may use, like `rv32imf_zicsr`
+    #[arg(long, default_value_t = Isa::ALL)]
+    isa: Isa,
+
+    /// Address of the first instruction
+    #[arg(long, default_value = "0", value_parser = address)]
+    origin: u32,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Elf,
+    Bin,
+}
+
+fn address(text: &str) -> Result<u32, String> {
+    parse_u32(text).ok_or(format!("invalid address `{}`", text))
+}
+
+fn main() -> Result<ExitCode, Box<dyn Error>> {
+    let args = Args::parse();
+    let source = std::fs::read_to_string(&args.input)?;
+
+    let assembler = Assembler::new(args.isa)
+        .origin(args.origin)
+        .file(args.input.display().to_string());
+    let program = match assembler.assemble(&source) {
+        Ok(program) => program,
+        Err(errors) => {
+            for error in &errors {
+                report(error, &source);
+            }
+
+            eprintln!("{} error(s)", errors.len());
+            return Ok(ExitCode::FAILURE);
+        }
+    };
+
+    let (bytes, extension) = match args.format {
+        Format::Elf => (program.to_elf().to_bytes(), "elf"),
+        Format::Bin => (program.to_binary(), "bin"),
+    };
+    let output = args
+        .output
+        .unwrap_or_else(|| args.input.with_extension(extension));
+    std::fs::write(output, bytes)?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Prints an error with the source line and a caret under the token
+fn report(error: &AsmError, source: &str) {
+    eprintln!("{}", error);
+
+    let Some(line) = source.lines().nth(error.line - 1) else {
+        return;
+    };
+    let indent: String = line
+        .chars()
+        .take(error.column - 1)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let width = error.token.chars().count().max(1);
+
+    eprintln!("  {}", line);
+    eprintln!("  {}{}", indent, "^".repeat(width));
+}
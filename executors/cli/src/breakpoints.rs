@@ -0,0 +1,387 @@
+use std::fmt::Display;
+
+use rv32i::RV32I;
+use rvcore::{util::parse_u32, Volatile};
+
+use crate::{emulator::Emulator, history::Step};
+
+/// Stops execution before the instruction at `addr` runs
+pub struct Breakpoint {
+    pub addr: u32,
+    pub condition: Option<Condition>,
+    /// Times the instruction was about to run, including the current one
+    pub hits: u64,
+}
+
+#[derive(Default)]
+pub struct Breakpoints {
+    list: Vec<Breakpoint>,
+}
+
+/// Where reverse execution stopped
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReverseStop {
+    /// The history has no older steps
+    HistoryEnd,
+    /// Before the instruction at the address, it triggered a watchpoint
+    Watch(u32),
+    /// At a breakpoint that would stop execution going forward
+    Breakpoint(u32),
+}
+
+impl Breakpoints {
+    pub fn iter(&self) -> impl Iterator<Item = &Breakpoint> {
+        self.list.iter()
+    }
+
+    pub fn contains(&self, addr: u32) -> bool {
+        self.list.iter().any(|b| b.addr == addr)
+    }
+
+    /// Adds a breakpoint, or removes it if there already is one at `addr`
+    pub fn toggle(&mut self, addr: u32) {
+        if self.contains(addr) {
+            self.list.retain(|b| b.addr != addr);
+        } else {
+            self.list.push(Breakpoint {
+                addr,
+                condition: None,
+                hits: 0,
+            });
+            self.list.sort_by_key(|b| b.addr);
+        }
+    }
+
+    pub fn get_mut(&mut self, addr: u32) -> Option<&mut Breakpoint> {
+        self.list.iter_mut().find(|b| b.addr == addr)
+    }
+
+    /// Returns true if the breakpoint at the pc stops execution, its current hit already counted
+    pub fn matches(&self, rv_base: &RV32I) -> bool {
+        let pc = *rv_base.pc() as u32;
+        self.list.iter().any(|b| {
+            b.addr == pc
+                && b.condition
+                    .as_ref()
+                    .is_none_or(|condition| condition.eval(rv_base, b.hits))
+        })
+    }
+
+    /// Counts a hit on the breakpoint at the pc, returns true if execution should stop
+    pub fn hit(&mut self, rv_base: &RV32I) -> bool {
+        let pc = *rv_base.pc() as u32;
+        let Some(breakpoint) = self.get_mut(pc) else {
+            return false;
+        };
+
+        breakpoint.hits += 1;
+        self.matches(rv_base)
+    }
+
+    /// Undoes the last instruction, returns it or None if the history is empty
+    ///
+    /// `counted` is set while the hit at the pc was counted but the instruction hasn't run yet,
+    /// like after stopping on it, and follows the pc back. Leaving a counted instruction takes
+    /// its hit back, so running forward again counts every hit once.
+    pub fn step_back(&mut self, emulator: &mut Emulator, counted: &mut bool) -> Option<Step> {
+        let pc = *emulator.base.pc() as u32;
+        let step = emulator.step_back()?;
+
+        // the instruction we're back at was counted before it ran
+        if std::mem::replace(counted, true) {
+            if let Some(breakpoint) = self.get_mut(pc) {
+                breakpoint.hits = breakpoint.hits.saturating_sub(1);
+            }
+        }
+        Some(step)
+    }
+
+    /// Steps back to where a breakpoint would have stopped execution, or to before an
+    /// instruction that triggered a watchpoint
+    pub fn reverse_continue(&mut self, emulator: &mut Emulator, counted: &mut bool) -> ReverseStop {
+        loop {
+            let Some(step) = self.step_back(emulator, counted) else {
+                return ReverseStop::HistoryEnd;
+            };
+
+            if step.watch {
+                return ReverseStop::Watch(step.pc as u32);
+            }
+            if self.matches(&emulator.base) {
+                return ReverseStop::Breakpoint(step.pc as u32);
+            }
+        }
+    }
+}
+
+// ---- Condition ----
+
+/// Comparisons joined by `&&`, like `x10 == 0 && hits >= 3`
+///
+/// Registers, the pc and numbers are compared as signed 32-bit values, so `0xffffffff == -1`.
+pub struct Condition {
+    text: String,
+    clauses: Vec<(Operand, Op, Operand)>,
+}
+
+#[derive(Clone, Copy)]
+enum Operand {
+    Register(u8),
+    Pc,
+    Hits,
+    Value(i64),
+}
+
+#[derive(Clone, Copy)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Condition {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let clauses = text
+            .split("&&")
+            .map(|clause| {
+                // two character operators first, so `<=` isn't read as `<`
+                let ops = [
+                    ("==", Op::Eq),
+                    ("!=", Op::Ne),
+                    ("<=", Op::Le),
+                    (">=", Op::Ge),
+                    ("<", Op::Lt),
+                    (">", Op::Gt),
+                ];
+                let (lhs, op, rhs) = ops
+                    .iter()
+                    .find_map(|(token, op)| {
+                        let (lhs, rhs) = clause.split_once(token)?;
+                        Some((lhs, *op, rhs))
+                    })
+                    .ok_or(format!("expected a comparison in `{}`", clause.trim()))?;
+
+                Ok((operand(lhs)?, op, operand(rhs)?))
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(Self {
+            text: text.trim().into(),
+            clauses,
+        })
+    }
+
+    pub fn eval(&self, rv_base: &RV32I, hits: u64) -> bool {
+        let value = |operand| match operand {
+            Operand::Register(i) => rv_base.get(i as usize) as i64,
+            Operand::Pc => *rv_base.pc() as i64,
+            Operand::Hits => hits as i64,
+            Operand::Value(value) => value,
+        };
+
+        self.clauses.iter().all(|(lhs, op, rhs)| {
+            let (lhs, rhs) = (value(*lhs), value(*rhs));
+            match op {
+                Op::Eq => lhs == rhs,
+                Op::Ne => lhs != rhs,
+                Op::Lt => lhs < rhs,
+                Op::Le => lhs <= rhs,
+                Op::Gt => lhs > rhs,
+                Op::Ge => lhs >= rhs,
+            }
+        })
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+fn operand(text: &str) -> Result<Operand, String> {
+    let text = text.trim();
+    if let Some(index) = rvasm::xreg(text) {
+        return Ok(Operand::Register(index));
+    }
+
+    match text {
+        "pc" => Ok(Operand::Pc),
+        "hits" => Ok(Operand::Hits),
+        _ => {
+            // registers and the pc are signed, so `0xffffffff` is -1
+            let value = match text.strip_prefix('-') {
+                Some(magnitude) => parse_u32(magnitude).map(|v| (v as i32).wrapping_neg() as i64),
+                None => parse_u32(text).map(|v| v as i32 as i64),
+            };
+            value
+                .map(Operand::Value)
+                .ok_or(format!("unknown operand `{}`", text))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use clap::Parser;
+    use rvcore::{
+        bus::Bus,
+        watch::{WatchKind, Watchpoint},
+        DRam,
+    };
+
+    use super::*;
+    use crate::{emulator::TickResult, setup, Args};
+
+    fn machine(pc: u32) -> RV32I {
+        let mut rv_base = RV32I::new(Bus::new(DRam::new(0x100)));
+        rv_base.set_pc(pc as i32);
+        rv_base
+    }
+
+    #[test]
+    fn parse() {
+        let condition = Condition::parse(" x10 == 0 && hits >= 3 ").unwrap();
+        assert_eq!(condition.to_string(), "x10 == 0 && hits >= 3");
+        assert_eq!(condition.clauses.len(), 2);
+
+        assert!(Condition::parse("a0 <= -4").is_ok());
+        assert!(Condition::parse("pc != 0x10").is_ok());
+        assert!(Condition::parse("x10").is_err());
+        assert!(Condition::parse("x10 == foo").is_err());
+        assert!(Condition::parse("x10 == 1 && ").is_err());
+    }
+
+    #[test]
+    fn eval() {
+        let mut rv_base = machine(0x10);
+        rv_base.set(10, -1);
+        rv_base.set(11, 5);
+
+        let eval =
+            |text: &str, rv_base: &RV32I, hits| Condition::parse(text).unwrap().eval(rv_base, hits);
+        assert!(eval("a0 == 0xffffffff", &rv_base, 0));
+        assert!(eval("a0 == -1 && a1 > a0", &rv_base, 0));
+        assert!(eval("a1 <= 5 && a1 >= 5 && a1 != 4 && a1 < 6", &rv_base, 0));
+        assert!(!eval("a1 == 5 && hits >= 3", &rv_base, 2));
+        assert!(eval("a1 == 5 && hits >= 3", &rv_base, 3));
+        assert!(eval("pc == 16", &rv_base, 0));
+    }
+
+    #[test]
+    fn high_pc() {
+        let rv_base = machine(0x8000_0010);
+        let condition = Condition::parse("pc == 0x80000010").unwrap();
+        assert!(condition.eval(&rv_base, 0));
+
+        let mut breakpoints = Breakpoints::default();
+        breakpoints.toggle(0x8000_0010);
+        breakpoints.get_mut(0x8000_0010).unwrap().condition = Some(condition);
+        assert!(breakpoints.matches(&rv_base));
+        assert!(breakpoints.hit(&rv_base));
+    }
+
+    /// The sample sum program adding up 5 to 1, recording its history
+    fn sum() -> (Emulator, u32) {
+        let args = Args::try_parse_from(["rvcli"]).unwrap();
+        let program = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scripts/sum.s");
+        let (mut emulator, symbols) = setup(&args, args.isa, Some(&program)).unwrap();
+        emulator.record_history(100);
+        emulator.base.set(10, 5);
+        (emulator, symbols.get("loop").unwrap().addr)
+    }
+
+    /// Runs like continuous mode in the TUI, returns true if a breakpoint stopped it before the
+    /// program exited
+    fn resume(breakpoints: &mut Breakpoints, emulator: &mut Emulator, counted: &mut bool) -> bool {
+        loop {
+            if !std::mem::take(counted) && breakpoints.hit(&emulator.base) {
+                *counted = true;
+                return true;
+            }
+            if let TickResult::Exit(..) = emulator.tick() {
+                return false;
+            }
+        }
+    }
+
+    #[test]
+    fn reverse_stops_where_forward_does() {
+        let (mut emulator, loop_) = sum();
+        let mut breakpoints = Breakpoints::default();
+        breakpoints.toggle(loop_);
+        breakpoints.get_mut(loop_).unwrap().condition =
+            Some(Condition::parse("hits == 2").unwrap());
+        let hits = |breakpoints: &Breakpoints| breakpoints.iter().next().unwrap().hits;
+        let mut counted = false;
+
+        // the second iteration, with a0 counted down once
+        assert!(resume(&mut breakpoints, &mut emulator, &mut counted));
+        assert_eq!((hits(&breakpoints), emulator.base.get(10)), (2, 4));
+        assert!(!resume(&mut breakpoints, &mut emulator, &mut counted));
+        assert_eq!(hits(&breakpoints), 5);
+
+        // back to the same hit, the later ones are taken back
+        let stop = breakpoints.reverse_continue(&mut emulator, &mut counted);
+        assert_eq!(stop, ReverseStop::Breakpoint(loop_));
+        assert_eq!((hits(&breakpoints), emulator.base.get(10)), (2, 4));
+        assert!(counted);
+
+        // running forward again counts each hit once
+        assert!(!resume(&mut breakpoints, &mut emulator, &mut counted));
+        assert_eq!(hits(&breakpoints), 5);
+        assert_eq!(emulator.base.get(10), 15);
+    }
+
+    #[test]
+    fn step_back_takes_back_hits() {
+        let (mut emulator, loop_) = sum();
+        let mut breakpoints = Breakpoints::default();
+        breakpoints.toggle(loop_);
+        let mut counted = false;
+
+        assert!(resume(&mut breakpoints, &mut emulator, &mut counted));
+        assert_eq!(breakpoints.iter().next().unwrap().hits, 1);
+
+        // back at `_start`, which was counted when it ran
+        let step = breakpoints.step_back(&mut emulator, &mut counted).unwrap();
+        assert_eq!(*emulator.base.pc(), step.pc);
+        assert_eq!(breakpoints.iter().next().unwrap().hits, 0);
+        assert!(counted);
+
+        // the start of the history changes nothing
+        assert!(breakpoints.step_back(&mut emulator, &mut counted).is_none());
+        let stop = breakpoints.reverse_continue(&mut emulator, &mut counted);
+        assert_eq!(stop, ReverseStop::HistoryEnd);
+        assert!(counted);
+
+        assert!(resume(&mut breakpoints, &mut emulator, &mut counted));
+        assert_eq!(breakpoints.iter().next().unwrap().hits, 1);
+    }
+
+    #[test]
+    fn reverse_stops_before_watch_hits() {
+        let (mut emulator, _) = sum();
+        let mut breakpoints = Breakpoints::default();
+        emulator.base.bus().add_watchpoint(Watchpoint {
+            addr: 0,
+            len: 0x1000,
+            kind: WatchKind::Write,
+        });
+        let mut counted = false;
+        assert!(!resume(&mut breakpoints, &mut emulator, &mut counted));
+
+        // the `sw` of the sum
+        let ReverseStop::Watch(pc) = breakpoints.reverse_continue(&mut emulator, &mut counted)
+        else {
+            panic!("expected to stop at the watchpoint");
+        };
+        assert_eq!(*emulator.base.pc() as u32, pc);
+        assert_eq!(emulator.base.bus_ref().fetch(pc as usize) & 0x7f, 0b0100011);
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+};
+
+use rvcore::{
+    bus::Bus,
+    elf::{LineTable, SymbolKind, SymbolTable},
+    ins::{OPCODE_BRANCH, OPCODE_MASK},
+};
+
+/// Which instructions ran and which way each branch went, mapped back to source lines
+pub struct Coverage {
+    lines: LineTable,
+    /// Times each pc retired
+    hits: HashMap<u32, u64>,
+    /// Times each branch was taken and not taken
+    branches: HashMap<u32, (u64, u64)>,
+}
+
+/// Counts of one source line
+#[derive(Default)]
+struct Line {
+    /// The most any of its instructions ran, the others may be skipped by branches
+    hits: u64,
+    /// The branch outcomes of each branch on the line, None if it never ran
+    branches: Vec<Option<(u64, u64)>>,
+}
+
+impl Coverage {
+    pub fn new(lines: LineTable) -> Self {
+        Self {
+            lines,
+            hits: HashMap::new(),
+            branches: HashMap::new(),
+        }
+    }
+
+    /// Counts the instruction at `pc` after it retired, with the outcome if it's a branch
+    pub fn retire(&mut self, pc: u32, taken: Option<bool>) {
+        *self.hits.entry(pc).or_default() += 1;
+
+        if let Some(outcome) = taken {
+            let (taken, not_taken) = self.branches.entry(pc).or_default();
+            if outcome {
+                *taken += 1;
+            } else {
+                *not_taken += 1;
+            }
+        }
+    }
+
+    /// An lcov tracefile, as read by `genhtml`
+    ///
+    /// Every instruction the line tables cover counts, whether or not it ran. Branch
+    /// instructions are read from `bus`.
+    pub fn lcov(&self, symbols: &SymbolTable, bus: &Bus) -> String {
+        let mut files: BTreeMap<usize, BTreeMap<u32, Line>> = BTreeMap::new();
+        for range in self.lines.ranges() {
+            let line = files
+                .entry(range.file)
+                .or_default()
+                .entry(range.line)
+                .or_default();
+
+            for pc in (range.start..range.end).step_by(4) {
+                line.hits = line.hits.max(self.hits.get(&pc).copied().unwrap_or(0));
+                if bus.fetch(pc as usize) & OPCODE_MASK == OPCODE_BRANCH {
+                    line.branches.push(self.branches.get(&pc).copied());
+                }
+            }
+        }
+
+        // functions are listed in the file and at the line of their first instruction
+        let mut functions: BTreeMap<&str, Vec<(u32, &str, u64)>> = BTreeMap::new();
+        for symbol in symbols.iter().filter(|s| s.kind == SymbolKind::Func) {
+            if let Some((file, line)) = self.lines.lookup(symbol.addr) {
+                let hits = self.hits.get(&symbol.addr).copied().unwrap_or(0);
+                functions
+                    .entry(file)
+                    .or_default()
+                    .push((line, &symbol.name, hits));
+            }
+        }
+
+        let mut lcov = String::new();
+        for (file, lines) in files {
+            let name = &self.lines.files()[file];
+            let _ = writeln!(lcov, "TN:\nSF:{}", name);
+
+            let functions = functions.remove(name.as_str()).unwrap_or_default();
+            for (line, function, _) in &functions {
+                let _ = writeln!(lcov, "FN:{},{}", line, function);
+            }
+            for (_, function, hits) in &functions {
+                let _ = writeln!(lcov, "FNDA:{},{}", hits, function);
+            }
+            let hit = functions.iter().filter(|(_, _, hits)| *hits > 0).count();
+            let _ = writeln!(lcov, "FNF:{}\nFNH:{}", functions.len(), hit);
+
+            // each branch has a block of its own, taken first
+            let (mut found, mut hit) = (0, 0);
+            for (number, line) in &lines {
+                for (block, branch) in line.branches.iter().enumerate() {
+                    let outcomes = match branch {
+                        Some((taken, not_taken)) => [taken.to_string(), not_taken.to_string()],
+                        None => ["-".to_string(), "-".to_string()],
+                    };
+                    for (i, count) in outcomes.iter().enumerate() {
+                        let _ = writeln!(lcov, "BRDA:{},{},{},{}", number, block, i, count);
+                    }
+
+                    found += 2;
+                    hit += branch.map_or(0, |(taken, not_taken)| {
+                        (taken > 0) as usize + (not_taken > 0) as usize
+                    });
+                }
+            }
+            let _ = writeln!(lcov, "BRF:{}\nBRH:{}", found, hit);
+
+            for (number, line) in &lines {
+                let _ = writeln!(lcov, "DA:{},{}", number, line.hits);
+            }
+            let hit = lines.values().filter(|line| line.hits > 0).count();
+            let _ = writeln!(lcov, "LF:{}\nLH:{}\nend_of_record", lines.len(), hit);
+        }
+
+        lcov
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rvcore::{
+        elf::{Symbol, SymbolKind},
+        watch::{WatchKind, Watchpoint},
+        DRam,
+    };
+
+    use super::*;
+
+    const ADDI: u32 = 0x0000_0013;
+    const BEQ: u32 = 0x0000_0063;
+
+    /// A line table unit for `src/main.c`: 0x1000 and 0x100c are line 10, 0x1008 is line 11
+    /// and 0x1010 is line 12
+    fn lines() -> LineTable {
+        let program = [
+            // set_address 0x1000, line 10
+            &[0, 5, 2, 0x00, 0x10, 0, 0][..],
+            &[3, 9, 1],
+            // special opcode, 8 bytes and a line on
+            &[131],
+            // 4 bytes on and back to line 10, then 4 bytes and 2 lines on
+            &[2, 4, 3, 0x7f, 1],
+            &[2, 4, 3, 2, 1],
+            // end the sequence at 0x1014
+            &[2, 4, 0, 1, 1],
+        ]
+        .concat();
+
+        let mut header = vec![1, 1, 1, (-5i8) as u8, 14, 13];
+        header.extend([0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1]);
+        header.extend(b"src\0\0main.c\0\x01\0\0\0");
+
+        let mut unit = 4u16.to_le_bytes().to_vec();
+        unit.extend((header.len() as u32).to_le_bytes());
+        unit.extend(header);
+        unit.extend(program);
+
+        let mut bytes = (unit.len() as u32).to_le_bytes().to_vec();
+        bytes.extend(unit);
+        LineTable::parse(&bytes, &[], &[]).unwrap()
+    }
+
+    #[test]
+    fn lcov() {
+        let mut bus = Bus::with_base(DRam::new(0x100), 0x1000);
+        for (i, ins) in [ADDI, ADDI, BEQ, ADDI, BEQ].into_iter().enumerate() {
+            bus.store(0x1000 + i * 4, 32, ins);
+        }
+        let symbols = SymbolTable::new(vec![Symbol {
+            name: "main".into(),
+            addr: 0x1000,
+            size: 0x14,
+            kind: SymbolKind::Func,
+            global: true,
+            section: 1,
+        }]);
+
+        // the loop is taken three times, then falls through to 0x100c
+        let mut coverage = Coverage::new(lines());
+        for taken in [true, true, true, false] {
+            coverage.retire(0x1000, None);
+            coverage.retire(0x1004, None);
+            coverage.retire(0x1008, Some(taken));
+        }
+        coverage.retire(0x100c, None);
+
+        // finding the branches doesn't count as the program reading its code
+        bus.add_watchpoint(Watchpoint {
+            addr: 0x1000,
+            len: 0x14,
+            kind: WatchKind::Read,
+        });
+        let lcov = coverage.lcov(&symbols, &bus);
+        assert_eq!(bus.take_watch_hit(), None);
+
+        let expected = [
+            "TN:",
+            "SF:src/main.c",
+            "FN:10,main",
+            "FNDA:4,main",
+            "FNF:1",
+            "FNH:1",
+            "BRDA:11,0,0,3",
+            "BRDA:11,0,1,1",
+            "BRDA:12,0,0,-",
+            "BRDA:12,0,1,-",
+            "BRF:4",
+            "BRH:2",
+            "DA:10,4",
+            "DA:11,4",
+            "DA:12,0",
+            "LF:3",
+            "LH:2",
+            "end_of_record",
+        ];
+        assert_eq!(lcov.lines().collect::<Vec<_>>(), expected);
+    }
+}
@@ -0,0 +1,133 @@
+use rv_zicsr::{FCSR, FFLAGS, FRM, MCAUSE, MIE, MIP, MISA, MSTATUS, MTVEC, SATP};
+
+/// Decodes the bitfields of a CSR, empty if it's a plain value
+pub fn fields(csr: usize, value: u32) -> String {
+    match csr {
+        FFLAGS => float_flags(value),
+        FRM => rounding_mode(value << 5).into(),
+        FCSR => format!("frm={} {}", rounding_mode(value), float_flags(value)),
+        MSTATUS => {
+            let bit = |i: u32| (value >> i) & 1;
+            format!(
+                "MIE={} MPIE={} MPP={} FS={}",
+                bit(3),
+                bit(7),
+                privilege((value >> 11) & 0b11),
+                (value >> 13) & 0b11
+            )
+        }
+        MISA => {
+            let extensions: String = ('A'..='Z')
+                .enumerate()
+                .filter(|(i, _)| value & (1 << i) != 0)
+                .map(|(_, letter)| letter)
+                .collect();
+            format!("MXL={} {}", value >> 30, extensions)
+        }
+        MTVEC => {
+            let mode = match value & 0b11 {
+                0 => "direct",
+                1 => "vectored",
+                _ => "reserved",
+            };
+            format!("BASE={:#x} {}", value & !0b11, mode)
+        }
+        MCAUSE => {
+            let code = value & 0x7fff_ffff;
+            match (value >> 31 == 1, cause(value >> 31 == 1, code)) {
+                (true, Some(name)) => format!("interrupt: {}", name),
+                (false, Some(name)) => name.into(),
+                (true, None) => format!("interrupt {}", code),
+                (false, None) => format!("exception {}", code),
+            }
+        }
+        MIE | MIP => {
+            let names = ["SSI", "MSI", "STI", "MTI", "SEI", "MEI"];
+            let bits = [1, 3, 5, 7, 9, 11];
+            let set: Vec<&str> = names
+                .iter()
+                .zip(bits)
+                .filter(|(_, bit)| value & (1 << bit) != 0)
+                .map(|(name, _)| *name)
+                .collect();
+            set.join(" ")
+        }
+        SATP => {
+            let mode = if value >> 31 == 1 { "Sv32" } else { "Bare" };
+            format!(
+                "{} ASID={} PPN={:#x}",
+                mode,
+                (value >> 22) & 0x1ff,
+                value & 0x3f_ffff
+            )
+        }
+        _ => String::new(),
+    }
+}
+
+/// The `frm` field of `fcsr`
+pub fn rounding_mode(fcsr: u32) -> &'static str {
+    match (fcsr >> 5) & 0b111 {
+        0 => "rne",
+        1 => "rtz",
+        2 => "rdn",
+        3 => "rup",
+        4 => "rmm",
+        7 => "dyn",
+        _ => "invalid",
+    }
+}
+
+/// The accrued exception flags set in `fcsr`
+pub fn float_flags(fcsr: u32) -> String {
+    let flags: Vec<&str> = ["NX", "UF", "OF", "DZ", "NV"]
+        .iter()
+        .enumerate()
+        .filter(|(bit, _)| fcsr & (1 << bit) != 0)
+        .map(|(_, name)| *name)
+        .rev()
+        .collect();
+
+    if flags.is_empty() {
+        "none".into()
+    } else {
+        flags.join(" ")
+    }
+}
+
+fn privilege(mode: u32) -> &'static str {
+    match mode {
+        0 => "U",
+        1 => "S",
+        3 => "M",
+        _ => "?",
+    }
+}
+
+/// Names of the standard `mcause` codes
+fn cause(interrupt: bool, code: u32) -> Option<&'static str> {
+    Some(match (interrupt, code) {
+        (true, 1) => "supervisor software",
+        (true, 3) => "machine software",
+        (true, 5) => "supervisor timer",
+        (true, 7) => "machine timer",
+        (true, 9) => "supervisor external",
+        (true, 11) => "machine external",
+
+        (false, 0) => "instruction address misaligned",
+        (false, 1) => "instruction access fault",
+        (false, 2) => "illegal instruction",
+        (false, 3) => "breakpoint",
+        (false, 4) => "load address misaligned",
+        (false, 5) => "load access fault",
+        (false, 6) => "store address misaligned",
+        (false, 7) => "store access fault",
+        (false, 8) => "ecall from U-mode",
+        (false, 9) => "ecall from S-mode",
+        (false, 11) => "ecall from M-mode",
+        (false, 12) => "instruction page fault",
+        (false, 13) => "load page fault",
+        (false, 15) => "store page fault",
+        _ => return None,
+    })
+}
@@ -0,0 +1,714 @@
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    fs::File,
+    io::{self, BufWriter},
+};
+
+use crate::{
+    coverage::Coverage,
+    history::{History, Step},
+    profile::Profiler,
+    replay::{InputEvent, Recorder},
+    stats::Stats,
+};
+use rv32i::RV32I;
+use rv_f::RV32F;
+use rv_m::RV32M;
+use rv_zicsr::{MISA, RVZICSR};
+
+use rvcore::{
+    cache::CacheHierarchy,
+    devices::{TestFinisher, Uart},
+    ins::{TypeJal, TypeJalR, OPCODE_JAL, OPCODE_JALR, OPCODE_MASK, OPCODE_SYSTEM},
+    isa::Isa,
+    predictor::Predictors,
+    snapshot::{Snapshot, SnapshotError},
+    trace::{AccessKind, TraceRecord, TraceWriter},
+    watch::WatchHit,
+    Base, EResult, Extension, Volatile,
+};
+
+/// Linux syscall numbers
+pub const SYS_READ: i32 = 63;
+pub const SYS_WRITE: i32 = 64;
+pub const SYS_EXIT: i32 = 93;
+pub const SYS_EXIT_GROUP: i32 = 94;
+
+/// `-EBADF`, returned for file descriptors other than stdin, stdout and stderr
+const EBADF: i32 = -9;
+
+/// The base ISA and the extensions selected by the ISA string
+pub struct Emulator {
+    pub base: RV32I,
+    pub rv_m: RV32M,
+    pub rv_f: RV32F,
+    pub zicsr: RVZICSR,
+    pub isa: Isa,
+
+    /// Instructions executed to completion
+    pub retired: u64,
+    /// Address of the HTIF `tohost` word
+    pub tohost: Option<u32>,
+    /// Shadow call stack, built from the calls and returns the program makes
+    pub calls: Vec<CallFrame>,
+    /// Undo journal for stepping backwards, only kept if enabled
+    pub history: Option<History>,
+    pub profiler: Option<Profiler>,
+    pub stats: Option<Stats>,
+    pub coverage: Option<Coverage>,
+    pub predictors: Option<Predictors>,
+    /// Gets every access the guest makes, the bus has to be tracing
+    pub trace: Option<TraceWriter<BufWriter<File>>>,
+    /// Instruction fetches go to the trace too, not just the caches
+    pub trace_fetches: bool,
+    /// The first error writing the trace, reported by [`Emulator::flush_trace`]
+    trace_error: Option<io::Error>,
+    /// Gets the same accesses as the trace, including fetches
+    pub caches: Option<CacheHierarchy>,
+
+    /// Bytes for the `read` syscall
+    input: VecDeque<u8>,
+    /// Once set, `read` returns end of file instead of waiting for input
+    input_closed: bool,
+    /// A `read` syscall is waiting, so input goes to it rather than the UART
+    waiting: bool,
+    /// Bytes from the `write` syscall
+    output: Vec<u8>,
+
+    /// Logs input for a later replay
+    pub recorder: Option<Recorder>,
+    /// Recorded input still to be replayed and the instruction count it arrives at, live input
+    /// is ignored until it runs out
+    pub replay: VecDeque<(u64, InputEvent)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CallFrame {
+    /// Address of the instruction after the call
+    pub return_addr: u32,
+    /// Address the call jumped to
+    pub function: u32,
+}
+
+impl Emulator {
+    pub fn new(base: RV32I, isa: Isa) -> Self {
+        // MXL = 32 bit, then a bit per extension letter
+        let misa = (1 << 30) | (1 << 8) | ((isa.m as u32) << 12) | ((isa.f as u32) << 5);
+        let mut zicsr = RVZICSR::default();
+        zicsr.set(MISA, misa);
+
+        Self {
+            base,
+            rv_m: RV32M,
+            rv_f: RV32F::default(),
+            zicsr,
+            isa,
+
+            retired: 0,
+            tohost: None,
+            calls: Vec::new(),
+            history: None,
+            profiler: None,
+            stats: None,
+            coverage: None,
+            predictors: None,
+            trace: None,
+            trace_fetches: false,
+            trace_error: None,
+            caches: None,
+
+            input: VecDeque::new(),
+            input_closed: false,
+            waiting: false,
+            output: Vec::new(),
+
+            recorder: None,
+            replay: VecDeque::new(),
+        }
+    }
+
+    // ---- Console ----
+
+    /// Queues guest input for a waiting `read` syscall, otherwise the UART if there is one
+    pub fn push_input(&mut self, bytes: &[u8]) {
+        let uart = self.base.bus_ref().device::<Uart>().is_some();
+        self.input(if uart && !self.waiting {
+            InputEvent::Uart(bytes.to_vec())
+        } else {
+            InputEvent::Read(bytes.to_vec())
+        });
+    }
+
+    pub fn close_input(&mut self) {
+        self.input(InputEvent::Close);
+    }
+
+    fn input(&mut self, event: InputEvent) {
+        if self.replay.is_empty() {
+            self.deliver(event);
+        }
+    }
+
+    fn deliver(&mut self, event: InputEvent) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(self.retired, &event);
+        }
+
+        match event {
+            InputEvent::Read(bytes) => self.input.extend(bytes),
+            InputEvent::Uart(bytes) => {
+                if let Some(uart) = self.base.bus().device_mut::<Uart>() {
+                    uart.push_input(&bytes);
+                }
+            }
+            InputEvent::Close => self.input_closed = true,
+        }
+    }
+
+    /// Feeds the recorded input due before the next instruction
+    fn replay_input(&mut self) {
+        while self
+            .replay
+            .front()
+            .is_some_and(|(at, _)| *at <= self.retired)
+        {
+            let (_, event) = self.replay.pop_front().unwrap();
+            self.deliver(event);
+        }
+    }
+
+    /// Takes what the guest wrote through the `write` syscall and the UART
+    pub fn take_output(&mut self) -> Vec<u8> {
+        let mut output = std::mem::take(&mut self.output);
+        if let Some(uart) = self.base.bus().device_mut::<Uart>() {
+            output.extend(uart.take_output());
+        }
+
+        output
+    }
+
+    /// Keeps the last `capacity` instructions so they can be undone
+    pub fn record_history(&mut self, capacity: usize) {
+        self.history = (capacity > 0).then(|| History::new(capacity));
+        self.base.bus().record_writes(capacity > 0);
+    }
+
+    /// Reads a CSR, the floating point ones are views into `rv_f` when F is enabled
+    pub fn csr(&self, csr: usize) -> u32 {
+        self.zicsr.read(csr, self.isa.f.then_some(&self.rv_f))
+    }
+
+    pub fn set_csr(&mut self, csr: usize, value: u32) {
+        let float = self.isa.f.then_some(&mut self.rv_f);
+        self.zicsr.write(csr, value, float);
+    }
+
+    // ---- Snapshots ----
+
+    pub fn snapshot(&self) -> Snapshot {
+        let mut snapshot = Snapshot {
+            retired: self.retired,
+            ..Default::default()
+        };
+        self.base.save(&mut snapshot);
+        self.rv_f.save(&mut snapshot);
+        self.zicsr.save(&mut snapshot);
+
+        snapshot
+    }
+
+    /// Restores the machine, the history and shadow call stack start over from here
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), SnapshotError> {
+        self.base.restore(snapshot)?;
+        self.rv_f.restore(snapshot);
+        self.zicsr.restore(snapshot);
+        self.retired = snapshot.retired;
+
+        self.calls.clear();
+        if let Some(history) = &mut self.history {
+            history.clear();
+        }
+        Ok(())
+    }
+
+    /// Returns true if `ins` is a `jal` or `jalr` that writes a link register
+    pub fn is_call(ins: u32) -> bool {
+        let rd = match ins & OPCODE_MASK {
+            OPCODE_JAL => TypeJal::decode(ins).rd,
+            OPCODE_JALR => TypeJalR::decode(ins).rd,
+            _ => return false,
+        };
+
+        rd == 1 || rd == 5
+    }
+
+    pub fn tick(&mut self) -> TickResult {
+        self.replay_input();
+
+        if self.profiler.is_none()
+            && self.stats.is_none()
+            && self.coverage.is_none()
+            && self.predictors.is_none()
+        {
+            return self.execute_recorded();
+        }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.enter(&self.calls);
+        }
+
+        let (pc, retired) = (*self.base.pc() as u32, self.retired);
+        // the instruction and the syscall number it sees, an `ecall` may change a7 for the next
+        let ins = self.base.bus_ref().fetch(pc as usize);
+        let syscall = self.base.get(17);
+        let taken = self.base.branch_taken(ins);
+
+        let result = self.execute_recorded();
+        if let Some(profiler) = &mut self.profiler {
+            if self.retired > retired {
+                profiler.retire(pc, self.retired);
+            }
+        }
+        let next_pc = *self.base.pc() as u32;
+        if let Some(stats) = &mut self.stats {
+            stats.record(ins, self.retired > retired, taken, syscall, &result);
+        }
+        if self.retired > retired {
+            if let Some(coverage) = &mut self.coverage {
+                coverage.retire(pc, taken);
+            }
+            if let Some(predictors) = &mut self.predictors {
+                predictors.retire(pc, ins, next_pc, taken);
+            }
+        }
+
+        result
+    }
+
+    /// Executes an instruction, keeping what it changed in the history if enabled
+    fn execute_recorded(&mut self) -> TickResult {
+        if self.history.is_none() {
+            return self.execute();
+        }
+
+        let pc = *self.base.pc();
+        let retired = self.retired;
+        let registers: [i32; 32] = std::array::from_fn(|i| self.base.get(i));
+        let float_registers: [f32; 32] = std::array::from_fn(|i| self.rv_f.get(i));
+        let fcsr = self.rv_f.fcsr();
+
+        // only instructions that can change them need a copy
+        let ins = self.base.bus_ref().fetch(pc as u32 as usize);
+        let calls =
+            matches!(ins & OPCODE_MASK, OPCODE_JAL | OPCODE_JALR).then(|| self.calls.clone());
+        let csr = (ins & OPCODE_MASK == OPCODE_SYSTEM).then(|| {
+            let csr = (ins >> 20) as usize;
+            (csr, self.zicsr.get(csr))
+        });
+
+        let result = self.execute();
+
+        let registers = (0..32)
+            .filter(|i| self.base.get(*i) != registers[*i])
+            .map(|i| (i as u8, registers[i]))
+            .collect();
+        // compare the bits, NaN never equals itself
+        let float_registers = (0..32)
+            .filter(|i| self.rv_f.get(*i).to_bits() != float_registers[*i].to_bits())
+            .map(|i| (i as u8, float_registers[i]))
+            .collect();
+
+        let step = Step {
+            pc,
+            retired,
+            registers,
+            float_registers,
+            fcsr,
+            csr,
+            calls,
+            writes: self.base.bus().take_writes(),
+            watch: matches!(result, TickResult::Watch(..)),
+        };
+        if let Some(history) = &mut self.history {
+            history.push(step);
+        }
+
+        result
+    }
+
+    /// Undoes the last instruction, returns it or None if the history is empty
+    pub fn step_back(&mut self) -> Option<Step> {
+        let step = self.history.as_mut()?.pop()?;
+
+        self.base.set_pc(step.pc);
+        self.retired = step.retired;
+        for (i, value) in &step.registers {
+            self.base.set(*i as usize, *value);
+        }
+        for (i, value) in &step.float_registers {
+            self.rv_f.set(*i as usize, *value);
+        }
+        self.rv_f.set_fcsr(step.fcsr);
+        if let Some((csr, value)) = step.csr {
+            self.zicsr.set(csr, value);
+        }
+        if let Some(calls) = &step.calls {
+            self.calls.clone_from(calls);
+        }
+        self.base.bus().undo_writes(&step.writes);
+
+        Some(step)
+    }
+
+    fn execute(&mut self) -> TickResult {
+        let pc = *self.base.pc() as u32;
+        if self.isa.zicsr {
+            self.zicsr.set_counters(self.retired);
+        }
+
+        // drop what was read between ticks, to show memory or disassemble
+        self.base.bus().take_accesses();
+        let instruction = self.base.fetch() as u32;
+        self.base.bus().take_watch_hit();
+
+        // RV_I
+        let mut result = self.base.execute(instruction);
+
+        // RV_M
+        if self.isa.m && matches!(result, EResult::NotFound) {
+            result = self.rv_m.execute(instruction, &mut self.base);
+        }
+
+        // RV_F
+        if self.isa.f && matches!(result, EResult::NotFound) {
+            result = self.rv_f.execute(instruction, &mut self.base);
+        }
+
+        // Zicsr
+        if self.isa.zicsr && matches!(result, EResult::NotFound) {
+            result = if self.isa.f {
+                self.zicsr
+                    .execute_with_float(instruction, &mut self.base, &mut self.rv_f)
+            } else {
+                self.zicsr.execute(instruction, &mut self.base)
+            };
+        }
+
+        // Execution Environment
+        // a syscall reads and writes the guest's buffers on its behalf, so those accesses count
+        // as the ecall's, polling `tohost` afterwards doesn't
+        let trap = match result {
+            EResult::ECall => Some(self.syscall()),
+            EResult::EBreak => Some(TickResult::EBreak),
+            EResult::NotFound => Some(TickResult::Illegal(instruction)),
+            EResult::Found => None,
+        };
+        let watch_hit = self.base.bus().take_watch_hit();
+        self.record_accesses(pc);
+        if let Some(trap) = trap {
+            return match (trap, watch_hit) {
+                // a completed `read` or `write` stops on the buffer it touched
+                (TickResult::Nothing, Some(hit)) => TickResult::Watch(pc, hit),
+                (trap, _) => trap,
+            };
+        }
+
+        self.retired += 1;
+        self.track_calls(pc, instruction);
+        self.poll_exit()
+            .or(watch_hit.map(|hit| TickResult::Watch(pc, hit)))
+            .unwrap_or(TickResult::Nothing)
+    }
+
+    /// Sends the accesses the instruction at `pc` made to the trace and the caches
+    fn record_accesses(&mut self, pc: u32) {
+        for access in self.base.bus().take_accesses() {
+            if let Some(trace) = &mut self.trace {
+                if access.kind != AccessKind::Fetch || self.trace_fetches {
+                    // losing the trace shouldn't stop the run, the error is reported at the end
+                    if let Err(error) = trace.write(&TraceRecord::new(pc, access)) {
+                        self.trace_error.get_or_insert(error);
+                    }
+                }
+            }
+            if let Some(caches) = &mut self.caches {
+                caches.access(&access);
+            }
+        }
+    }
+
+    /// Writes out the buffered trace, returns the first error writing it
+    pub fn flush_trace(&mut self) -> io::Result<()> {
+        if let Some(error) = self.trace_error.take() {
+            return Err(error);
+        }
+
+        match &mut self.trace {
+            Some(trace) => trace.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Updates the shadow call stack after the instruction at `pc` ran
+    fn track_calls(&mut self, pc: u32, ins: u32) {
+        // `ra` and `t0` are the link registers in the calling convention
+        let link = |register: u8| register == 1 || register == 5;
+        let target = *self.base.pc() as u32;
+
+        let (rd, rs1) = match ins & OPCODE_MASK {
+            OPCODE_JAL => (TypeJal::decode(ins).rd, 0),
+            OPCODE_JALR => {
+                let data = TypeJalR::decode(ins);
+                (data.rd, data.rs1)
+            }
+            _ => return,
+        };
+
+        if link(rd) {
+            self.calls.push(CallFrame {
+                return_addr: pc.wrapping_add(4),
+                function: target,
+            });
+        } else if rd == 0 && link(rs1) {
+            // a return may skip frames, like `longjmp`
+            match self.calls.iter().rposition(|f| f.return_addr == target) {
+                Some(i) => self.calls.truncate(i),
+                None => {
+                    self.calls.pop();
+                }
+            }
+        }
+    }
+
+    fn syscall(&mut self) -> TickResult {
+        let (fd, buffer, len) = (
+            self.base.get(10),
+            self.base.get(11) as u32 as usize,
+            self.base.get(12) as u32 as usize,
+        );
+
+        match self.base.get(17) {
+            SYS_EXIT | SYS_EXIT_GROUP => {
+                self.retired += 1;
+                return TickResult::Exit(ExitSource::Syscall, fd as u32);
+            }
+            SYS_WRITE if fd == 1 || fd == 2 => {
+                let bus = self.base.bus_ref();
+                // a garbage length can't write more than there is memory, like a short write
+                let len = len.min(bus.dram.size());
+                self.output
+                    .extend((0..len).map(|i| bus.load(buffer.wrapping_add(i), 8) as u8));
+                self.base.set(10, len as i32);
+            }
+            SYS_READ if fd == 0 => {
+                if self.input.is_empty() && !self.input_closed && len > 0 {
+                    // run the `ecall` again once there's input
+                    let pc = self.base.pc().wrapping_sub(4);
+                    self.base.set_pc(pc);
+                    self.waiting = true;
+                    return TickResult::WaitInput;
+                }
+
+                self.waiting = false;
+                let count = len.min(self.input.len());
+                for (i, byte) in self.input.drain(..count).enumerate() {
+                    self.base
+                        .bus()
+                        .store(buffer.wrapping_add(i), 8, byte as u32);
+                }
+                self.base.set(10, count as i32);
+            }
+            SYS_READ | SYS_WRITE => self.base.set(10, EBADF),
+            _ => return TickResult::ECall,
+        }
+
+        self.retired += 1;
+        TickResult::Nothing
+    }
+
+    /// Checks the HTIF `tohost` word and the test finisher for an exit request
+    fn poll_exit(&mut self) -> Option<TickResult> {
+        if let Some(tohost) = self.tohost {
+            let bus = self.base.bus();
+            let value = bus.load(tohost as usize, 32);
+            if value != 0 {
+                // acknowledge the command, only exits are supported
+                bus.store(tohost as usize, 32, 0);
+                if value & 1 == 1 {
+                    return Some(TickResult::Exit(ExitSource::Htif, value >> 1));
+                }
+            }
+        }
+
+        let finisher = self.base.bus().device_mut::<TestFinisher>()?;
+        let code = finisher.take_exit()?;
+        Some(TickResult::Exit(ExitSource::Finisher, code))
+    }
+}
+
+pub enum TickResult {
+    Nothing,
+    /// An `ecall` that isn't a supported syscall
+    ECall,
+    /// A `read` syscall is waiting for input, it runs again on the next tick
+    WaitInput,
+    EBreak,
+    /// An instruction none of the enabled extensions implement
+    Illegal(u32),
+    /// The guest asked to exit with a code
+    Exit(ExitSource, u32),
+    /// The instruction at the pc triggered a watchpoint
+    Watch(u32, WatchHit),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitSource {
+    Syscall,
+    Htif,
+    Finisher,
+}
+
+impl Display for ExitSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExitSource::Syscall => write!(f, "exit syscall"),
+            ExitSource::Htif => write!(f, "HTIF tohost"),
+            ExitSource::Finisher => write!(f, "test finisher"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use clap::Parser;
+    use rvcore::{
+        trace::TraceFormat,
+        watch::{Access, WatchKind, Watchpoint},
+    };
+
+    use super::*;
+    use crate::{setup, Args};
+
+    #[test]
+    fn watch_read_buffer() {
+        let args = Args::try_parse_from(["rvcli"]).unwrap();
+        let program = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scripts/echo.s");
+        let (mut emulator, symbols) = setup(&args, args.isa, Some(&program)).unwrap();
+        let buffer = symbols.get("buffer").unwrap().addr as usize;
+
+        emulator.base.bus().add_watchpoint(Watchpoint {
+            addr: buffer + 2,
+            len: 1,
+            kind: WatchKind::Write,
+        });
+        emulator.push_input(b"hey\n");
+
+        let (pc, hit) = loop {
+            match emulator.tick() {
+                TickResult::Watch(pc, hit) => break (pc, hit),
+                TickResult::Nothing => (),
+                _ => panic!("the read didn't stop on the buffer"),
+            }
+        };
+
+        // the ecall stops after it completed, with the byte `read` stored
+        let ecall = pc as usize;
+        assert_eq!(emulator.base.bus_ref().fetch(ecall), 0x0000_0073);
+        assert_eq!(*emulator.base.pc() as u32, pc + 4);
+        assert_eq!(emulator.base.get(10), 4);
+        assert_eq!(hit.access, Access::Write);
+        assert_eq!((hit.addr, hit.new), (buffer + 2, b'y' as u32));
+
+        // the hit doesn't linger into the next instruction
+        assert!(matches!(emulator.tick(), TickResult::Nothing));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn trace_write_errors() {
+        let args = Args::try_parse_from(["rvcli"]).unwrap();
+        let program = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scripts/sum.s");
+        let (mut emulator, _) = setup(&args, args.isa, Some(&program)).unwrap();
+
+        // every write to /dev/full fails once the buffer fills up
+        let file = File::create("/dev/full").unwrap();
+        emulator.trace = Some(TraceWriter::new(BufWriter::new(file), TraceFormat::Text).unwrap());
+        emulator.trace_fetches = true;
+        emulator.base.bus().trace_accesses(true, true);
+        emulator.base.set(10, 1000);
+
+        while !matches!(emulator.tick(), TickResult::Exit(..)) {}
+        assert!(emulator.trace_error.is_some());
+        assert!(emulator.flush_trace().is_err());
+        // the error is only reported once
+        assert!(emulator.trace_error.is_none());
+    }
+
+    #[test]
+    fn write_length_is_clamped() {
+        let args = Args::try_parse_from(["rvcli", "--memory", "256"]).unwrap();
+        let (mut emulator, _) = setup(&args, args.isa, None).unwrap();
+        emulator.base.bus().store(0, 32, 0x0000_0073);
+        for (register, value) in [(10, 1), (11, 0), (12, -1), (17, SYS_WRITE)] {
+            emulator.base.set(register, value);
+        }
+
+        assert!(matches!(emulator.tick(), TickResult::Nothing));
+        assert_eq!(emulator.take_output().len(), 256);
+        assert_eq!(emulator.base.get(10), 256);
+    }
+
+    #[test]
+    fn shadow_call_stack() {
+        let args = Args::try_parse_from(["rvcli"]).unwrap();
+        let program = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scripts/calls.s");
+        let (mut emulator, symbols) = setup(&args, args.isa, Some(&program)).unwrap();
+        let [outer, inner] = ["outer", "inner"].map(|name| symbols.get(name).unwrap().addr);
+
+        // the call stack each time inner starts
+        let mut stacks = Vec::new();
+        while !matches!(emulator.tick(), TickResult::Exit(..)) {
+            if *emulator.base.pc() as u32 == inner {
+                let frames = emulator.calls.iter();
+                stacks.push(
+                    frames
+                        .map(|f| (f.return_addr, f.function))
+                        .collect::<Vec<_>>(),
+                );
+            }
+        }
+
+        // `call` is auipc and jalr, `jal` links directly and `tail` links nothing
+        let main = (symbols.get("_start").unwrap().addr + 12, outer);
+        assert_eq!(
+            stacks,
+            [
+                vec![main, (outer + 16, inner)],
+                vec![main, (outer + 20, inner)],
+                vec![main],
+            ]
+        );
+        // inner's last return skips outer's frame
+        assert!(emulator.calls.is_empty());
+        assert_eq!(emulator.base.get(10), 3);
+    }
+
+    #[test]
+    fn branch_outcomes_from_the_condition() {
+        let args = Args::try_parse_from(["rvcli", "--memory", "256"]).unwrap();
+        let (mut emulator, _) = setup(&args, args.isa, None).unwrap();
+        emulator.predictors = Some(Predictors::new(Vec::new(), 4, 4));
+
+        // `beq x0, x0, 4` and `bne x0, x0, 4` both go on to the next instruction
+        emulator.base.bus().store(0, 32, 0x0000_0263);
+        emulator.base.bus().store(4, 32, 0x0000_1263);
+        emulator.tick();
+        emulator.tick();
+
+        let branches = &emulator.predictors.as_ref().unwrap().branches;
+        assert_eq!((branches[&0].executed, branches[&0].taken), (1, 1));
+        assert_eq!((branches[&4].executed, branches[&4].taken), (1, 0));
+    }
+}
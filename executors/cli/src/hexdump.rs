@@ -0,0 +1,217 @@
+use ratatui::prelude::*;
+
+/// Bytes shown on each row
+pub const ROW: usize = 16;
+
+/// How the bytes of a row are grouped and shown
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Interpretation {
+    Byte,
+    Half,
+    Word,
+    Float,
+}
+
+impl Interpretation {
+    pub fn next(self) -> Self {
+        match self {
+            Interpretation::Byte => Interpretation::Half,
+            Interpretation::Half => Interpretation::Word,
+            Interpretation::Word => Interpretation::Float,
+            Interpretation::Float => Interpretation::Byte,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Interpretation::Byte => "bytes",
+            Interpretation::Half => "halfwords",
+            Interpretation::Word => "words",
+            Interpretation::Float => "floats",
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Interpretation::Byte => 1,
+            Interpretation::Half => 2,
+            Interpretation::Word | Interpretation::Float => 4,
+        }
+    }
+
+    fn format(self, bytes: &[u8]) -> String {
+        match self {
+            Interpretation::Byte => format!("{:02x}", bytes[0]),
+            Interpretation::Half => format!("{:04x}", u16::from_le_bytes([bytes[0], bytes[1]])),
+            Interpretation::Word => format!("{:08x}", word(bytes)),
+            Interpretation::Float => format!("{:>11.4e}", f32::from_bits(word(bytes))),
+        }
+    }
+}
+
+fn word(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// One row of a hexdump, `style` picks the style of the cell covering `addr..addr + len`
+pub fn row(
+    addr: usize,
+    bytes: &[u8],
+    interpretation: Interpretation,
+    style: impl Fn(usize, usize) -> Style,
+) -> Line<'static> {
+    let size = interpretation.size();
+    let mut spans = vec![Span::raw(format!("{:08x}  ", addr))];
+
+    for (i, cell) in bytes.chunks_exact(size).enumerate() {
+        let offset = i * size;
+        spans.push(Span::styled(
+            interpretation.format(cell),
+            style(addr + offset, size),
+        ));
+
+        // an extra gap between the two halves of the row
+        let gap = if offset + size == ROW / 2 { "  " } else { " " };
+        spans.push(Span::raw(gap));
+    }
+
+    let ascii: String = bytes
+        .iter()
+        .map(|b| match b {
+            0x20..=0x7e => *b as char,
+            _ => '.',
+        })
+        .collect();
+    spans.push(Span::raw(format!(" |{}|", ascii)));
+
+    Line::from(spans)
+}
+
+/// Parses a search pattern, either a quoted string or hex bytes like `de ad be ef`
+///
+/// Strings may use the escapes `\n`, `\t`, `\r`, `\0`, `\\`, `\'`, `\"` and `\x7f`, which covers
+/// everything [`slice::escape_ascii`] produces.
+pub fn parse_pattern(text: &str) -> Result<Vec<u8>, String> {
+    let text = text.trim();
+    if let Some(string) = text
+        .strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+    {
+        return unescape(string);
+    }
+
+    // `0xdead 0xbeef` as well as `de ad be ef`
+    let digits: String = text
+        .split_whitespace()
+        .map(|token| {
+            token
+                .strip_prefix("0x")
+                .or(token.strip_prefix("0X"))
+                .unwrap_or(token)
+        })
+        .collect();
+    if digits.is_empty() || !digits.is_ascii() || !digits.len().is_multiple_of(2) {
+        return Err(format!("expected `\"text\"` or hex bytes, got `{}`", text));
+    }
+
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| {
+            let pair = &digits[i..i + 2];
+            // `from_str_radix` would take a sign, like `+f`
+            u8::from_str_radix(pair, 16)
+                .ok()
+                .filter(|_| pair.bytes().all(|b| b.is_ascii_hexdigit()))
+                .ok_or(format!("invalid hex byte `{}`", pair))
+        })
+        .collect()
+}
+
+fn unescape(string: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let mut chars = string.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+
+        bytes.push(match chars.next() {
+            Some('n') => b'\n',
+            Some('t') => b'\t',
+            Some('r') => b'\r',
+            Some('0') => 0,
+            Some('\\') => b'\\',
+            Some('"') => b'"',
+            Some('\'') => b'\'',
+            Some('x') => {
+                let digits: String = chars.by_ref().take(2).collect();
+                u8::from_str_radix(&digits, 16)
+                    .ok()
+                    .filter(|_| digits.len() == 2)
+                    .ok_or(format!("invalid escape `\\x{}`", digits))?
+            }
+            Some(c) => return Err(format!("unknown escape `\\{}`", c)),
+            None => return Err("unfinished escape at the end of the string".into()),
+        });
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_patterns() {
+        let deadbeef = Ok(vec![0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(parse_pattern("de ad be ef"), deadbeef);
+        assert_eq!(parse_pattern("  DEADBEEF "), deadbeef);
+        assert_eq!(parse_pattern("0xdeadbeef"), deadbeef);
+        // every token may have its own prefix
+        assert_eq!(parse_pattern("0xdead 0XBEEF"), deadbeef);
+        assert_eq!(parse_pattern("0xde ad 0xbe ef"), deadbeef);
+        assert_eq!(parse_pattern("00"), Ok(vec![0]));
+
+        // the digits are joined before they're split into bytes
+        assert_eq!(parse_pattern("0xa b"), Ok(vec![0xab]));
+        assert!(parse_pattern("abc").is_err());
+        assert!(parse_pattern("0xa bc").is_err());
+        assert!(parse_pattern("").is_err());
+        assert!(parse_pattern("0x").is_err());
+        assert!(parse_pattern("de0xad").is_err());
+        assert!(parse_pattern("zz").is_err());
+        assert!(parse_pattern("+f").is_err());
+        assert!(parse_pattern("éé").is_err());
+    }
+
+    #[test]
+    fn string_patterns() {
+        assert_eq!(parse_pattern("\"hello\""), Ok(b"hello".to_vec()));
+        assert_eq!(parse_pattern(" \"two words\" "), Ok(b"two words".to_vec()));
+        assert_eq!(parse_pattern("\"\""), Ok(Vec::new()));
+        assert_eq!(parse_pattern("\"é\""), Ok("é".as_bytes().to_vec()));
+        // an unterminated string is read as hex
+        assert!(parse_pattern("\"hello").is_err());
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            parse_pattern(r#""a\n\t\r\0\\\"\'\x7f\xFF""#),
+            Ok(b"a\n\t\r\0\\\"'\x7f\xff".to_vec())
+        );
+
+        // everything `escape_ascii` writes reads back
+        let bytes: Vec<u8> = (0..=255).collect();
+        let escaped = format!("\"{}\"", bytes.escape_ascii());
+        assert_eq!(parse_pattern(&escaped), Ok(bytes));
+
+        assert!(parse_pattern(r#""\q""#).is_err());
+        assert!(parse_pattern(r#""\x7""#).is_err());
+        assert!(parse_pattern(r#""\xzz""#).is_err());
+        assert!(parse_pattern(r#""trailing\""#).is_err());
+    }
+}
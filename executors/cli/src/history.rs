@@ -0,0 +1,53 @@
+use std::collections::VecDeque;
+
+use rvcore::bus::MemoryWrite;
+
+use crate::emulator::CallFrame;
+
+/// What an instruction changed, enough to undo it
+pub struct Step {
+    pub pc: i32,
+    pub retired: u64,
+    /// Old values of the integer registers it wrote
+    pub registers: Vec<(u8, i32)>,
+    pub float_registers: Vec<(u8, f32)>,
+    pub fcsr: u32,
+    /// Old value of the CSR a Zicsr instruction could have written
+    pub csr: Option<(usize, u32)>,
+    /// The shadow call stack before a `jal` or `jalr`
+    pub calls: Option<Vec<CallFrame>>,
+    pub writes: Vec<MemoryWrite>,
+    /// The instruction triggered a watchpoint
+    pub watch: bool,
+}
+
+/// The most recent steps, older ones are dropped once `capacity` is reached
+pub struct History {
+    steps: VecDeque<Step>,
+    capacity: usize,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            steps: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, step: Step) {
+        if self.steps.len() == self.capacity {
+            self.steps.pop_front();
+        }
+
+        self.steps.push_back(step);
+    }
+
+    pub fn clear(&mut self) {
+        self.steps.clear();
+    }
+
+    pub fn pop(&mut self) -> Option<Step> {
+        self.steps.pop_back()
+    }
+}
@@ -1,2 +0,0 @@
-mod decode;
-mod encode;
@@ -1,45 +1,358 @@
-mod instruction;
+mod breakpoints;
+mod coverage;
+mod csr;
+mod emulator;
+mod hexdump;
+mod history;
+mod profile;
+mod repl;
+mod replay;
+mod script;
+mod stats;
 mod ui;
 
-use rv_f::RV32F;
-use rv_m::RV32M;
-use std::error::Error;
+use clap::{Parser, Subcommand};
+use coverage::Coverage;
+use emulator::{Emulator, TickResult};
+use profile::Profiler;
+use stats::Stats;
+use std::{
+    error::Error,
+    fs::File,
+    io::{BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{Duration, Instant},
+};
 use ui::UserInterface;
 
 use rv32i::RV32I;
+use rvasm::Assembler;
 use rvcore::{
-    bus::{Bus, DRAM_ADDR},
-    Base, DRam, EResult, Extension,
+    bus::Bus,
+    cache::{CacheConfig, CacheHierarchy},
+    devices::{TestFinisher, Uart},
+    elf::{Elf, LineTable, SymbolTable},
+    isa::Isa,
+    loader::{Format, Image},
+    predictor::{PredictorConfig, Predictors},
+    snapshot::Snapshot,
+    trace::{TraceFormat, TraceWriter},
+    util::parse_u32,
+    DRam, Volatile,
 };
 
-fn main() -> Result<(), Box<dyn Error>> {
+/// Runs a RISC-V program in a terminal debugger or headless
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// ELF, Intel HEX, S-record, raw binary or assembly source to run, a small demo program is
+    /// used otherwise
+    program: Option<PathBuf>,
+
+    /// Format of the program, detected from its contents and extension by default
+    #[arg(short, long)]
+    format: Option<Format>,
+
+    /// Memory size in bytes, `K` and `M` suffixes are allowed
+    #[arg(short, long, default_value = "1M", value_parser = size)]
+    memory: usize,
+
+    /// Address memory starts at, raw binaries are loaded here
+    #[arg(short, long, default_value = "0", value_parser = address)]
+    base: u32,
+
+    /// Initial pc, defaults to the program's entry point
+    #[arg(short, long, value_parser = address)]
+    entry: Option<u32>,
+
+    /// Enabled extensions
+    #[arg(long, default_value = "rv32imf_zicsr")]
+    isa: Isa,
+
+    /// Start from a snapshot saved in the TUI, after loading the program for its symbols
+    #[arg(long, value_name = "FILE")]
+    snapshot: Option<PathBuf>,
+
+    /// Log the guest's input to a file, to reproduce the run with `--replay`
+    #[arg(long, value_name = "FILE")]
+    record: Option<PathBuf>,
+
+    /// Feed the guest the input logged by `--record` at the same instructions, live input is
+    /// ignored until the log runs out
+    #[arg(long, value_name = "FILE")]
+    replay: Option<PathBuf>,
+
+    /// Write a profile of where the guest spent its instructions to a file, as folded stacks
+    /// for flamegraph tools
+    #[arg(long, value_name = "FILE")]
+    profile: Option<PathBuf>,
+
+    /// Profile one in this many instructions instead of every one
+    #[arg(long, value_name = "COUNT", default_value = "1")]
+    profile_interval: u64,
+
+    /// Write every load and store the guest makes to a file, with its pc, address, size and value
+    #[arg(long, value_name = "FILE")]
+    trace: Option<PathBuf>,
+
+    /// Format of `--trace`, `text` or a compact `binary`
+    #[arg(long, value_name = "FORMAT", default_value = "text")]
+    trace_format: TraceFormat,
+
+    /// Trace instruction fetches too
+    #[arg(long, requires = "trace")]
+    trace_fetches: bool,
+
+    /// Simulate split L1 instruction and data caches, see `--l1i` for their defaults
+    #[arg(long)]
+    cache: bool,
+
+    /// L1 instruction cache, as `key=value` pairs that replace the defaults of
+    /// `size=16K,ways=4,line=32,replace=lru,write=back,latency=1`. Replacement is `lru`, `fifo`
+    /// or `random`, writes are `back` or `through`
+    #[arg(long, value_name = "CONFIG")]
+    l1i: Option<CacheConfig>,
+
+    /// L1 data cache, configured like `--l1i`
+    #[arg(long, value_name = "CONFIG")]
+    l1d: Option<CacheConfig>,
+
+    /// Add a unified L2 cache behind the L1 caches, configured like `--l1i`
+    #[arg(long, value_name = "CONFIG")]
+    l2: Option<CacheConfig>,
+
+    /// Cycles an access takes once it misses every cache
+    #[arg(long, value_name = "CYCLES", default_value = "100")]
+    memory_latency: u32,
+
+    /// Simulate branch predictors side by side, from `not-taken`, `bimodal:BITS` and
+    /// `gshare:BITS`, and report how often each mispredicted after a headless run
+    #[arg(long, value_name = "LIST", value_delimiter = ',')]
+    predictors: Vec<PredictorConfig>,
+
+    /// Index bits of the branch target buffer that predicts jumps
+    #[arg(long, value_name = "BITS", default_value = "9", value_parser = clap::value_parser!(u32).range(1..=24))]
+    btb_bits: u32,
+
+    /// Entries of the return address stack that predicts returns
+    #[arg(long, value_name = "DEPTH", default_value = "8")]
+    ras_depth: usize,
+
+    /// Write the source lines and branches the guest executed to an lcov tracefile, the
+    /// program has to be an ELF file with DWARF line tables
+    #[arg(long, value_name = "FILE")]
+    coverage: Option<PathBuf>,
+
+    /// Initial register value, like `a0=5` or `x10=0x10`, may be repeated
+    #[arg(short, long = "reg", value_name = "REG=VALUE", value_parser = register)]
+    registers: Vec<(u8, i32)>,
+
+    /// Run without the TUI until the guest exits, traps or hits a limit
+    ///
+    /// The guest's exit code becomes the exit status, traps exit with 125 and limits with 124.
+    #[arg(long)]
+    headless: bool,
+
+    /// Read gdb-like debugger commands from stdin instead of showing the TUI, see `help`
+    #[arg(long, conflicts_with = "headless")]
+    repl: bool,
+
+    /// Stop after this many instructions
+    #[arg(long, value_name = "COUNT")]
+    max_instructions: Option<u64>,
+
+    /// Stop after this many seconds of wall-clock time
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<f64>,
+
+    /// Address of the HTIF `tohost` word, defaults to the `tohost` symbol
+    #[arg(long, value_parser = address)]
+    tohost: Option<u32>,
+
+    /// Attach a SiFive test finisher at this address, QEMU's `virt` machine uses 0x100000
+    #[arg(long, value_parser = address)]
+    finisher: Option<u32>,
+
+    /// Attach a 16550 UART at this address, QEMU's `virt` machine uses 0x10000000
+    #[arg(long, value_parser = address)]
+    uart: Option<u32>,
+
+    /// Print the registers after a headless run
+    #[arg(long)]
+    dump: bool,
+
+    /// Print the instruction mix, branch outcomes, syscalls and traps after a headless run
+    #[arg(long)]
+    stats: bool,
+
+    /// Instructions the TUI keeps to step back through, 0 disables stepping back
+    #[arg(long, value_name = "COUNT", default_value = "100000")]
+    history: usize,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run test scripts and report which pass
+    ///
+    /// Directories are searched for `.rvtest` files. The other options set up each test's
+    /// machine. Scripts run a statement per line:
+    ///
+    ///   # a comment
+    ///   program sum.s            ELF, HEX, S-record, binary or assembly, next to the script
+    ///   isa rv32im               extensions, `--isa` by default
+    ///   limit 1000               instructions a `run` may take, 10000000 by default
+    ///   set a0 = 5               write a register, `pc` or a memory word like `*buffer`
+    ///   input "1 2\n"            bytes for the guest to read, as a string or hex bytes
+    ///   run                      run until the guest exits
+    ///   run until done           run until the pc reaches a location
+    ///   run 100                  run 100 instructions
+    ///   expect a0 == 15          check a register, `pc` or a memory word like `*buffer+4`
+    ///   expect mem buffer == "hi"
+    ///   expect output == "15\n"
+    ///   expect exit == 0
+    ///
+    /// Locations are symbols, addresses or registers, plus or minus an offset.
+    #[command(verbatim_doc_comment)]
+    Test {
+        /// Scripts, or directories of them
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+}
+
+/// Exit status of a headless run that hit a limit, like `timeout(1)`
+const EXIT_LIMIT: u8 = 124;
+/// Exit status of a headless run that stopped on a trap
+const EXIT_TRAP: u8 = 125;
+
+fn address(text: &str) -> Result<u32, String> {
+    parse_u32(text).ok_or(format!("invalid address `{}`", text))
+}
+
+fn size(text: &str) -> Result<usize, String> {
+    let (number, unit) = match text.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&text[..i], 1024),
+        Some((i, 'm' | 'M')) => (&text[..i], 1024 * 1024),
+        _ => (text, 1),
+    };
+
+    match parse_u32(number).and_then(|n| (n as usize).checked_mul(unit)) {
+        Some(size) if size > 0 && size.is_multiple_of(4) => Ok(size),
+        _ => Err(format!(
+            "invalid memory size `{}`, expected a multiple of 4",
+            text
+        )),
+    }
+}
+
+fn register(text: &str) -> Result<(u8, i32), String> {
+    let (name, value) = text
+        .split_once('=')
+        .ok_or(format!("expected `REG=VALUE`, got `{}`", text))?;
+    let index = rvasm::xreg(name.trim()).ok_or(format!("unknown register `{}`", name))?;
+
+    let value = value.trim();
+    let value = match value.strip_prefix('-') {
+        Some(magnitude) => parse_u32(magnitude).map(|v| (v as i32).wrapping_neg()),
+        None => parse_u32(value).map(|v| v as i32),
+    };
+
+    value
+        .map(|value| (index, value))
+        .ok_or(format!("invalid value in `{}`", text))
+}
+
+fn main() -> Result<ExitCode, Box<dyn Error>> {
     env_logger::init();
+    let args = Args::parse();
 
-    // ---- Setup Emulator ----
-    let bus = Bus::new(DRam::new(1024 * 1024));
-    let mut rv_base = RV32I::new(bus);
+    if let Some(Command::Test { paths }) = &args.command {
+        return Ok(script::run(paths, &args));
+    }
 
-    let mut rv_m = rv_m::RV32M;
-    let mut rv_f = rv_f::RV32F::default();
+    let (mut emulator, symbols) = setup(&args, args.isa, args.program.as_deref())?;
+    if let Some(path) = &args.replay {
+        emulator.replay = replay::load(path)
+            .map_err(|error| format!("failed to read `{}`: {}", path.display(), error))?;
+    }
+    if let Some(path) = &args.record {
+        emulator.recorder = Some(replay::Recorder::create(path)?);
+    }
 
-    {
-        let bus = rv_base.bus();
-        bus.store(DRAM_ADDR, 32, 0x00130293u32); // addi x5, x6, 1
-        bus.store(4 + DRAM_ADDR, 32, 0x00128313u32); // addi x6, x5, 1
-        bus.store(8 + DRAM_ADDR, 32, 0x00100073u32); // ebreak
-        bus.store(12 + DRAM_ADDR, 32, 0xff5ff0efu32); // jal x0, -12
+    if args.profile.is_some() {
+        emulator.profiler = Some(Profiler::new(args.profile_interval));
+    }
+    if args.stats {
+        emulator.stats = Some(Stats::default());
+    }
+    if args.coverage.is_some() {
+        emulator.coverage = Some(Coverage::new(line_table(&args)?));
+    }
+    if let Some(path) = &args.trace {
+        let file = BufWriter::new(File::create(path)?);
+        emulator.trace = Some(TraceWriter::new(file, args.trace_format)?);
+        emulator.trace_fetches = args.trace_fetches;
+    }
+    if args.cache || args.l1i.is_some() || args.l1d.is_some() || args.l2.is_some() {
+        emulator.caches = Some(CacheHierarchy::new(
+            args.l1i.unwrap_or_default(),
+            args.l1d.unwrap_or_default(),
+            args.l2,
+            args.memory_latency,
+        ));
+    }
+    if !args.predictors.is_empty() {
+        let configs = args.predictors.clone();
+        emulator.predictors = Some(Predictors::new(configs, args.btb_bits, args.ras_depth));
+    }
+    if emulator.trace.is_some() || emulator.caches.is_some() {
+        let fetches = args.trace_fetches || emulator.caches.is_some();
+        emulator.base.bus().trace_accesses(true, fetches);
+    }
+
+    if args.headless {
+        let status = headless(&mut emulator, &args, &symbols);
+        write_reports(&mut emulator, &args, &symbols)?;
+        return Ok(status);
+    }
+    if args.repl {
+        let mut repl = repl::Repl::new(&mut emulator, symbols, args.base);
+        repl.run()?;
+        let symbols = repl.into_symbols();
+        write_reports(&mut emulator, &args, &symbols)?;
+        return Ok(ExitCode::SUCCESS);
     }
 
     // ---- Setup Ratatui ----
-    let mut interface = UserInterface::init()?;
+    emulator.record_history(args.history);
+    // the TUI always shows a profile and statistics
+    emulator.profiler = Some(Profiler::new(args.profile_interval));
+    emulator.stats = Some(Stats::default());
+    let mut interface = UserInterface::init(symbols.clone())?;
 
     loop {
-        interface.render(&rv_base)?;
+        interface.render(&emulator)?;
 
-        match interface.event(&mut rv_base)? {
+        match interface.event(&mut emulator)? {
             ui::UIEvent::Nothing => (),
             ui::UIEvent::Tick => {
-                interface.tick_event(tick(&mut rv_base, &mut rv_m, &mut rv_f));
+                // running to a target only draws between batches
+                for _ in 0..interface.batch() {
+                    if interface.check_breakpoints(&emulator.base) {
+                        break;
+                    }
+
+                    let result = emulator.tick();
+                    interface.tick_event(result, &emulator);
+                    interface.write_console(&emulator.take_output());
+                    if !interface.running() {
+                        break;
+                    }
+                }
             }
             ui::UIEvent::Exit => {
                 drop(interface);
@@ -48,65 +361,220 @@ fn main() -> Result<(), Box<dyn Error>> {
         }
     }
 
+    write_reports(&mut emulator, &args, &symbols)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Writes the folded stacks to `--profile` and the tracefile to `--coverage`, if given, and
+/// flushes `--trace`
+fn write_reports(
+    emulator: &mut Emulator,
+    args: &Args,
+    symbols: &SymbolTable,
+) -> Result<(), Box<dyn Error>> {
+    if let (Some(path), Some(profiler)) = (&args.profile, &emulator.profiler) {
+        std::fs::write(path, profiler.folded(symbols))?;
+    }
+    if let (Some(path), Some(coverage)) = (&args.coverage, &emulator.coverage) {
+        std::fs::write(path, coverage.lcov(symbols, emulator.base.bus_ref()))?;
+    }
+    emulator
+        .flush_trace()
+        .map_err(|error| format!("failed to write the trace: {}", error))?;
+
     Ok(())
 }
 
-fn tick(base: &mut RV32I, rv_m: &mut RV32M, rv_f: &mut RV32F) -> TickResult {
-    //println!("---- Debug ----\n{:?}\n---- Debug ----", base);
+/// Reads the DWARF line tables of the program, for `--coverage`
+fn line_table(args: &Args) -> Result<LineTable, Box<dyn Error>> {
+    let path = args
+        .program
+        .as_deref()
+        .ok_or("--coverage needs a program")?;
+    let bytes = std::fs::read(path)?;
+    if !Elf::is_elf(&bytes) {
+        return Err(format!(
+            "--coverage needs an ELF file, `{}` isn't one",
+            path.display()
+        )
+        .into());
+    }
 
-    //println!("fetch");
-    let instruction = base.fetch() as u32;
+    let lines = Elf::parse(&bytes)?.lines()?;
+    if lines.is_empty() {
+        return Err(format!(
+            "`{}` has no line tables, build it with debug info (`-g`)",
+            path.display()
+        )
+        .into());
+    }
+    Ok(lines)
+}
 
-    //println!("execute");
+/// Builds the machine the options describe with the `isa` extensions, then loads `program`
+fn setup(
+    args: &Args,
+    isa: Isa,
+    program: Option<&Path>,
+) -> Result<(Emulator, SymbolTable), Box<dyn Error>> {
+    let mut bus = Bus::with_base(DRam::new(args.memory), args.base as usize);
+    if let Some(addr) = args.finisher {
+        bus.attach(addr as usize, Box::<TestFinisher>::default());
+    }
+    if let Some(addr) = args.uart {
+        bus.attach(addr as usize, Box::<Uart>::default());
+    }
+    let mut emulator = Emulator::new(RV32I::new(bus), isa);
 
-    let (mut ecall, mut ebreak) = (false, false);
+    let mut symbols = SymbolTable::default();
+    let mut entry = args.base;
+    if let Some(path) = program {
+        let image = match path.extension() {
+            Some(extension) if args.format.is_none() && (extension == "s" || extension == "S") => {
+                assemble(path, args.base)?
+            }
+            _ => {
+                let bytes = std::fs::read(path)?;
+                let format = args
+                    .format
+                    .unwrap_or_else(|| Format::detect(Some(path), &bytes));
+                Image::parse(&bytes, format, args.base)?
+            }
+        };
+        image.load(emulator.base.bus())?;
+        entry = image.entry;
+        symbols = image.symbols;
+    } else {
+        let bus = emulator.base.bus();
+        let base = args.base as usize;
+        bus.store(base, 32, 0x00130293u32); // addi x5, x6, 1
+        bus.store(4 + base, 32, 0x00128313u32); // addi x6, x5, 1
+        bus.store(8 + base, 32, 0x00100073u32); // ebreak
+        bus.store(12 + base, 32, 0xff5ff0efu32); // jal x0, -12
+    }
 
-    // RV_I
-    if !(ecall || ebreak) {
-        let result = base.execute(instruction);
-        match result {
-            EResult::ECall => ecall = true,
-            EResult::EBreak => ebreak = true,
-            EResult::Found => return TickResult::Nothing,
-            EResult::NotFound => (),
-        }
+    emulator.base.set_pc(args.entry.unwrap_or(entry) as i32);
+    emulator.tohost = args.tohost.or(symbols.get("tohost").map(|s| s.addr));
+    if let Some(path) = &args.snapshot {
+        let snapshot = Snapshot::from_bytes(&std::fs::read(path)?, args.memory)?;
+        emulator.restore(&snapshot)?;
+    }
+    for (index, value) in &args.registers {
+        emulator.base.set(*index as usize, *value);
     }
 
-    // RV_M
-    if !(ecall || ebreak) {
-        let result = rv_m.execute(instruction, base);
-        match result {
-            EResult::ECall => ecall = true,
-            EResult::EBreak => ebreak = true,
-            EResult::Found => return TickResult::Nothing,
-            EResult::NotFound => (),
+    Ok((emulator, symbols))
+}
+
+/// Assembles a source file into an image placed at `origin`
+fn assemble(path: &Path, origin: u32) -> Result<Image, Box<dyn Error>> {
+    let source = std::fs::read_to_string(path)?;
+    let assembler = Assembler::new(Isa::ALL)
+        .origin(origin)
+        .file(path.display().to_string());
+
+    let program = assembler.assemble(&source).map_err(|errors| {
+        let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        errors.join("\n")
+    })?;
+
+    Ok(Image::parse(
+        &program.to_elf().to_bytes(),
+        Format::Elf,
+        origin,
+    )?)
+}
+
+/// Runs until the guest exits, traps or hits a limit, then prints a summary
+fn headless(emulator: &mut Emulator, args: &Args, symbols: &SymbolTable) -> ExitCode {
+    let start = Instant::now();
+    let timeout = args.timeout.map(Duration::from_secs_f64);
+
+    // the pc has already moved past a trapping instruction, but not past an exit
+    let (reason, status, trapped) = loop {
+        if args
+            .max_instructions
+            .is_some_and(|max| emulator.retired >= max)
+        {
+            break ("instruction limit reached".to_string(), EXIT_LIMIT, false);
+        }
+        // checking the clock every instruction is slow
+        if emulator.retired.is_multiple_of(4096) && timeout.is_some_and(|t| start.elapsed() >= t) {
+            break ("timeout reached".to_string(), EXIT_LIMIT, false);
+        }
+
+        let result = emulator.tick();
+        let output = emulator.take_output();
+        if !output.is_empty() {
+            let mut stdout = std::io::stdout();
+            let _ = stdout.write_all(&output).and_then(|_| stdout.flush());
         }
-    }
 
-    // RV_F
-    if !(ecall || ebreak) {
-        let result = rv_f.execute(instruction, base);
         match result {
-            EResult::ECall => ecall = true,
-            EResult::EBreak => ebreak = true,
-            EResult::Found => return TickResult::Nothing,
-            EResult::NotFound => (),
+            TickResult::Nothing | TickResult::Watch(..) => (),
+            TickResult::WaitInput => {
+                let mut buffer = [0; 4096];
+                match std::io::stdin().read(&mut buffer) {
+                    Ok(0) | Err(_) => emulator.close_input(),
+                    Ok(len) => emulator.push_input(&buffer[..len]),
+                }
+            }
+            TickResult::ECall => {
+                let syscall = emulator.base.get(17);
+                break (format!("unsupported ecall {}", syscall), EXIT_TRAP, true);
+            }
+            TickResult::EBreak => break ("ebreak".to_string(), EXIT_TRAP, true),
+            TickResult::Illegal(ins) => {
+                break (
+                    format!("illegal instruction {:#010x}", ins),
+                    EXIT_TRAP,
+                    true,
+                );
+            }
+            TickResult::Exit(source, code) => {
+                // only the low byte survives as a process exit status
+                break (format!("{} with code {}", source, code), code as u8, false);
+            }
         }
+    };
+
+    let mut pc = *emulator.base.pc() as u32;
+    if trapped {
+        pc = pc.wrapping_sub(4);
+    }
+
+    eprintln!("rvcli: {}", reason);
+    eprintln!(
+        "rvcli: {} instructions retired in {:.3}s",
+        emulator.retired,
+        start.elapsed().as_secs_f64()
+    );
+    match symbols.describe(pc) {
+        Some(symbol) => eprintln!("rvcli: final pc {:#010x} <{}>", pc, symbol),
+        None => eprintln!("rvcli: final pc {:#010x}", pc),
     }
 
-    // Execution Environment
-    match (ecall, ebreak) {
-        (true, false) => TickResult::ECall,
-        (false, true) => TickResult::EBreak,
-        _ => {
-            //eprintln!("instruction not supported");
-            TickResult::Nothing
+    if args.dump {
+        for row in 0..8 {
+            let line: Vec<String> = (0..4)
+                .map(|column| {
+                    let i = row * 4 + column;
+                    format!("x{:<2} {:#010x}", i, emulator.base.get(i) as u32)
+                })
+                .collect();
+            println!("{}", line.join("  "));
         }
     }
-}
 
-pub enum TickResult {
-    Nothing,
-    ECall,
-    EBreak,
+    if let Some(stats) = &emulator.stats {
+        eprint!("\n{}", stats.report());
+    }
+    if let Some(caches) = &emulator.caches {
+        eprint!("\n{}", stats::cache_report(caches));
+    }
+    if let Some(predictors) = &emulator.predictors {
+        eprint!("\n{}", stats::predictor_report(predictors, symbols));
+    }
+
+    ExitCode::from(status)
 }
@@ -0,0 +1,235 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use rvcore::elf::SymbolTable;
+
+use crate::emulator::CallFrame;
+
+/// Name of code outside of any symbol
+const UNKNOWN: &str = "[unknown]";
+
+/// Counts retired instructions per pc and call stack
+///
+/// Every instruction is counted by default, a sampling profiler counts one in `interval`.
+pub struct Profiler {
+    interval: u64,
+
+    /// Each distinct call stack, as the return addresses of its frames
+    stacks: Vec<Vec<u32>>,
+    stack_ids: HashMap<Vec<u32>, usize>,
+    /// The stack instructions are counted in until the shadow call stack changes
+    current: usize,
+
+    /// Samples per stack and pc
+    counts: HashMap<(usize, u32), u64>,
+    pub samples: u64,
+}
+
+/// Samples of a function, on its own and including the functions it called
+pub struct FunctionProfile {
+    pub name: String,
+    pub exclusive: u64,
+    pub inclusive: u64,
+}
+
+impl Profiler {
+    pub fn new(interval: u64) -> Self {
+        Self {
+            interval: interval.max(1),
+
+            stacks: vec![Vec::new()],
+            stack_ids: HashMap::from([(Vec::new(), 0)]),
+            current: 0,
+
+            counts: HashMap::new(),
+            samples: 0,
+        }
+    }
+
+    /// Switches to the stack in `calls`, call before running an instruction
+    pub fn enter(&mut self, calls: &[CallFrame]) {
+        // a single instruction pushes or pops frames, so the depth or the top frame changes
+        let stack = &self.stacks[self.current];
+        if stack.len() == calls.len() && stack.last() == calls.last().map(|f| &f.return_addr) {
+            return;
+        }
+
+        let stack: Vec<u32> = calls.iter().map(|f| f.return_addr).collect();
+        self.current = match self.stack_ids.get(&stack) {
+            Some(id) => *id,
+            None => {
+                self.stacks.push(stack.clone());
+                self.stack_ids.insert(stack, self.stacks.len() - 1);
+                self.stacks.len() - 1
+            }
+        };
+    }
+
+    /// Counts the instruction at `pc`, after it retired as instruction number `retired`
+    pub fn retire(&mut self, pc: u32, retired: u64) {
+        if retired.is_multiple_of(self.interval) {
+            *self.counts.entry((self.current, pc)).or_default() += 1;
+            self.samples += 1;
+        }
+    }
+
+    /// Names of the functions a sample is in, outermost first
+    fn frames<'a>(&self, stack: usize, pc: u32, symbols: &'a SymbolTable) -> Vec<&'a str> {
+        let name = |addr: u32| {
+            symbols
+                .lookup(addr)
+                .map_or(UNKNOWN, |(symbol, _)| symbol.name.as_str())
+        };
+
+        // each frame was called from the instruction before its return address
+        let callers = self.stacks[stack]
+            .iter()
+            .map(|ret| name(ret.wrapping_sub(4)));
+        callers.chain([name(pc)]).collect()
+    }
+
+    /// Every function with samples, most exclusive samples first
+    pub fn functions(&self, symbols: &SymbolTable) -> Vec<FunctionProfile> {
+        let mut functions: HashMap<&str, (u64, u64)> = HashMap::new();
+        for ((stack, pc), count) in &self.counts {
+            let frames = self.frames(*stack, *pc, symbols);
+            functions.entry(frames[frames.len() - 1]).or_default().0 += count;
+
+            // recursion counts once per sample
+            let unique: HashSet<&str> = frames.into_iter().collect();
+            for name in unique {
+                functions.entry(name).or_default().1 += count;
+            }
+        }
+
+        let mut functions: Vec<FunctionProfile> = functions
+            .into_iter()
+            .map(|(name, (exclusive, inclusive))| FunctionProfile {
+                name: name.to_string(),
+                exclusive,
+                inclusive,
+            })
+            .collect();
+        functions.sort_by(|a, b| b.exclusive.cmp(&a.exclusive).then(a.name.cmp(&b.name)));
+        functions
+    }
+
+    /// Stacks in the folded format flamegraph tools read, a `main;parse;next 120` line per stack
+    pub fn folded(&self, symbols: &SymbolTable) -> String {
+        let mut folded: BTreeMap<String, u64> = BTreeMap::new();
+        for ((stack, pc), count) in &self.counts {
+            let frames = self.frames(*stack, *pc, symbols);
+            *folded.entry(frames.join(";")).or_default() += count;
+        }
+
+        folded
+            .into_iter()
+            .map(|(stack, count)| format!("{} {}\n", stack, count))
+            .collect()
+    }
+
+    /// Samples per pc
+    pub fn pcs(&self) -> BTreeMap<u32, u64> {
+        let mut pcs = BTreeMap::new();
+        for ((_, pc), count) in &self.counts {
+            *pcs.entry(*pc).or_default() += count;
+        }
+
+        pcs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rvcore::elf::{Symbol, SymbolKind};
+
+    use super::*;
+
+    fn symbols() -> SymbolTable {
+        let function = |name: &str, addr: u32| Symbol {
+            name: name.to_string(),
+            addr,
+            size: 0x10,
+            kind: SymbolKind::Func,
+            global: true,
+            section: 1,
+        };
+        SymbolTable::new(vec![function("main", 0x100), function("helper", 0x200)])
+    }
+
+    fn frame(return_addr: u32) -> CallFrame {
+        CallFrame {
+            return_addr,
+            function: 0x200,
+        }
+    }
+
+    /// main calls helper, which calls itself once, then main runs into unknown code
+    fn profile() -> Profiler {
+        let mut profiler = Profiler::new(1);
+        let mut retired = 0;
+        let mut run = |profiler: &mut Profiler, calls: &[CallFrame], pcs: &[u32]| {
+            profiler.enter(calls);
+            for pc in pcs {
+                retired += 1;
+                profiler.retire(*pc, retired);
+            }
+        };
+
+        run(&mut profiler, &[], &[0x100, 0x104, 0x108]);
+        run(&mut profiler, &[frame(0x10c)], &[0x200, 0x204, 0x208]);
+        run(
+            &mut profiler,
+            &[frame(0x10c), frame(0x20c)],
+            &[0x200, 0x204],
+        );
+        run(&mut profiler, &[frame(0x10c)], &[0x20c]);
+        run(&mut profiler, &[], &[0x10c, 0x400]);
+        profiler
+    }
+
+    #[test]
+    fn counts_per_pc() {
+        let profiler = profile();
+        assert_eq!(profiler.samples, 11);
+
+        let pcs = profiler.pcs();
+        assert_eq!(pcs.values().sum::<u64>(), 11);
+        assert_eq!(pcs[&0x200], 2);
+        assert_eq!(pcs[&0x208], 1);
+        assert_eq!(pcs[&0x400], 1);
+        assert_eq!(pcs.len(), 9);
+    }
+
+    #[test]
+    fn counts_per_symbol() {
+        let functions = profile().functions(&symbols());
+        let counts: Vec<(&str, u64, u64)> = functions
+            .iter()
+            .map(|f| (f.name.as_str(), f.exclusive, f.inclusive))
+            .collect();
+
+        // recursion counts once toward helper's inclusive samples
+        assert_eq!(
+            counts,
+            [("helper", 6, 6), ("main", 4, 10), ("[unknown]", 1, 1)]
+        );
+    }
+
+    #[test]
+    fn folded_stacks() {
+        assert_eq!(
+            profile().folded(&symbols()),
+            "[unknown] 1\nmain 4\nmain;helper 4\nmain;helper;helper 2\n"
+        );
+    }
+
+    #[test]
+    fn sampling_interval() {
+        let mut profiler = Profiler::new(3);
+        for retired in 1..=10 {
+            profiler.retire(0x100, retired);
+        }
+        assert_eq!(profiler.samples, 3);
+        assert_eq!(profiler.pcs()[&0x100], 3);
+    }
+}
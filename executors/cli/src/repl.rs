@@ -0,0 +1,650 @@
+use std::{
+    error::Error,
+    io::{BufRead, IsTerminal, Write},
+    path::Path,
+};
+
+use rv32i::RV32I;
+use rvasm::{decode_instruction, XREG_ABI};
+use rvcore::{
+    elf::SymbolTable,
+    loader::load_file,
+    util::parse_u32,
+    watch::{WatchKind, Watchpoint},
+    Volatile,
+};
+
+use crate::{
+    breakpoints::{Breakpoints, Condition},
+    emulator::{Emulator, TickResult},
+};
+
+const HELP: &str = "\
+break <loc> [if <cond>]     stop before the instruction at <loc>, like `break main if a0 == 0`
+delete [<loc>]              remove the breakpoint at <loc>, or all of them
+watch|rwatch|awatch <loc> [len]
+                            stop after a write, read or any access to <loc>
+step [n]                    run n instructions, 1 by default
+continue                    run until a breakpoint, watchpoint, trap or exit
+x/<n><f><u> <loc>           examine memory, f is x, d, u, c or i and u is b, h or w
+disas [<loc>] [n]           disassemble n instructions at <loc>, the pc by default
+info registers|breakpoints  list the registers or breakpoints
+backtrace                   show the shadow call stack
+set <reg|pc|*loc> = <value> write a register, the pc or a memory word
+input <text>                queue a line of input for the guest
+load <file>                 load a program and jump to its entry point
+quit
+
+<loc> is a symbol, an address, a register like `sp` or `pc`, optionally plus an offset.";
+
+enum Flow {
+    Continue,
+    Quit,
+}
+
+/// A gdb-like debugger reading commands from stdin, one per line
+pub struct Repl<'a> {
+    emulator: &'a mut Emulator,
+    symbols: SymbolTable,
+    breakpoints: Breakpoints,
+    /// Address raw binaries are loaded at
+    base: u32,
+    /// The guest exited, it has to be loaded again to keep running
+    exited: bool,
+}
+
+impl<'a> Repl<'a> {
+    pub fn new(emulator: &'a mut Emulator, symbols: SymbolTable, base: u32) -> Self {
+        Self {
+            emulator,
+            symbols,
+            breakpoints: Breakpoints::default(),
+            base,
+            exited: false,
+        }
+    }
+
+    /// The symbols of the last program loaded
+    pub fn into_symbols(self) -> SymbolTable {
+        self.symbols
+    }
+
+    /// Runs commands until `quit` or the end of input
+    pub fn run(&mut self) -> Result<(), Box<dyn Error>> {
+        let stdin = std::io::stdin();
+        // scripts don't need a prompt between every line of output
+        let interactive = stdin.is_terminal();
+
+        let mut lines = stdin.lock().lines();
+        loop {
+            if interactive {
+                print!("(rvcli) ");
+                std::io::stdout().flush()?;
+            }
+
+            let Some(line) = lines.next() else {
+                return Ok(());
+            };
+
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match self.command(line) {
+                Ok(Flow::Continue) => (),
+                Ok(Flow::Quit) => return Ok(()),
+                Err(error) => println!("error: {}", error),
+            }
+        }
+    }
+
+    fn command(&mut self, line: &str) -> Result<Flow, String> {
+        let (name, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+
+        // `x/16xw` keeps its format after the slash
+        if let Some(format) = name.strip_prefix("x/").or((name == "x").then_some("")) {
+            for line in self.examine(format, rest)? {
+                println!("{}", line);
+            }
+            return Ok(Flow::Continue);
+        }
+
+        match name {
+            "help" | "h" => println!("{}", HELP),
+            "quit" | "q" => return Ok(Flow::Quit),
+
+            "break" | "b" => {
+                let (location, condition) = match rest.split_once(" if ") {
+                    Some((location, condition)) => (location, Some(Condition::parse(condition)?)),
+                    None => (rest, None),
+                };
+                let addr = self.location(location)?;
+
+                if !self.breakpoints.contains(addr) {
+                    self.breakpoints.toggle(addr);
+                }
+                if let Some(breakpoint) = self.breakpoints.get_mut(addr) {
+                    breakpoint.condition = condition;
+                }
+                println!("Breakpoint at {}", self.describe(addr));
+            }
+            "delete" | "d" if rest.is_empty() => {
+                let all: Vec<u32> = self.breakpoints.iter().map(|b| b.addr).collect();
+                for addr in all {
+                    self.breakpoints.toggle(addr);
+                }
+            }
+            "delete" | "d" => {
+                let addr = self.location(rest)?;
+                if !self.breakpoints.contains(addr) {
+                    return Err(format!("no breakpoint at {}", self.describe(addr)));
+                }
+                self.breakpoints.toggle(addr);
+            }
+            "watch" | "rwatch" | "awatch" => {
+                let kind = match name {
+                    "watch" => WatchKind::Write,
+                    "rwatch" => WatchKind::Read,
+                    _ => WatchKind::Access,
+                };
+
+                let mut parts = rest.split_whitespace();
+                let addr = self.location(parts.next().ok_or("expected a location")?)?;
+                let len = match parts.next() {
+                    Some(len) => parse_u32(len).ok_or(format!("invalid length `{}`", len))?,
+                    None => 4,
+                };
+
+                let watchpoint = Watchpoint {
+                    addr: addr as usize,
+                    len: len as usize,
+                    kind,
+                };
+                self.emulator.base.bus().add_watchpoint(watchpoint);
+                println!("Watchpoint {}", watchpoint);
+            }
+
+            "step" | "s" | "stepi" | "si" => {
+                let count = match rest {
+                    "" => 1,
+                    count => parse_u32(count).ok_or(format!("invalid count `{}`", count))?,
+                };
+                self.resume(Some(count as u64))?;
+            }
+            "continue" | "c" => self.resume(None)?,
+
+            "disas" | "disassemble" => {
+                let mut parts = rest.split_whitespace();
+                let addr = match parts.next() {
+                    Some(location) => self.location(location)?,
+                    None => *self.emulator.base.pc() as u32,
+                };
+                let count = match parts.next() {
+                    Some(count) => parse_u32(count).ok_or(format!("invalid count `{}`", count))?,
+                    None => 8,
+                };
+
+                for line in self.disassemble(addr, count) {
+                    println!("{}", line);
+                }
+            }
+            "info" | "i" => match rest {
+                "registers" | "reg" | "r" => self.info_registers(),
+                "breakpoints" | "break" | "b" => self.info_breakpoints(),
+                _ => return Err("expected `info registers` or `info breakpoints`".into()),
+            },
+            "backtrace" | "bt" => {
+                let pc = *self.emulator.base.pc() as u32;
+                println!("#0  {}", self.describe(pc));
+                for (i, frame) in self.emulator.calls.iter().rev().enumerate() {
+                    println!("#{}  {}", i + 1, self.describe(frame.return_addr));
+                }
+            }
+
+            "set" => {
+                let (target, value) = rest
+                    .split_once('=')
+                    .ok_or("expected `set <target> = <value>`")?;
+                let (target, value) = (target.trim(), self.value(value.trim())?);
+
+                if target == "pc" || target == "$pc" {
+                    self.emulator.base.set_pc(value as i32);
+                } else if let Some(location) = target.strip_prefix('*') {
+                    let addr = self.location(location)?;
+                    self.emulator.base.bus().store(addr as usize, 32, value);
+                } else {
+                    let register = target.trim_start_matches('$');
+                    let index =
+                        rvasm::xreg(register).ok_or(format!("unknown register `{}`", register))?;
+                    self.emulator.base.set(index as usize, value as i32);
+                }
+            }
+            "input" => {
+                self.emulator.push_input(format!("{}\n", rest).as_bytes());
+            }
+            "load" => {
+                let image = load_file(self.emulator.base.bus(), Path::new(rest), self.base)
+                    .map_err(|error| format!("failed to load `{}`: {}", rest, error))?;
+
+                self.emulator.base.set_pc(image.entry as i32);
+                self.emulator.calls.clear();
+                self.symbols = image.symbols;
+                self.exited = false;
+                println!("Loaded {}, entry {}", rest, self.describe(image.entry));
+            }
+
+            _ => return Err(format!("unknown command `{}`, try `help`", name)),
+        }
+
+        Ok(Flow::Continue)
+    }
+
+    /// Runs `count` instructions, or until something stops execution if there's no count
+    fn resume(&mut self, count: Option<u64>) -> Result<(), String> {
+        if self.exited {
+            return Err("the program has exited, `load` it to run it again".into());
+        }
+
+        let mut executed = 0;
+        let reason = loop {
+            if count.is_some_and(|count| executed >= count) {
+                break None;
+            }
+
+            // like gdb, continuing never stops at the instruction it starts from, and
+            // stepping ignores breakpoints
+            let pc = *self.emulator.base.pc() as u32;
+            if count.is_none() && executed > 0 && self.breakpoints.hit(&self.emulator.base) {
+                break Some(format!("Breakpoint at {}", self.describe(pc)));
+            }
+
+            let result = self.emulator.tick();
+            executed += 1;
+            self.print_output();
+
+            match result {
+                TickResult::Nothing => (),
+                TickResult::WaitInput => {
+                    break Some("The guest is waiting for input, see `input`".into());
+                }
+                TickResult::ECall => break Some("Unsupported ecall".into()),
+                TickResult::EBreak => break Some(format!("ebreak at {}", self.describe(pc))),
+                TickResult::Illegal(ins) => {
+                    break Some(format!(
+                        "Illegal instruction {:#010x} at {}",
+                        ins,
+                        self.describe(pc)
+                    ));
+                }
+                TickResult::Exit(source, code) => {
+                    self.exited = true;
+                    println!("Exited with code {} ({})", code, source);
+                    return Ok(());
+                }
+                TickResult::Watch(pc, hit) => {
+                    break Some(format!(
+                        "Watchpoint {}: {} {:#x} -> {:#x} at {}",
+                        hit.watchpoint,
+                        hit.access,
+                        hit.old,
+                        hit.new,
+                        self.describe(pc)
+                    ));
+                }
+            }
+        };
+
+        if let Some(reason) = reason {
+            println!("{}", reason);
+        }
+        self.print_instruction(*self.emulator.base.pc() as u32);
+        Ok(())
+    }
+
+    fn print_output(&mut self) {
+        let output = self.emulator.take_output();
+        if !output.is_empty() {
+            let mut stdout = std::io::stdout();
+            let _ = stdout.write_all(&output).and_then(|_| stdout.flush());
+        }
+    }
+
+    // ---- Display ----
+
+    /// Like `0x00000010 <main+0x4>`
+    fn describe(&self, addr: u32) -> String {
+        match self.symbols.describe(addr) {
+            Some(symbol) => format!("{:#010x} <{}>", addr, symbol),
+            None => format!("{:#010x}", addr),
+        }
+    }
+
+    fn print_instruction(&self, addr: u32) {
+        println!("{}", self.instruction_line(addr));
+    }
+
+    /// Like `=> 0x00000010 <main+0x4>: addi a0, a0, 1`, the arrow marks the pc
+    fn instruction_line(&self, addr: u32) -> String {
+        let ins = self.emulator.base.bus_ref().fetch(addr as usize);
+        let marker = if addr == *self.emulator.base.pc() as u32 {
+            "=>"
+        } else {
+            "  "
+        };
+        format!(
+            "{} {}: {}",
+            marker,
+            self.describe(addr),
+            decode_instruction(ins)
+        )
+    }
+
+    fn disassemble(&self, addr: u32, count: u32) -> Vec<String> {
+        (0..self.clamp_count(count, 4))
+            .map(|i| self.instruction_line(addr.wrapping_add(i * 4)))
+            .collect()
+    }
+
+    fn info_registers(&self) {
+        let base = &self.emulator.base;
+        for (i, name) in XREG_ABI.iter().enumerate() {
+            let value = base.get(i);
+            println!("{:<5} x{:<3} {:#010x}  {}", name, i, value as u32, value);
+        }
+
+        let pc = *base.pc() as u32;
+        println!("{:<10} {}", "pc", self.describe(pc));
+    }
+
+    fn info_breakpoints(&self) {
+        for breakpoint in self.breakpoints.iter() {
+            let mut line = format!(
+                "{}  hits: {}",
+                self.describe(breakpoint.addr),
+                breakpoint.hits
+            );
+            if let Some(condition) = &breakpoint.condition {
+                line = format!("{}  if {}", line, condition);
+            }
+            println!("{}", line);
+        }
+
+        for watchpoint in self.emulator.base.bus_ref().watchpoints() {
+            println!("watchpoint {}", watchpoint);
+        }
+    }
+
+    /// Limits a count of `size`-byte units to what fits in memory, so a typo can't print gigabytes
+    fn clamp_count(&self, count: u32, size: u32) -> u32 {
+        let units = self.emulator.base.bus_ref().dram.size() / size as usize;
+        count.min(units.max(1) as u32)
+    }
+
+    /// `x/<count><format><unit>`, like gdb, returns the lines to print
+    fn examine(&self, format: &str, location: &str) -> Result<Vec<String>, String> {
+        let digits = format.chars().take_while(char::is_ascii_digit).count();
+        let count = match &format[..digits] {
+            "" => 1,
+            count => count.parse().map_err(|_| "invalid count")?,
+        };
+
+        let (mut display, mut unit) = ('x', 'w');
+        for c in format[digits..].chars() {
+            match c {
+                'x' | 'd' | 'u' | 'c' | 'i' => display = c,
+                'b' | 'h' | 'w' => unit = c,
+                _ => return Err(format!("unknown format letter `{}`", c)),
+            }
+        }
+
+        let addr = self.location(location)?;
+        if display == 'i' {
+            return Ok(self.disassemble(addr, count));
+        }
+        if display == 'c' {
+            unit = 'b';
+        }
+
+        let (size, per_line) = match unit {
+            'b' => (1, 8),
+            'h' => (2, 8),
+            _ => (4, 4),
+        };
+
+        // read the memory directly, examining it shouldn't trigger watchpoints or devices
+        let bus = self.emulator.base.bus_ref();
+        let values = (0..self.clamp_count(count, size))
+            .map(|i| {
+                let bytes = bus
+                    .load_bytes(addr.wrapping_add(i * size) as usize, size as usize)
+                    .map_err(|error| error.to_string())?;
+                let value = bytes
+                    .iter()
+                    .rev()
+                    .fold(0, |value, b| (value << 8) | *b as u32);
+                Ok(match display {
+                    'd' => {
+                        let shift = 32 - size * 8;
+                        (((value << shift) as i32) >> shift).to_string()
+                    }
+                    'u' => value.to_string(),
+                    'c' => format!("'{}'", (value as u8).escape_ascii()),
+                    _ => format!("{:#0width$x}", value, width = size as usize * 2 + 2),
+                })
+            })
+            .collect::<Result<Vec<String>, String>>()?;
+
+        let lines = values.chunks(per_line).enumerate().map(|(i, line)| {
+            let line_addr = addr.wrapping_add((i * per_line) as u32 * size);
+            format!("{}:\t{}", self.describe(line_addr), line.join("\t"))
+        });
+        Ok(lines.collect())
+    }
+
+    fn location(&self, text: &str) -> Result<u32, String> {
+        location(text, &self.symbols, &self.emulator.base)
+    }
+
+    fn value(&self, text: &str) -> Result<u32, String> {
+        value(text, &self.symbols, &self.emulator.base)
+    }
+}
+
+// ---- Parsing ----
+
+/// A symbol, number, register or the pc, optionally plus or minus an offset
+pub fn location(text: &str, symbols: &SymbolTable, rv_base: &RV32I) -> Result<u32, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("expected a location".into());
+    }
+
+    let (base, offset) = match text.rfind(['+', '-']).filter(|i| *i > 0) {
+        Some(i) => {
+            let offset =
+                parse_u32(&text[i + 1..]).ok_or(format!("invalid offset in `{}`", text))?;
+            let offset = if text.as_bytes()[i] == b'-' {
+                offset.wrapping_neg()
+            } else {
+                offset
+            };
+            (&text[..i], offset)
+        }
+        None => (text, 0),
+    };
+
+    let base = base.trim().trim_start_matches('$');
+    let addr = if let Some(symbol) = symbols.get(base) {
+        symbol.addr
+    } else if base == "pc" {
+        *rv_base.pc() as u32
+    } else if let Some(index) = rvasm::xreg(base) {
+        rv_base.get(index as usize) as u32
+    } else {
+        parse_u32(base).ok_or(format!("unknown location `{}`", base))?
+    };
+
+    Ok(addr.wrapping_add(offset))
+}
+
+/// A number, which may be negative, or a location
+pub fn value(text: &str, symbols: &SymbolTable, rv_base: &RV32I) -> Result<u32, String> {
+    match text.strip_prefix('-').and_then(parse_u32) {
+        Some(magnitude) => Ok(magnitude.wrapping_neg()),
+        None => location(text, symbols, rv_base),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::{setup, Args};
+
+    /// The sample sum program in 256 bytes of memory at 0x1000
+    fn machine() -> (Emulator, SymbolTable) {
+        let args = Args::try_parse_from(["rvcli", "--memory", "256", "--base", "0x1000"]).unwrap();
+        let program = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scripts/sum.s");
+        setup(&args, args.isa, Some(&program)).unwrap()
+    }
+
+    fn addr(symbols: &SymbolTable, name: &str) -> u32 {
+        symbols.get(name).unwrap().addr
+    }
+
+    #[test]
+    fn locations_and_values() {
+        let (mut emulator, symbols) = machine();
+        emulator.base.set(10, 0x1234);
+        let repl = Repl::new(&mut emulator, symbols.clone(), 0x1000);
+        let done = addr(&symbols, "done");
+
+        assert_eq!(repl.location("done"), Ok(done));
+        assert_eq!(repl.location(" done+8 "), Ok(done + 8));
+        assert_eq!(repl.location("done-0x4"), Ok(done - 4));
+        assert_eq!(repl.location("pc"), Ok(addr(&symbols, "_start")));
+        assert_eq!(repl.location("$a0"), Ok(0x1234));
+        assert_eq!(repl.location("x10+1"), Ok(0x1235));
+        assert_eq!(repl.location("sp-16"), Ok(0x10f0));
+        assert_eq!(repl.location("0x2000"), Ok(0x2000));
+
+        assert_eq!(repl.value("-1"), Ok(u32::MAX));
+        assert_eq!(repl.value("42"), Ok(42));
+        assert_eq!(repl.value("a0"), Ok(0x1234));
+
+        assert!(repl.location("").is_err());
+        assert!(repl.location("nowhere").is_err());
+        assert!(repl.location("done+zz").is_err());
+        assert!(repl.value("-zz").is_err());
+    }
+
+    #[test]
+    fn examine_formats() {
+        let (mut emulator, symbols) = machine();
+        let result = addr(&symbols, "result");
+        let bus = emulator.base.bus();
+        bus.store_bytes(result as usize, &[b'h', b'i', 0xfe, 0xff])
+            .unwrap();
+        let repl = Repl::new(&mut emulator, symbols.clone(), 0x1000);
+        let at = |text: &str| format!("{:#010x} <{}>:\t{}", result, "result", text);
+
+        assert_eq!(repl.examine("", "result"), Ok(vec![at("0xfffe6968")]));
+        assert_eq!(
+            repl.examine("4xb", "result"),
+            Ok(vec![at("0x68\t0x69\t0xfe\t0xff")])
+        );
+        assert_eq!(repl.examine("2dh", "result"), Ok(vec![at("26984\t-2")]));
+        assert_eq!(repl.examine("2uh", "result"), Ok(vec![at("26984\t65534")]));
+        // characters are always bytes
+        assert_eq!(repl.examine("2cw", "result"), Ok(vec![at("'h'\t'i'")]));
+
+        // eight bytes to a line, the next one starts at its own address
+        let lines = repl.examine("9xb", "result").unwrap();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].starts_with(&format!("{:#010x}", result + 8)));
+
+        let lines = repl.examine("3i", "_start").unwrap();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("=> 0x00001000 <_start>: "));
+        assert!(lines[1].starts_with("   0x00001004 <loop>: add"));
+    }
+
+    #[test]
+    fn examine_clamps_counts() {
+        let (mut emulator, symbols) = machine();
+        let repl = Repl::new(&mut emulator, symbols, 0x1000);
+
+        // 256 bytes of memory hold 64 instructions
+        assert_eq!(repl.examine("4000000000i", "_start").unwrap().len(), 64);
+        assert_eq!(repl.disassemble(0x1000, u32::MAX).len(), 64);
+        assert_eq!(repl.examine("64xw", "0x1000").unwrap().len(), 16);
+        // past the end of memory is an error rather than zeroes
+        assert!(repl.examine("2xw", "0x10fc").is_err());
+        assert!(repl.examine("xb", "0").is_err());
+    }
+
+    fn run(repl: &mut Repl, line: &str) -> Result<(), String> {
+        repl.command(line).map(|_| ())
+    }
+
+    #[test]
+    fn break_delete_continue() {
+        let (mut emulator, symbols) = machine();
+        let (loop_, done) = (addr(&symbols, "loop"), addr(&symbols, "done"));
+        let mut repl = Repl::new(&mut emulator, symbols, 0x1000);
+
+        run(&mut repl, "set a0 = 3").unwrap();
+        run(&mut repl, "break done").unwrap();
+        run(&mut repl, "break loop if a0 == 1").unwrap();
+        run(&mut repl, "continue").unwrap();
+        assert_eq!(*repl.emulator.base.pc() as u32, loop_);
+        assert_eq!(repl.emulator.base.get(10), 1);
+
+        run(&mut repl, "delete loop").unwrap();
+        assert!(run(&mut repl, "delete loop").is_err());
+        run(&mut repl, "continue").unwrap();
+        assert_eq!(*repl.emulator.base.pc() as u32, done);
+        assert_eq!(repl.emulator.base.get(11), 6);
+
+        // stepping ignores breakpoints, continuing doesn't stop where it starts
+        run(&mut repl, "break done").unwrap();
+        run(&mut repl, "step 2").unwrap();
+        run(&mut repl, "delete").unwrap();
+        assert_eq!(repl.breakpoints.iter().count(), 0);
+        run(&mut repl, "continue").unwrap();
+        assert!(repl.exited);
+        assert!(run(&mut repl, "continue").is_err());
+        assert!(run(&mut repl, "step").is_err());
+    }
+
+    #[test]
+    fn bad_input() {
+        let (mut emulator, symbols) = machine();
+        let mut repl = Repl::new(&mut emulator, symbols, 0x1000);
+
+        for line in [
+            "frobnicate",
+            "x/4q result",
+            "x/4xw nowhere",
+            "break",
+            "break nowhere",
+            "break done if a0 ?? 1",
+            "delete done",
+            "watch",
+            "watch result lots",
+            "step lots",
+            "disas _start lots",
+            "info frames",
+            "set a0 7",
+            "set q9 = 1",
+            "set a0 = nothing",
+            "load /nonexistent/program.s",
+        ] {
+            assert!(repl.command(line).is_err(), "{}", line);
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use std::{collections::VecDeque, fmt::Display, fs::File, io::Write, path::Path};
+
+use crate::hexdump::parse_pattern;
+
+/// Input from outside the machine, the only thing that can make two runs differ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputEvent {
+    /// Bytes for a `read` syscall
+    Read(Vec<u8>),
+    /// Bytes received by the UART
+    Uart(Vec<u8>),
+    /// The end of input, `read` returns 0 from then on
+    Close,
+}
+
+impl Display for InputEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputEvent::Read(bytes) => write!(f, "read \"{}\"", bytes.escape_ascii()),
+            InputEvent::Uart(bytes) => write!(f, "uart \"{}\"", bytes.escape_ascii()),
+            InputEvent::Close => write!(f, "close"),
+        }
+    }
+}
+
+impl InputEvent {
+    fn parse(text: &str) -> Result<Self, String> {
+        let (kind, bytes) = text.split_once(' ').unwrap_or((text, ""));
+        match kind {
+            "read" => Ok(InputEvent::Read(parse_pattern(bytes)?)),
+            "uart" => Ok(InputEvent::Uart(parse_pattern(bytes)?)),
+            "close" => Ok(InputEvent::Close),
+            _ => Err(format!("unknown input `{}`", kind)),
+        }
+    }
+}
+
+/// Appends each input to a log as it arrives, so a crash keeps everything up to it
+///
+/// Each line is the number of instructions retired before the input, then the input:
+///
+/// ```text
+/// 1520 uart "hello\n"
+/// 1733 read "\x04"
+/// 1790 close
+/// ```
+pub struct Recorder {
+    file: File,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> std::io::Result<Self> {
+        let mut file = File::create(path)?;
+        writeln!(file, "# rvcli input log, replay it with `--replay`")?;
+        Ok(Self { file })
+    }
+
+    pub fn record(&mut self, retired: u64, event: &InputEvent) {
+        // losing the log shouldn't stop the run, and the TUI has nowhere to report it
+        let _ = writeln!(self.file, "{} {}", retired, event);
+    }
+}
+
+/// Reads a log written by a [`Recorder`], oldest input first
+pub fn load(path: &Path) -> Result<VecDeque<(u64, InputEvent)>, String> {
+    let text = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+
+    let mut events = VecDeque::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let event = line
+            .split_once(' ')
+            .and_then(|(retired, event)| Some((retired.parse().ok()?, event)))
+            .ok_or("expected an instruction count and an input".to_string())
+            .and_then(|(retired, event)| Ok((retired, InputEvent::parse(event)?)))
+            .map_err(|error| format!("line {}: {}", i + 1, error))?;
+        events.push_back(event);
+    }
+
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use clap::Parser;
+
+    use super::*;
+    use crate::{emulator::TickResult, setup, Args};
+
+    fn temp(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("rvcli-{}-{}.log", name, std::process::id()))
+    }
+
+    #[test]
+    fn log_round_trip() {
+        let events = vec![
+            (0, InputEvent::Read(b"hello \"there\"\n".to_vec())),
+            (17, InputEvent::Uart((0..=255).collect())),
+            (17, InputEvent::Read(Vec::new())),
+            (1 << 40, InputEvent::Close),
+        ];
+
+        let path = temp("round-trip");
+        let mut recorder = Recorder::create(&path).unwrap();
+        for (retired, event) in &events {
+            recorder.record(*retired, event);
+        }
+        drop(recorder);
+
+        let loaded = load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, Ok(events.into()));
+    }
+
+    #[test]
+    fn malformed_logs() {
+        let path = temp("malformed");
+        for (text, error) in [
+            (
+                "# comment\n\n5 close\nclose\n",
+                "line 4: expected an instruction count",
+            ),
+            ("5 paste \"x\"\n", "line 1: unknown input `paste`"),
+            ("5 read 4\n", "line 1: expected `\"text\"` or hex bytes"),
+        ] {
+            std::fs::write(&path, text).unwrap();
+            let result = load(&path);
+            assert!(result.is_err_and(|e| e.starts_with(error)), "{}", text);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// Runs the sample echo program to the end, typing `typed` whenever it waits for input
+    fn run_echo(emulator: &mut crate::emulator::Emulator, typed: &[u8]) -> (Vec<u64>, Vec<u8>) {
+        let mut waits = Vec::new();
+        let mut output = Vec::new();
+        for _ in 0..10_000 {
+            let result = emulator.tick();
+            output.extend(emulator.take_output());
+            match result {
+                TickResult::Exit(..) => return (waits, output),
+                TickResult::WaitInput if waits.is_empty() => {
+                    waits.push(emulator.retired);
+                    emulator.push_input(typed);
+                }
+                TickResult::WaitInput => {
+                    waits.push(emulator.retired);
+                    emulator.close_input();
+                }
+                _ => (),
+            }
+        }
+        panic!("the program didn't exit");
+    }
+
+    #[test]
+    fn replay_at_recorded_counts() {
+        let args = Args::try_parse_from(["rvcli"]).unwrap();
+        let program = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scripts/echo.s");
+        let path = temp("replay");
+
+        let (mut emulator, _) = setup(&args, args.isa, Some(&program)).unwrap();
+        emulator.recorder = Some(Recorder::create(&path).unwrap());
+        let (waits, output) = run_echo(&mut emulator, b"hello\n");
+        assert_eq!(output, b"hello\n");
+        drop(emulator);
+
+        // each input is logged at the instruction count the guest waited at
+        let events = load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let expected = [
+            (waits[0], InputEvent::Read(b"hello\n".to_vec())),
+            (waits[1], InputEvent::Close),
+        ];
+        assert_eq!(events, expected);
+
+        // the replay delivers it before the guest asks, and live input is ignored
+        let (mut emulator, _) = setup(&args, args.isa, Some(&program)).unwrap();
+        emulator.replay = events;
+        emulator.push_input(b"ignored");
+        let (waits, replayed) = run_echo(&mut emulator, b"ignored");
+        assert_eq!(waits, []);
+        assert_eq!(replayed, output);
+    }
+}
@@ -0,0 +1,454 @@
+//! Test scripts that load a program, run it and check the machine's state, see `rvcli test --help`
+
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use rvcore::{elf::SymbolTable, isa::Isa, util::parse_u32, Volatile};
+
+use crate::{
+    emulator::{Emulator, TickResult},
+    hexdump::parse_pattern,
+    repl::{location, value},
+    setup, Args,
+};
+
+/// Extension of the scripts found in directories
+const EXTENSION: &str = "rvtest";
+/// Instructions a `run` may take before the test fails, so a wrong program can't hang the suite
+const DEFAULT_LIMIT: u64 = 10_000_000;
+
+/// Runs every script in `paths`, prints a line per test and a summary
+pub fn run(paths: &[PathBuf], args: &Args) -> ExitCode {
+    let mut scripts = Vec::new();
+    for path in paths {
+        collect(path, &mut scripts);
+    }
+
+    let mut failed = 0;
+    for script in &scripts {
+        let result = Test::new(script, args).run();
+        failed += result.is_err() as usize;
+        println!("{}", report(script, &result));
+    }
+
+    println!("{} passed, {} failed", scripts.len() - failed, failed);
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// The line printed for a test
+fn report(script: &Path, result: &Result<(), String>) -> String {
+    match result {
+        Ok(()) => format!("PASS {}", script.display()),
+        Err(error) => format!("FAIL {}{}", script.display(), error),
+    }
+}
+
+/// Adds `path`, or the scripts in it and its subdirectories, in a stable order
+fn collect(path: &Path, scripts: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        // reading a missing file fails the test with the reason
+        scripts.push(path.to_path_buf());
+        return;
+    };
+
+    let mut entries: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            collect(&entry, scripts);
+        } else if entry.extension().is_some_and(|e| e == EXTENSION) {
+            scripts.push(entry);
+        }
+    }
+}
+
+struct Test<'a> {
+    path: &'a Path,
+    args: &'a Args,
+
+    program: Option<PathBuf>,
+    isa: Isa,
+    limit: u64,
+
+    /// Created by the first statement that needs it, after `program` and `isa`
+    machine: Option<(Emulator, SymbolTable)>,
+    /// Everything the guest wrote
+    output: Vec<u8>,
+    /// Exit code, once the guest exited
+    exit: Option<u32>,
+}
+
+impl<'a> Test<'a> {
+    fn new(path: &'a Path, args: &'a Args) -> Self {
+        Self {
+            path,
+            args,
+
+            program: None,
+            isa: args.isa,
+            limit: DEFAULT_LIMIT,
+
+            machine: None,
+            output: Vec::new(),
+            exit: None,
+        }
+    }
+
+    /// Runs the script, errors start with the line number to follow the path
+    fn run(mut self) -> Result<(), String> {
+        let script = std::fs::read_to_string(self.path).map_err(|error| format!(": {}", error))?;
+
+        for (i, line) in script.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            self.statement(line)
+                .map_err(|error| format!(":{}: {}", i + 1, error))?;
+        }
+
+        Ok(())
+    }
+
+    fn statement(&mut self, line: &str) -> Result<(), String> {
+        let (name, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+
+        match name {
+            "program" | "isa" if self.machine.is_some() => {
+                return Err(format!("`{}` has to come before the program runs", name));
+            }
+            "program" => {
+                let dir = self.path.parent().unwrap_or(Path::new(""));
+                self.program = Some(dir.join(rest));
+            }
+            "isa" => {
+                self.isa = rest
+                    .parse()
+                    .map_err(|error| format!("invalid ISA `{}`: {}", rest, error))?;
+            }
+            "limit" => {
+                self.limit = parse_u32(rest).ok_or(format!("invalid limit `{}`", rest))? as u64;
+            }
+
+            "input" => {
+                let bytes = parse_pattern(rest)?;
+                self.machine()?.0.push_input(&bytes);
+            }
+            "set" => {
+                let (target, value) = rest
+                    .split_once('=')
+                    .ok_or("expected `set <target> = <value>`")?;
+                self.set(target.trim(), value.trim())?;
+            }
+            "run" => self.run_until(rest)?,
+            "expect" => {
+                let (target, expected) = rest
+                    .split_once("==")
+                    .ok_or("expected `expect <target> == <value>`")?;
+                self.expect(target.trim(), expected.trim())?;
+            }
+
+            _ => return Err(format!("unknown statement `{}`", name)),
+        }
+
+        Ok(())
+    }
+
+    fn machine(&mut self) -> Result<&mut (Emulator, SymbolTable), String> {
+        if self.machine.is_none() {
+            // a snapshot from the command line is enough to run
+            let program = self.program.as_deref();
+            if program.is_none() && self.args.snapshot.is_none() {
+                return Err("no `program` to run".into());
+            }
+
+            let machine = setup(self.args, self.isa, program).map_err(|error| match program {
+                Some(program) => format!("failed to load `{}`: {}", program.display(), error),
+                None => format!("failed to set up the machine: {}", error),
+            })?;
+            self.machine = Some(machine);
+        }
+
+        Ok(self.machine.as_mut().unwrap())
+    }
+
+    fn set(&mut self, target: &str, text: &str) -> Result<(), String> {
+        let (emulator, symbols) = self.machine()?;
+
+        if let Some(index) = rvasm::freg(target) {
+            let value = text
+                .parse()
+                .map_err(|_| format!("invalid float `{}`", text))?;
+            emulator.rv_f.set(index as usize, value);
+            return Ok(());
+        }
+
+        let value = value(text, symbols, &emulator.base)?;
+        if target == "pc" {
+            emulator.base.set_pc(value as i32);
+        } else if let Some(loc) = target.strip_prefix('*') {
+            let addr = location(loc, symbols, &emulator.base)?;
+            emulator.base.bus().store(addr as usize, 32, value);
+        } else {
+            let index = rvasm::xreg(target).ok_or(format!("unknown register `{}`", target))?;
+            emulator.base.set(index as usize, value as i32);
+        }
+
+        Ok(())
+    }
+
+    /// `run` until the guest exits, `run until <loc>` or `run <count>` instructions
+    fn run_until(&mut self, rest: &str) -> Result<(), String> {
+        let limit = self.limit;
+        if let Some(code) = self.exit {
+            return Err(format!("the program already exited with code {}", code));
+        }
+
+        self.machine()?;
+        // the output is kept in another field
+        let Some((emulator, symbols)) = &mut self.machine else {
+            unreachable!()
+        };
+        let (target, count) = match rest.strip_prefix("until") {
+            Some(loc) => (Some(location(loc, symbols, &emulator.base)?), None),
+            None if rest.is_empty() => (None, None),
+            None => (
+                None,
+                Some(parse_u32(rest).ok_or(format!("invalid count `{}`", rest))?),
+            ),
+        };
+        let count = count.map(|count| count as u64);
+
+        let mut executed = 0;
+        loop {
+            let pc = *emulator.base.pc() as u32;
+            // a loop can run until its own start again
+            if count == Some(executed) || (executed > 0 && target == Some(pc)) {
+                return Ok(());
+            }
+            if executed >= limit {
+                return Err(format!("instruction limit of {} reached", limit));
+            }
+
+            let result = emulator.tick();
+            executed += 1;
+            self.output.extend(emulator.take_output());
+
+            let at = || match symbols.describe(pc) {
+                Some(symbol) => format!("{:#010x} <{}>", pc, symbol),
+                None => format!("{:#010x}", pc),
+            };
+            match result {
+                TickResult::Nothing | TickResult::Watch(..) => (),
+                // the script gave all of its input up front
+                TickResult::WaitInput => emulator.close_input(),
+                TickResult::Exit(_, code) => {
+                    self.exit = Some(code);
+                    if target.is_some() || count.is_some() {
+                        return Err(format!("exited with code {} at {}", code, at()));
+                    }
+                    return Ok(());
+                }
+                TickResult::ECall => {
+                    let syscall = emulator.base.get(17);
+                    return Err(format!("unsupported ecall {} at {}", syscall, at()));
+                }
+                TickResult::EBreak => return Err(format!("ebreak at {}", at())),
+                TickResult::Illegal(ins) => {
+                    return Err(format!("illegal instruction {:#010x} at {}", ins, at()));
+                }
+            }
+        }
+    }
+
+    /// Checks the exit code, output, memory, a register or the pc
+    fn expect(&mut self, target: &str, expected: &str) -> Result<(), String> {
+        let mismatch = |actual: String| {
+            Err(format!(
+                "expected {} == {}, got {}",
+                target, expected, actual
+            ))
+        };
+
+        match target {
+            "exit" => {
+                let code = self.exit.ok_or("the program hasn't exited")?;
+                let (emulator, symbols) = self.machine()?;
+                if code != value(expected, symbols, &emulator.base)? {
+                    return mismatch(code.to_string());
+                }
+            }
+            "output" => {
+                if self.output != parse_pattern(expected)? {
+                    return mismatch(format!("\"{}\"", self.output.escape_ascii()));
+                }
+            }
+            _ => {
+                let (emulator, symbols) = self.machine()?;
+
+                if let Some(loc) = target.strip_prefix("mem ") {
+                    let addr = location(loc, symbols, &emulator.base)? as usize;
+                    let expected = parse_pattern(expected)?;
+                    // checking memory shouldn't trigger watchpoints or devices
+                    let actual = emulator
+                        .base
+                        .bus_ref()
+                        .load_bytes(addr, expected.len())
+                        .map_err(|error| error.to_string())?;
+
+                    if actual != expected {
+                        return mismatch(format!("\"{}\"", actual.escape_ascii()));
+                    }
+                } else if let Some(index) = rvasm::freg(target) {
+                    let actual = emulator.rv_f.get(index as usize);
+                    let expected: f32 = expected
+                        .parse()
+                        .map_err(|_| format!("invalid float `{}`", expected))?;
+
+                    // NaNs never equal themselves
+                    if actual != expected && !(actual.is_nan() && expected.is_nan()) {
+                        return mismatch(actual.to_string());
+                    }
+                } else {
+                    let actual = if target == "pc" {
+                        *emulator.base.pc() as u32
+                    } else if let Some(loc) = target.strip_prefix('*') {
+                        let addr = location(loc, symbols, &emulator.base)?;
+                        let bytes = emulator
+                            .base
+                            .bus_ref()
+                            .load_bytes(addr as usize, 4)
+                            .map_err(|error| error.to_string())?;
+                        u32::from_le_bytes(bytes.try_into().unwrap())
+                    } else {
+                        let index =
+                            rvasm::xreg(target).ok_or(format!("unknown register `{}`", target))?;
+                        emulator.base.get(index as usize) as u32
+                    };
+
+                    if actual != value(expected, symbols, &emulator.base)? {
+                        return mismatch(format!("{} ({:#x})", actual as i32, actual));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    fn args() -> Args {
+        Args::try_parse_from(["rvcli"]).unwrap()
+    }
+
+    fn scripts() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scripts")
+    }
+
+    /// Runs `statements` one by one, as if they were a script next to the samples
+    fn statements(statements: &[&str]) -> Result<(), String> {
+        let args = args();
+        let path = scripts().join("inline.rvtest");
+        let mut test = Test::new(&path, &args);
+        for statement in statements {
+            test.statement(statement)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn setup_statements() {
+        let args = args();
+        let path = scripts().join("inline.rvtest");
+        let mut test = Test::new(&path, &args);
+
+        test.statement("program sum.s").unwrap();
+        assert_eq!(test.program, Some(scripts().join("sum.s")));
+        test.statement("isa rv32im").unwrap();
+        assert!(test.isa.m && !test.isa.f);
+        test.statement("limit 0x100").unwrap();
+        assert_eq!(test.limit, 0x100);
+
+        assert!(test.statement("isa rv64").is_err());
+        assert!(test.statement("limit lots").is_err());
+        assert!(test.statement("frobnicate").is_err());
+
+        // the machine is set up from them once it's needed
+        test.statement("set a0 = 3").unwrap();
+        assert!(test.statement("program echo.s").is_err());
+        assert!(test.statement("isa rv32i").is_err());
+    }
+
+    #[test]
+    fn machine_statements() {
+        let prefix = ["program sum.s", "set a0 = 4"];
+        let with = |rest: &[&str]| statements(&[&prefix[..], rest].concat());
+
+        assert!(with(&["expect a0 == 4", "expect x10 == 0x4"]).is_ok());
+        assert!(with(&["set *result = 7", "expect *result == 7"]).is_ok());
+        assert!(with(&["set pc = done", "expect pc == done"]).is_ok());
+        assert!(with(&["set ft0 = 1.5", "expect ft0 == 1.5"]).is_ok());
+        assert!(with(&["expect mem _start == 93 05 00 00"]).is_ok());
+        assert!(with(&["input \"abc\"", "input 64 65"]).is_ok());
+
+        assert!(with(&["run until done", "expect a1 == 10"]).is_ok());
+        assert!(with(&["run 2", "expect a1 == 4"]).is_ok());
+        assert!(with(&["run", "expect exit == 10", "expect output == \"\""]).is_ok());
+
+        assert!(with(&["expect a0 == 5"]).is_err());
+        assert!(with(&["expect exit == 0"]).is_err());
+        assert!(with(&["set a0 = nothing"]).is_err());
+        assert!(with(&["expect q9 == 1"]).is_err());
+        // outside of memory rather than zeroes
+        assert!(with(&["expect mem 0x200000 == 00"]).is_err());
+        assert!(with(&["expect *0xfffffffe == 0"]).is_err());
+        assert!(with(&["limit 5", "run"]).is_err());
+        assert!(with(&["run", "run"]).is_err());
+        assert!(statements(&["run"]).is_err());
+    }
+
+    #[test]
+    fn passing_scripts() {
+        let args = args();
+        for name in ["sum.rvtest", "echo.rvtest"] {
+            let path = scripts().join(name);
+            assert_eq!(Test::new(&path, &args).run(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn failing_script() {
+        let dir = std::env::temp_dir().join(format!("rvcli-script-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::copy(scripts().join("sum.s"), dir.join("sum.s")).unwrap();
+        let path = dir.join("wrong.rvtest");
+        std::fs::write(
+            &path,
+            "program sum.s\nset a0 = 3\n\nrun\nexpect exit == 5\n",
+        )
+        .unwrap();
+
+        let args = args();
+        let result = Test::new(&path, &args).run();
+        let line = report(&path, &result);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(result, Err(":5: expected exit == 5, got 6".into()));
+        assert!(line.starts_with("FAIL "));
+        assert!(line.ends_with("wrong.rvtest:5: expected exit == 5, got 6"));
+    }
+}
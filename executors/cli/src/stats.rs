@@ -0,0 +1,434 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+};
+
+use rv_f::{OPCODE_LOADF, OPCODE_OPFP, OPCODE_STOREF};
+use rvcore::{
+    cache::CacheHierarchy,
+    elf::SymbolTable,
+    ins::{
+        OPCODE_AUIPC, OPCODE_BRANCH, OPCODE_JAL, OPCODE_JALR, OPCODE_LOAD, OPCODE_LUI, OPCODE_MASK,
+        OPCODE_OP, OPCODE_OPIMM, OPCODE_STORE, OPCODE_SYSTEM,
+    },
+    predictor::Predictors,
+};
+
+use crate::emulator::{TickResult, SYS_EXIT, SYS_EXIT_GROUP, SYS_READ, SYS_WRITE};
+
+const ECALL: u32 = 0x0000_0073;
+
+/// Execution statistics, the instruction mix broken down by mnemonic, extension and class
+#[derive(Default)]
+pub struct Stats {
+    /// Retired instructions per encoding, everything but branch outcomes follows from it
+    words: HashMap<u32, u64>,
+    pub retired: u64,
+    branches_taken: u64,
+    branches_not_taken: u64,
+
+    /// Executed `ecall`s per syscall number, supported or not
+    syscalls: BTreeMap<i32, u64>,
+    /// Traps that stopped the machine
+    ecalls: u64,
+    ebreaks: u64,
+    illegal: u64,
+}
+
+impl Stats {
+    /// Counts an executed instruction, `retired` is false if it trapped or is waiting for input
+    ///
+    /// `taken` is the outcome of a branch, None for other instructions.
+    pub fn record(
+        &mut self,
+        ins: u32,
+        retired: bool,
+        taken: Option<bool>,
+        syscall: i32,
+        result: &TickResult,
+    ) {
+        match result {
+            TickResult::ECall => self.ecalls += 1,
+            TickResult::EBreak => self.ebreaks += 1,
+            TickResult::Illegal(_) => self.illegal += 1,
+            _ => (),
+        }
+
+        // a waiting `read` runs again once there's input, count it when it finishes
+        if ins == ECALL && (retired || matches!(result, TickResult::ECall)) {
+            *self.syscalls.entry(syscall).or_default() += 1;
+        }
+
+        if !retired {
+            return;
+        }
+        self.retired += 1;
+        *self.words.entry(ins).or_default() += 1;
+
+        match taken {
+            Some(true) => self.branches_taken += 1,
+            Some(false) => self.branches_not_taken += 1,
+            None => (),
+        }
+    }
+
+    /// The report printed at the end of a headless run and shown in the TUI
+    pub fn report(&self) -> String {
+        let mut mnemonics: HashMap<String, u64> = HashMap::new();
+        let mut extensions: BTreeMap<&str, u64> = BTreeMap::new();
+        let mut classes: BTreeMap<Class, u64> = BTreeMap::new();
+        for (ins, count) in &self.words {
+            let text = rvasm::decode_instruction(*ins);
+            let mnemonic = text.split_whitespace().next().unwrap_or("?");
+            *mnemonics.entry(mnemonic.to_string()).or_default() += count;
+            *extensions.entry(extension(*ins)).or_default() += count;
+            if let Some(class) = Class::of(*ins) {
+                *classes.entry(class).or_default() += count;
+            }
+        }
+        classes.insert(Class::BranchTaken, self.branches_taken);
+        classes.insert(Class::BranchNotTaken, self.branches_not_taken);
+
+        let mut report = String::new();
+        let line = |report: &mut String, name: &str, count: u64| {
+            let share = count as f64 * 100.0 / self.retired.max(1) as f64;
+            let _ = writeln!(report, "  {:<18} {:>12} {:>6.2}%", name, count, share);
+        };
+
+        let _ = writeln!(report, "Retired instructions: {}", self.retired);
+
+        report.push_str("\nBy extension\n");
+        for (extension, count) in sorted(extensions) {
+            line(&mut report, extension, count);
+        }
+
+        report.push_str("\nBy class\n");
+        for (class, count) in classes {
+            line(&mut report, class.name(), count);
+        }
+
+        report.push_str("\nBy mnemonic\n");
+        for (mnemonic, count) in sorted(mnemonics) {
+            line(&mut report, &mnemonic, count);
+        }
+
+        report.push_str("\nSyscalls\n");
+        for (number, count) in &self.syscalls {
+            let name = match syscall_name(*number) {
+                Some(name) => format!("{} {}", number, name),
+                None => number.to_string(),
+            };
+            let _ = writeln!(report, "  {:<18} {:>12}", name, count);
+        }
+
+        report.push_str("\nTraps\n");
+        let traps = [
+            ("unsupported ecall", self.ecalls),
+            ("ebreak", self.ebreaks),
+            ("illegal", self.illegal),
+        ];
+        for (name, count) in traps {
+            let _ = writeln!(report, "  {:<18} {:>12}", name, count);
+        }
+
+        report
+    }
+}
+
+/// Hits and misses of each cache level, and the cycles the accesses took
+pub fn cache_report(caches: &CacheHierarchy) -> String {
+    let levels = [("L1I", &caches.l1i), ("L1D", &caches.l1d)]
+        .into_iter()
+        .chain(caches.l2.as_ref().map(|l2| ("L2", l2)));
+
+    let mut report = String::from("Caches\n");
+    let _ = writeln!(
+        report,
+        "  {:<6} {:>12} {:>10} {:>12} {:>10} {:>10} {:>9}",
+        "", "Reads", "Misses", "Writes", "Misses", "Writebacks", "Miss rate"
+    );
+    for (name, cache) in levels.clone() {
+        let stats = &cache.stats;
+        let _ = writeln!(
+            report,
+            "  {:<6} {:>12} {:>10} {:>12} {:>10} {:>10} {:>8.2}%",
+            name,
+            stats.reads,
+            stats.read_misses,
+            stats.writes,
+            stats.write_misses,
+            stats.writebacks,
+            stats.miss_rate() * 100.0
+        );
+    }
+    let _ = writeln!(
+        report,
+        "  {:<6} {:>12} {:>10} {:>12}",
+        "memory", caches.memory_reads, "", caches.memory_writes
+    );
+
+    report.push('\n');
+    for (name, cache) in levels {
+        let _ = writeln!(report, "  {:<6} {}", name, cache.config);
+    }
+
+    let average = caches.cycles as f64 / caches.accesses.max(1) as f64;
+    let _ = writeln!(
+        report,
+        "\n{} accesses took {} cycles, {:.2} on average",
+        caches.accesses, caches.cycles, average
+    );
+    report
+}
+
+/// Most executed first, ties by name
+fn sorted<K: Ord>(counts: impl IntoIterator<Item = (K, u64)>) -> Vec<(K, u64)> {
+    let mut counts: Vec<(K, u64)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    counts
+}
+
+/// The extension that implements a retired instruction
+fn extension(ins: u32) -> &'static str {
+    match ins & OPCODE_MASK {
+        OPCODE_OP if ins >> 25 == 1 => "M",
+        OPCODE_LOADF | OPCODE_STOREF | OPCODE_OPFP => "F",
+        OPCODE_SYSTEM if (ins >> 12) & 0b111 != 0 => "Zicsr",
+        _ => "I",
+    }
+}
+
+fn syscall_name(number: i32) -> Option<&'static str> {
+    match number {
+        SYS_READ => Some("read"),
+        SYS_WRITE => Some("write"),
+        SYS_EXIT => Some("exit"),
+        SYS_EXIT_GROUP => Some("exit_group"),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Class {
+    Alu,
+    Load,
+    Store,
+    BranchTaken,
+    BranchNotTaken,
+    Jump,
+    Fp,
+    System,
+}
+
+impl Class {
+    /// Class of an instruction, branches are counted apart as they depend on the outcome
+    fn of(ins: u32) -> Option<Class> {
+        Some(match ins & OPCODE_MASK {
+            OPCODE_OP | OPCODE_OPIMM | OPCODE_LUI | OPCODE_AUIPC => Class::Alu,
+            OPCODE_LOAD | OPCODE_LOADF => Class::Load,
+            OPCODE_STORE | OPCODE_STOREF => Class::Store,
+            OPCODE_BRANCH => return None,
+            OPCODE_JAL | OPCODE_JALR => Class::Jump,
+            OPCODE_OPFP => Class::Fp,
+            // `ecall`, CSR accesses and fences
+            _ => Class::System,
+        })
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Class::Alu => "ALU",
+            Class::Load => "load",
+            Class::Store => "store",
+            Class::BranchTaken => "branch taken",
+            Class::BranchNotTaken => "branch not taken",
+            Class::Jump => "jump",
+            Class::Fp => "FP",
+            Class::System => "system",
+        }
+    }
+}
+
+/// Misprediction rates of each predictor, overall and per branch
+pub fn predictor_report(predictors: &Predictors, symbols: &SymbolTable) -> String {
+    let rate = |missed: u64, total: u64| missed as f64 * 100.0 / total.max(1) as f64;
+    let executed = predictors.executed();
+
+    let mut report = String::new();
+    let _ = writeln!(
+        report,
+        "Branch predictors, {} conditional branches\n  {:<18} {:>12} {:>9}",
+        executed, "", "Mispredicted", "Rate"
+    );
+    for (config, missed) in predictors.configs.iter().zip(predictors.mispredicted()) {
+        let _ = writeln!(
+            report,
+            "  {:<18} {:>12} {:>8.2}%",
+            config.to_string(),
+            missed,
+            rate(missed, executed)
+        );
+    }
+
+    let jumps = &predictors.jumps;
+    let _ = writeln!(
+        report,
+        "  {:<18} {:>12} {:>8.2}%  of {} jumps",
+        "BTB",
+        jumps.btb_misses,
+        rate(jumps.btb_misses, jumps.jumps),
+        jumps.jumps
+    );
+    let _ = writeln!(
+        report,
+        "  {:<18} {:>12} {:>8.2}%  of {} returns",
+        "RAS",
+        jumps.ras_misses,
+        rate(jumps.ras_misses, jumps.returns),
+        jumps.returns
+    );
+
+    // every branch that ran, most executed first
+    let _ = write!(
+        report,
+        "\n  {:<32} {:>10} {:>7}",
+        "Branch", "Executed", "Taken"
+    );
+    for config in &predictors.configs {
+        let _ = write!(report, " {:>12}", config.to_string());
+    }
+    report.push('\n');
+
+    let mut branches: Vec<_> = predictors.branches.iter().collect();
+    branches.sort_by(|a, b| b.1.executed.cmp(&a.1.executed).then(a.0.cmp(b.0)));
+    for (pc, record) in branches {
+        let location = match symbols.describe(*pc) {
+            Some(symbol) => format!("{:08x} <{}>", pc, symbol),
+            None => format!("{:08x}", pc),
+        };
+        let taken = rate(record.taken, record.executed);
+        let _ = write!(
+            report,
+            "  {:<32} {:>10} {:>6.1}%",
+            location, record.executed, taken
+        );
+        for missed in &record.mispredicted {
+            let _ = write!(report, " {:>11.2}%", rate(*missed, record.executed));
+        }
+        report.push('\n');
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDI: u32 = 0x0010_0093;
+    const ADD: u32 = 0x0020_81b3;
+    const MUL: u32 = 0x0220_81b3;
+    const LW: u32 = 0x0001_2083;
+    const SW: u32 = 0x0011_2023;
+    const FLW: u32 = 0x0001_2087;
+    const FADD: u32 = 0x0010_80d3;
+    /// `csrr ra, cycle`
+    const CSRR: u32 = 0xc000_20f3;
+    /// `beq` and `bne`, only the opcode matters
+    const BEQ: u32 = 0x0000_0063;
+    const BNE: u32 = 0x0000_1063;
+    const JAL: u32 = 0x0000_006f;
+    const EBREAK: u32 = 0x0010_0073;
+
+    /// The count on `name`'s line in `section` of the report
+    fn count(report: &str, section: &str, name: &str) -> Option<u64> {
+        let lines = report.lines().skip_while(|line| *line != section).skip(1);
+        let line = lines
+            .take_while(|line| !line.is_empty())
+            .find_map(|line| line.strip_prefix("  ")?.strip_prefix(name))?;
+        line.split_whitespace().next()?.parse().ok()
+    }
+
+    fn stats() -> Stats {
+        let mut stats = Stats::default();
+        for ins in [ADDI, ADD, MUL, LW, SW, FLW, FADD, CSRR, JAL] {
+            stats.record(ins, true, None, 0, &TickResult::Nothing);
+        }
+        stats.record(BEQ, true, Some(true), 0, &TickResult::Nothing);
+        stats.record(BNE, true, Some(false), 0, &TickResult::Nothing);
+
+        stats.record(ECALL, true, None, SYS_WRITE, &TickResult::Nothing);
+        // a `read` waiting for input counts once it completes
+        stats.record(ECALL, false, None, SYS_READ, &TickResult::WaitInput);
+        let exit = TickResult::Exit(crate::emulator::ExitSource::Syscall, 0);
+        stats.record(ECALL, true, None, SYS_EXIT, &exit);
+
+        stats.record(ECALL, false, None, 999, &TickResult::ECall);
+        stats.record(EBREAK, false, None, 0, &TickResult::EBreak);
+        let illegal = TickResult::Illegal(u32::MAX);
+        stats.record(u32::MAX, false, None, 0, &illegal);
+        stats
+    }
+
+    #[test]
+    fn instruction_mix() {
+        let stats = stats();
+        assert_eq!(stats.retired, 13);
+        assert_eq!((stats.branches_taken, stats.branches_not_taken), (1, 1));
+        assert_eq!(stats.syscalls, BTreeMap::from([(64, 1), (93, 1), (999, 1)]));
+        assert_eq!((stats.ecalls, stats.ebreaks, stats.illegal), (1, 1, 1));
+    }
+
+    #[test]
+    fn report() {
+        let report = stats().report();
+        assert!(report.starts_with("Retired instructions: 13\n"));
+
+        let extensions = [("I", 9), ("F", 2), ("M", 1), ("Zicsr", 1)];
+        for (name, expected) in extensions {
+            assert_eq!(
+                count(&report, "By extension", name),
+                Some(expected),
+                "{}",
+                name
+            );
+        }
+        assert!(report.contains("  I                             9  69.23%\n"));
+
+        let classes = [
+            ("ALU", 3),
+            ("load", 2),
+            ("store", 1),
+            ("branch taken", 1),
+            ("branch not taken", 1),
+            ("jump", 1),
+            ("FP", 1),
+            ("system", 3),
+        ];
+        for (name, expected) in classes {
+            assert_eq!(count(&report, "By class", name), Some(expected), "{}", name);
+        }
+
+        assert_eq!(count(&report, "By mnemonic", "ecall"), Some(2));
+        assert_eq!(count(&report, "By mnemonic", "mul"), Some(1));
+        assert_eq!(count(&report, "By mnemonic", "fadd.s"), Some(1));
+
+        assert_eq!(count(&report, "Syscalls", "64 write"), Some(1));
+        assert_eq!(count(&report, "Syscalls", "93 exit"), Some(1));
+        assert_eq!(count(&report, "Syscalls", "999"), Some(1));
+        assert_eq!(count(&report, "Syscalls", "63 read"), None);
+
+        assert_eq!(count(&report, "Traps", "unsupported ecall"), Some(1));
+        assert_eq!(count(&report, "Traps", "ebreak"), Some(1));
+        assert_eq!(count(&report, "Traps", "illegal"), Some(1));
+    }
+
+    #[test]
+    fn syscall_names() {
+        assert_eq!(syscall_name(SYS_READ), Some("read"));
+        assert_eq!(syscall_name(SYS_WRITE), Some("write"));
+        assert_eq!(syscall_name(SYS_EXIT), Some("exit"));
+        assert_eq!(syscall_name(SYS_EXIT_GROUP), Some("exit_group"));
+        assert_eq!(syscall_name(57), None);
+    }
+}
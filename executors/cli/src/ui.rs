@@ -4,7 +4,7 @@ use std::{
 };
 
 use crossterm::{
-    event::{self, KeyCode, KeyEventKind, KeyModifiers},
+    event::{self, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     ExecutableCommand,
 };
@@ -14,9 +14,28 @@ use ratatui::{
     widgets::{Block, Borders, Clear, List, ListItem, Paragraph},
     Terminal,
 };
-use rvcore::Volatile;
+use rv_zicsr::CSRS;
+use rvasm::{branch_target, decode_instruction, encode_instruction};
+use rvcore::{
+    elf::SymbolTable,
+    snapshot::Snapshot,
+    util::parse_u32,
+    watch::{Access, Watchpoint},
+    Volatile,
+};
+
+use crate::{
+    breakpoints::{Breakpoints, Condition, ReverseStop},
+    csr,
+    emulator::{Emulator, TickResult},
+    hexdump::{self, Interpretation},
+    stats::{cache_report, predictor_report},
+};
 
-use crate::{instruction::{decode_instruction, encode_instruction}, TickResult};
+/// Bytes of guest output the console keeps
+const CONSOLE_LIMIT: usize = 64 * 1024;
+/// File the snapshot prompts start with
+const SNAPSHOT_FILE: &str = "machine.rvsnap";
 
 pub enum UIEvent {
     Nothing,
@@ -24,10 +43,70 @@ pub enum UIEvent {
     Exit,
 }
 
+#[derive(PartialEq, Eq)]
+pub enum EditTarget {
+    Register,
+    FloatRegister,
+    /// The CSR numbered `index`
+    Csr,
+    Memory,
+    /// Jump the memory pane to a symbol or address
+    Symbol,
+    /// Condition of the breakpoint at the address in `index`
+    Condition,
+    /// Adds or removes a watchpoint
+    Watchpoint,
+    /// Searches memory for a string or bytes
+    Search,
+    /// Saves a snapshot of the machine to a file
+    SaveSnapshot,
+    /// Restores the machine from a snapshot file
+    LoadSnapshot,
+}
+
+/// Where a step over, step out or run to cursor stops
+#[derive(Debug, PartialEq, Eq)]
+enum RunTarget {
+    /// Until the shadow call stack is at most this deep
+    Depth(usize),
+    /// Until the pc reaches the address
+    Address(u32),
+}
+
+impl RunTarget {
+    /// Stepping over the call at the pc stops once the frame it pushes is popped, None if it
+    /// isn't a call
+    fn step_over(emulator: &Emulator) -> Option<Self> {
+        let ins = emulator.base.bus_ref().fetch(*emulator.base.pc() as u32 as usize);
+        Emulator::is_call(ins).then_some(RunTarget::Depth(emulator.calls.len()))
+    }
+
+    /// Stepping out stops once the innermost frame is popped, None outside of a call
+    fn step_out(emulator: &Emulator) -> Option<Self> {
+        emulator.calls.len().checked_sub(1).map(RunTarget::Depth)
+    }
+
+    /// Returns true once the machine got to the target, checked after every instruction
+    fn reached(&self, emulator: &Emulator) -> bool {
+        match self {
+            RunTarget::Depth(depth) => emulator.calls.len() <= *depth,
+            RunTarget::Address(addr) => *emulator.base.pc() as u32 == *addr,
+        }
+    }
+}
+
+/// What the registers pane shows
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum RegisterView {
+    Integer,
+    Float,
+    Csr,
+}
+
 pub struct EditInfo {
     text: String,
     index: usize,
-    is_memory: bool,
+    target: EditTarget,
 }
 
 pub struct UserInterface {
@@ -35,18 +114,42 @@ pub struct UserInterface {
 
     core_hz: f32,
     continuous: bool,
+    /// Set while continuous mode runs to a target, this runs as fast as possible
+    target: Option<RunTarget>,
     stop_at_breakpoint: bool,
     message: Option<String>,
 
+    breakpoints: Breakpoints,
+    /// Stopped on a breakpoint, the next tick runs the instruction without checking it again
+    resuming: bool,
+
     edit: Option<EditInfo>,
+    symbols: SymbolTable,
 
+    /// Everything the guest printed, trimmed from the front past `CONSOLE_LIMIT`
+    console: String,
+    /// Lines scrolled up from the end of the console
+    console_scroll: usize,
+    /// Keys go to the guest instead of the debugger
+    console_input: bool,
+
+    register_view: RegisterView,
+    /// Show a hexdump in place of the instructions
+    hex_dump: bool,
+    /// Show the hottest functions and instructions in place of the instructions
+    profile_view: bool,
+    /// Show the instruction mix in place of the instructions
+    stats_view: bool,
+    interpretation: Interpretation,
+    /// The last search pattern and where it was found
+    search: Option<(Vec<u8>, usize)>,
     cursor: (i32, [i32; 2]),
     registers_scroll: usize,
     memory_scroll: usize,
 }
 
 impl UserInterface {
-    pub fn init() -> Result<Self, Box<dyn Error>> {
+    pub fn init(symbols: SymbolTable) -> Result<Self, Box<dyn Error>> {
         stdout().execute(EnterAlternateScreen)?;
         enable_raw_mode()?;
         let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
@@ -57,62 +160,103 @@ impl UserInterface {
 
             core_hz: 10.0,
             continuous: false,
+            target: None,
             stop_at_breakpoint: true,
             message: None,
 
+            breakpoints: Breakpoints::default(),
+            resuming: false,
+
             edit: None,
+            symbols,
+
+            console: String::new(),
+            console_scroll: 0,
+            console_input: false,
 
+            register_view: RegisterView::Integer,
+            hex_dump: false,
+            profile_view: false,
+            stats_view: false,
+            interpretation: Interpretation::Byte,
+            search: None,
             cursor: (0, [0; 2]),
             registers_scroll: 0,
             memory_scroll: 0,
         })
     }
 
-    pub fn render(&mut self, rv_base: &rv32i::RV32I) -> Result<(), Box<dyn Error>> {
+    pub fn render(&mut self, emulator: &Emulator) -> Result<(), Box<dyn Error>> {
+        let rv_base = &emulator.base;
         self.terminal.draw(|frame| {
             let area = frame.size();
             let sections =
                 Layout::vertical(vec![Constraint::Fill(1), Constraint::Length(1)]).split(area);
             let footer = sections[1];
+            // float registers and CSRs need room for the decoded value
+            let registers_width = if self.register_view == RegisterView::Integer { 20 } else { 35 };
             let sections = Layout::horizontal(vec![
-                Constraint::Percentage(20),
+                Constraint::Percentage(registers_width),
                 Constraint::Percentage(30),
-                Constraint::Percentage(50),
+                Constraint::Percentage(70 - registers_width),
             ])
             .split(sections[0]);
+            let right = Layout::vertical(vec![Constraint::Fill(1), Constraint::Percentage(30)]).split(sections[2]);
 
-            {
-                let mut text = format!(
-                    " [q] Quit | [s] Step | [space] continuous | [b] stop at breakpoint || continuous: {}",
-                    self.continuous
-                );
+            let breakpoint_lines: usize = self.breakpoints.iter().map(|b| 1 + b.condition.is_some() as usize).sum::<usize>()
+                + rv_base.bus_ref().watchpoints().len();
+            let left = Layout::vertical(vec![
+                Constraint::Fill(1),
+                Constraint::Length(breakpoint_lines.clamp(1, 8) as u16 + 2),
+            ])
+            .split(sections[0]);
 
+            {
+                // the status comes first so narrow terminals cut off the key help instead
+                let mut text = format!(" continuous: {}", self.continuous);
                 if let Some(message) = self.message.as_ref() {
                     text = format!("{} || {}", text, message);
                 }
 
+                text = format!(
+                    "{} || [q] Quit | [s] Step | [space] continuous | [b] stop at ebreak | [f] float | [c] CSRs | [g] go to | [x] hexdump | [/] search | [o] step over | [u] step out | [r] run to cursor | [p] step back | [R] reverse continue | [t] breakpoint | [e] condition | [w] watchpoint | [S] save snapshot | [L] load snapshot | [P] profile | [M] instruction mix",
+                    text
+                );
+
                 frame.render_widget(Paragraph::new(text).white(), footer);
             }
 
             let registers = {
-                let height = sections[0].height as i32 - 3;
+                let rows = if self.register_view == RegisterView::Csr { CSRS.len() } else { 32 };
+                let height = left[0].height as i32 - 3;
                 if self.cursor.0 == 0 {
                     if self.cursor.1[0] > height
-                        && self.registers_scroll < 31usize.saturating_sub(height as usize)
+                        && self.registers_scroll < (rows - 1).saturating_sub(height as usize)
                     {
                         self.registers_scroll += 1;
                     } else if self.cursor.1[0] < 0 && self.registers_scroll > 0 {
                         self.registers_scroll -= 1;
                     }
 
-                    self.cursor.1[0] = self.cursor.1[0].clamp(0, height.min(31));
+                    self.cursor.1[0] = self.cursor.1[0].clamp(0, height.min(rows as i32 - 1));
                 }
 
                 let items = (self.registers_scroll
-                    ..(self.registers_scroll + area.height as usize).min(32))
+                    ..(self.registers_scroll + area.height as usize).min(rows))
                     .map(|i| {
-                        let value = rv_base.get(i);
-                        let mut widget = ListItem::new(Text::raw(format!("{:2}: {}", i, value)));
+                        let text = match self.register_view {
+                            RegisterView::Integer => format!("{:2}: {}", i, rv_base.get(i)),
+                            RegisterView::Float => {
+                                let value = emulator.rv_f.get(i);
+                                format!("f{:<2} {:>12} {:08x} {}", i, value, value.to_bits(), float_class(value))
+                            }
+                            RegisterView::Csr => {
+                                let (number, name) = CSRS[i];
+                                let value = emulator.csr(number);
+                                format!("{:<9} {:#010x} {}", name, value, csr::fields(number, value))
+                            }
+                        };
+                        let mut widget = ListItem::new(Text::raw(text));
                         if self.cursor.0 == 0
                             && self.cursor.1[0] + self.registers_scroll as i32 == i as i32
                         {
@@ -123,18 +267,60 @@ impl UserInterface {
                     });
 
                 let selected = self.cursor.1[0] + self.registers_scroll as i32;
-                let block = Block::default()
-                    .borders(Borders::ALL)
-                    .title_top("Registers")
-                    .title_bottom(format!("{}/{}", selected + 1, 32));
+                let block = Block::default().borders(Borders::ALL);
+                let block = match self.register_view {
+                    RegisterView::Integer => block
+                        .title_top("Registers")
+                        .title_bottom(format!("{}/{}", selected + 1, rows)),
+                    RegisterView::Float => {
+                        let fcsr = emulator.rv_f.fcsr();
+                        block
+                            .title_top("Float Registers")
+                            .title_bottom(format!("{}/{}───frm: {}───fflags: {}", selected + 1, rows, csr::rounding_mode(fcsr), csr::float_flags(fcsr)))
+                    }
+                    RegisterView::Csr => {
+                        let title = if emulator.isa.zicsr { "CSRs" } else { "CSRs (Zicsr disabled)" };
+                        block
+                            .title_top(title)
+                            .title_bottom(format!("{}/{}", selected + 1, rows))
+                    }
+                };
+                List::new(items).block(block)
+            };
+
+            let breakpoints = {
+                let items = self.breakpoints.iter().map(|breakpoint| {
+                    let mut line = format!("{:08x} hits: {}", breakpoint.addr, breakpoint.hits);
+                    if let Some(symbol) = self.symbols.describe(breakpoint.addr) {
+                        line = format!("{:08x} <{}> hits: {}", breakpoint.addr, symbol, breakpoint.hits);
+                    }
+
+                    let mut text = Text::raw(line);
+                    if let Some(condition) = &breakpoint.condition {
+                        text.push_line(format!("  if {}", condition));
+                    }
+                    if *rv_base.pc() as u32 == breakpoint.addr {
+                        text = text.on_blue();
+                    }
+
+                    ListItem::new(text)
+                });
+                let watchpoints = rv_base.bus_ref().watchpoints().iter().map(|watchpoint| {
+                    ListItem::new(Text::raw(format!("{}", watchpoint)))
+                });
+
+                let block = Block::default().borders(Borders::ALL).title_top("Breakpoints");
+                let items: Vec<ListItem> = items.chain(watchpoints).collect();
                 List::new(items).block(block)
             };
 
+            // (address, value) of each visible word
+            let base = rv_base.bus_ref().base();
             let visible_memory: Vec<(usize, u32)> = {
                 let dram_size = rv_base.bus_ref().dram.size() / 4;
                 (self.memory_scroll
                     ..(self.memory_scroll + area.height as usize).min(dram_size)).map(|i| {
-                        (i*4, rv_base.bus_ref().dram.load(i * 4, 32))
+                        (base + i*4, rv_base.bus_ref().dram.load(i * 4, 32))
                     }).collect()
             };
 
@@ -149,17 +335,19 @@ impl UserInterface {
                         self.memory_scroll -= 1;
                     }
 
-                    self.cursor.1[1] = self.cursor.1[1].clamp(0, height);
+                    // memory may be smaller than the pane
+                    let last = height.min(dram_size as i32 - 1).max(0);
+                    self.cursor.1[1] = self.cursor.1[1].clamp(0, last);
                     self.memory_scroll =
-                        self.memory_scroll.clamp(0, dram_size - height as usize - 1);
+                        self.memory_scroll.min(dram_size.saturating_sub(height as usize + 1));
                 }
 
                 let items = visible_memory.iter()
                     .map(|(i, v)| {
                         let mut text =
-                            Text::raw(format!("{}: {}", i, v));
+                            Text::raw(format!("{:08x}: {}", i, v));
                         if self.cursor.0 == 1
-                            && self.cursor.1[1] + self.memory_scroll as i32 == *i as i32 / 4
+                            && self.cursor.1[1] + self.memory_scroll as i32 == ((*i - base) / 4) as i32
                         {
                             text = text.on_dark_gray();
                         } else if *rv_base.pc() as usize == *i {
@@ -169,10 +357,16 @@ impl UserInterface {
                         text
                     });
 
+                let pc = *rv_base.pc() as u32;
+                let title = match self.symbols.describe(pc) {
+                    Some(symbol) => format!("Memory───PC: {:#x} <{}>", pc, symbol),
+                    None => format!("Memory───PC: {:#x}", pc),
+                };
+
                 let selected = self.cursor.1[1] + self.memory_scroll as i32;
                 let block = Block::default()
                     .borders(Borders::ALL)
-                    .title_top(format!("Memory───PC: {}", rv_base.pc()))
+                    .title_top(title)
                     .title_bottom(format!("{}/{}", selected + 1, dram_size));
 
                 List::new(items).block(block)
@@ -180,9 +374,21 @@ impl UserInterface {
 
             let instructions = {
                 let items = visible_memory.iter().map(|(i, v)| {
-                    let ins = decode_instruction(*v);
-                    let mut text = Text::raw(format!("{}: {}", i, ins));
-                    if self.cursor.0 == 1 && self.cursor.1[1] + self.memory_scroll as i32 == *i as i32 / 4 {
+                    let addr = *i as u32;
+                    let mut ins = decode_instruction(*v);
+                    if let Some(target) = branch_target(*v, addr).and_then(|t| self.symbols.describe(t)) {
+                        ins = format!("{} <{}>", ins, target);
+                    }
+
+                    // the first symbol starting here, functions before labels
+                    let label = match self.symbols.at(addr).next() {
+                        Some(symbol) => format!("{}:", symbol.name),
+                        None => String::new(),
+                    };
+
+                    let marker = if self.breakpoints.contains(addr) { Span::raw("● ").red() } else { Span::raw("  ") };
+                    let mut text = Text::from(Line::from(vec![marker, Span::raw(format!("{:08x}: {:<16} {}", addr, label, ins))]));
+                    if self.cursor.0 == 1 && self.cursor.1[1] + self.memory_scroll as i32 == ((*i - base) / 4) as i32 {
                         text = text.on_dark_gray();
                     } else if *rv_base.pc()  as usize == *i {
                         text = text.on_blue();
@@ -191,16 +397,140 @@ impl UserInterface {
                     text
                 });
                 
-                let block = Block::default().borders(Borders::ALL).title_top("Instructions");
+                // the innermost frames of the shadow call stack
+                let calls: Vec<String> = emulator.calls.iter().rev().take(4).rev().map(|frame| {
+                    match self.symbols.describe(frame.function) {
+                        Some(symbol) => symbol.to_string(),
+                        None => format!("{:#x}", frame.function),
+                    }
+                }).collect();
+                let title = match calls.len() {
+                    0 => "Instructions".to_string(),
+                    _ => format!("Instructions───{}", calls.join(" > ")),
+                };
+
+                let block = Block::default().borders(Borders::ALL).title_top(title);
                 List::new(items).block(block)
             };
 
-            frame.render_widget(registers, sections[0]);
+            frame.render_widget(registers, left[0]);
+            frame.render_widget(breakpoints, left[1]);
             frame.render_widget(memory, sections[1]);
-            frame.render_widget(instructions, sections[2]);
+            if self.hex_dump {
+                // rows start at the top of the memory pane, aligned to a row
+                let start = (base + self.memory_scroll * 4) & !(hexdump::ROW - 1);
+                let selected = base + (self.memory_scroll + self.cursor.1[1] as usize) * 4;
+                let pc = *rv_base.pc() as u32 as usize;
+                let found = self.search.as_ref().map(|(pattern, addr)| (*addr, pattern.len()));
+
+                let style = |addr: usize, len: usize| {
+                    let overlaps = |start: usize, size: usize| addr < start + size && start < addr + len;
+                    if self.cursor.0 == 1 && overlaps(selected, 4) {
+                        Style::default().on_dark_gray()
+                    } else if found.is_some_and(|(start, size)| overlaps(start, size)) {
+                        Style::default().black().on_yellow()
+                    } else if overlaps(pc, 4) {
+                        Style::default().on_blue()
+                    } else {
+                        Style::default()
+                    }
+                };
+
+                let lines: Vec<Line> = (0..right[0].height.saturating_sub(2) as usize)
+                    .map(|row| start + row * hexdump::ROW)
+                    .filter_map(|addr| {
+                        let bytes = rv_base.bus_ref().load_bytes(addr, hexdump::ROW).ok()?;
+                        Some(hexdump::row(addr, bytes, self.interpretation, style))
+                    })
+                    .collect();
+
+                let block = Block::default()
+                    .borders(Borders::ALL)
+                    .title_top(format!("Hexdump───{}", self.interpretation.name()))
+                    .title_bottom("[i] interpretation | [n] next match");
+                frame.render_widget(Paragraph::new(lines).block(block), right[0]);
+            } else if let (true, Some(profiler)) = (self.profile_view, &emulator.profiler) {
+                let total = profiler.samples.max(1) as f64;
+                let percent = |count: u64| 100.0 * count as f64 / total;
+                // half the pane for functions, the rest for instructions
+                let rows = (right[0].height.saturating_sub(6) / 2) as usize;
+
+                let mut lines = vec![Line::from(format!("{:>10} {:>6} {:>10} {:>6}  Function", "Self", "%", "Total", "%")).bold()];
+                for function in profiler.functions(&self.symbols).iter().take(rows) {
+                    lines.push(Line::from(format!(
+                        "{:>10} {:>5.1}% {:>10} {:>5.1}%  {}",
+                        function.exclusive, percent(function.exclusive), function.inclusive, percent(function.inclusive), function.name
+                    )));
+                }
+
+                let mut pcs: Vec<(u32, u64)> = profiler.pcs().into_iter().collect();
+                pcs.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+                lines.push(Line::raw(""));
+                lines.push(Line::from(format!("{:>10} {:>6}  Instruction", "Count", "%")).bold());
+                for (pc, count) in pcs.into_iter().take(rows) {
+                    let location = match self.symbols.describe(pc) {
+                        Some(symbol) => format!("{:08x} <{}>", pc, symbol),
+                        None => format!("{:08x}", pc),
+                    };
+                    let ins = decode_instruction(rv_base.bus_ref().fetch(pc as usize));
+                    lines.push(Line::from(format!("{:>10} {:>5.1}%  {:<32} {}", count, percent(count), location, ins)));
+                }
+
+                let block = Block::default()
+                    .borders(Borders::ALL)
+                    .title_top(format!("Profile───{} samples", profiler.samples))
+                    .title_bottom("[P] instructions | --profile FILE writes folded stacks for flamegraphs");
+                frame.render_widget(Paragraph::new(lines).block(block), right[0]);
+            } else if let (true, Some(stats)) = (self.stats_view, &emulator.stats) {
+                let block = Block::default()
+                    .borders(Borders::ALL)
+                    .title_top("Instruction mix")
+                    .title_bottom("[M] instructions | --stats prints this after a headless run");
+                // caches and predictors first, the lists of mnemonics and branches run past the bottom
+                let mut report = stats.report();
+                if let Some(predictors) = &emulator.predictors {
+                    report = format!("{}\n{}", predictor_report(predictors, &self.symbols), report);
+                }
+                if let Some(caches) = &emulator.caches {
+                    report = format!("{}\n{}", cache_report(caches), report);
+                }
+                frame.render_widget(Paragraph::new(report).block(block), right[0]);
+            } else {
+                frame.render_widget(instructions, right[0]);
+            }
+
+            {
+                let lines: Vec<&str> = self.console.lines().collect();
+                let height = right[1].height.saturating_sub(2) as usize;
+                self.console_scroll = self.console_scroll.min(lines.len().saturating_sub(height));
+
+                let end = lines.len() - self.console_scroll;
+                let text = lines[end.saturating_sub(height)..end].join("\n");
+
+                let mut block = Block::default().borders(Borders::ALL);
+                block = if self.console_input {
+                    block.title_top("Console───typing to the guest, [Esc] to stop").border_style(Style::default().yellow())
+                } else {
+                    block.title_top("Console───[Tab] to type, [PgUp]/[PgDn] to scroll")
+                };
+                if self.console_scroll > 0 {
+                    block = block.title_bottom(format!("{} lines up", self.console_scroll));
+                }
+
+                frame.render_widget(Paragraph::new(text).block(block), right[1]);
+            }
 
             if let Some(info) = &self.edit {
-                let popup_block = Block::default().title("Edit value").borders(Borders::ALL).style(Style::default().bg(Color::DarkGray));
+                let title = match info.target {
+                    EditTarget::Symbol => "Go to symbol or hex address",
+                    EditTarget::Search => "Search for `\"text\"` or hex bytes like `de ad be ef`",
+                    EditTarget::Condition => "Breakpoint condition, like `x10 == 0 && hits > 2`",
+                    EditTarget::Watchpoint => "Watchpoint, like `w buffer 16`, `r 0x100` or `rw 0x100 8`",
+                    EditTarget::SaveSnapshot => "Save a snapshot to",
+                    EditTarget::LoadSnapshot => "Load a snapshot from",
+                    _ => "Edit value",
+                };
+                let popup_block = Block::default().title(title).borders(Borders::ALL).style(Style::default().bg(Color::DarkGray));
 
                 let x = 40;
                 let layout = Layout::vertical([Constraint::Fill(1), Constraint::Length(3), Constraint::Fill(1)]).split(area);
@@ -216,8 +546,12 @@ impl UserInterface {
         Ok(())
     }
 
-    pub fn event(&mut self, rv_base: &mut rv32i::RV32I) -> Result<UIEvent, Box<dyn Error>> {
-        let timeout = 1.0 / if self.continuous {
+    pub fn event(&mut self, emulator: &mut Emulator) -> Result<UIEvent, Box<dyn Error>> {
+        let rv_base = &mut emulator.base;
+        let timeout = 1.0 / if self.target.is_some() {
+            // only poll for keys between batches
+            1000.0
+        } else if self.continuous {
             self.core_hz
         } else {
             10.0
@@ -225,6 +559,9 @@ impl UserInterface {
         
         if event::poll(std::time::Duration::from_secs_f32(timeout))? {
             match event::read()? {
+                event::Event::Key(key) if key.kind == KeyEventKind::Press && self.console_input => {
+                    self.console_key(key, emulator);
+                }
                 event::Event::Key(key) if key.kind == KeyEventKind::Press => match &mut self.edit {
                     Some(info) => {
                         match key.code {
@@ -238,6 +575,111 @@ impl UserInterface {
                             KeyCode::Esc => {
                                 self.edit = None;
                             }
+                            KeyCode::Enter if info.target == EditTarget::Symbol => {
+                                let text = info.text.trim();
+                                // bare numbers are hex, like the addresses shown
+                                let addr = match self.symbols.get(text) {
+                                    Some(symbol) => symbol.addr,
+                                    None => match parse_u32(text).filter(|_| text.starts_with("0x") || text.starts_with("0b")).or(u32::from_str_radix(text, 16).ok()) {
+                                        Some(addr) => addr,
+                                        None => {
+                                            self.message = Some(format!("Unknown symbol `{}`", info.text.trim()));
+                                            self.edit = None;
+                                            return Ok(UIEvent::Nothing);
+                                        }
+                                    },
+                                };
+
+                                self.go_to(addr as usize, rv_base);
+                                self.edit = None;
+                            }
+                            KeyCode::Enter if info.target == EditTarget::Search => {
+                                match hexdump::parse_pattern(&info.text) {
+                                    Ok(pattern) => {
+                                        self.edit = None;
+                                        self.search = Some((pattern, self.selected_address(rv_base) as usize));
+                                        self.find_next(rv_base, false);
+                                    }
+                                    Err(error) => self.message = Some(error),
+                                }
+                            }
+                            KeyCode::Enter if info.target == EditTarget::Watchpoint => {
+                                match parse_watchpoint(&info.text, &self.symbols) {
+                                    // entering an existing watchpoint removes it
+                                    Ok(watchpoint) => {
+                                        let bus = rv_base.bus();
+                                        if !bus.remove_watchpoint(&watchpoint) {
+                                            bus.add_watchpoint(watchpoint);
+                                        }
+                                        self.message = None;
+                                        self.edit = None;
+                                    }
+                                    Err(error) => self.message = Some(error),
+                                }
+                            }
+                            KeyCode::Enter if info.target == EditTarget::SaveSnapshot => {
+                                let path = info.text.trim();
+                                self.message = Some(match std::fs::write(path, emulator.snapshot().to_bytes()) {
+                                    Ok(()) => format!("Saved a snapshot to `{}`", path),
+                                    Err(error) => format!("Failed to save `{}`: {}", path, error),
+                                });
+                                self.edit = None;
+                            }
+                            KeyCode::Enter if info.target == EditTarget::LoadSnapshot => {
+                                let path = info.text.trim();
+                                let result = std::fs::read(path)
+                                    .map_err(|error| error.to_string())
+                                    .and_then(|bytes| {
+                                        let memory = emulator.base.bus_ref().dram.size();
+                                        Snapshot::from_bytes(&bytes, memory).map_err(|error| error.to_string())
+                                    })
+                                    .and_then(|snapshot| emulator.restore(&snapshot).map_err(|error| error.to_string()));
+
+                                self.message = Some(match result {
+                                    Ok(()) => format!("Loaded the snapshot `{}`", path),
+                                    Err(error) => format!("Failed to load `{}`: {}", path, error),
+                                });
+                                self.continuous = false;
+                                self.target = None;
+                                self.resuming = false;
+                                self.edit = None;
+                            }
+                            KeyCode::Enter if info.target == EditTarget::Condition => {
+                                let condition = match info.text.trim() {
+                                    "" => None,
+                                    text => match Condition::parse(text) {
+                                        Ok(condition) => Some(condition),
+                                        Err(error) => {
+                                            self.message = Some(error);
+                                            return Ok(UIEvent::Nothing);
+                                        }
+                                    },
+                                };
+
+                                if let Some(breakpoint) = self.breakpoints.get_mut(info.index as u32) {
+                                    breakpoint.condition = condition;
+                                }
+                                self.message = None;
+                                self.edit = None;
+                            }
+                            KeyCode::Enter if info.target == EditTarget::FloatRegister => {
+                                // hex is taken as the raw bits
+                                let value = match info.text.trim() {
+                                    text if text.starts_with("0x") => parse_u32(text).map(f32::from_bits),
+                                    text => text.parse().ok(),
+                                };
+
+                                if let Some(value) = value {
+                                    emulator.rv_f.set(info.index, value);
+                                    self.edit = None;
+                                }
+                            }
+                            KeyCode::Enter if info.target == EditTarget::Csr => {
+                                if let Some(value) = parse_u32(info.text.trim()) {
+                                    emulator.set_csr(info.index, value);
+                                    self.edit = None;
+                                }
+                            }
                             KeyCode::Enter => {
                                 let number = if let Ok(number) = info.text.parse::<u32>() {
                                     number
@@ -247,8 +689,8 @@ impl UserInterface {
                                     return Ok(UIEvent::Nothing);
                                 };
                                 
-                                if info.is_memory {
-                                    rv_base.bus().dram.store(info.index * 4, 32, number as u32);
+                                if info.target == EditTarget::Memory {
+                                    rv_base.bus().dram.store(info.index * 4, 32, number);
                                 } else {
                                     rv_base.set(info.index, number as i32);
                                 }
@@ -267,6 +709,35 @@ impl UserInterface {
 
                             KeyCode::Char(' ') => {
                                 self.continuous = !self.continuous;
+                                self.target = None;
+                            }
+                            KeyCode::Char('p') => {
+                                self.continuous = false;
+                                self.target = None;
+                                // the instruction we're back at runs without stopping on its breakpoint
+                                self.message = match self.breakpoints.step_back(emulator, &mut self.resuming) {
+                                    Some(_) => None,
+                                    None => Some(history_end(emulator)),
+                                };
+                            }
+                            KeyCode::Char('R') => {
+                                self.reverse_continue(emulator);
+                            }
+                            KeyCode::Char('o') => match RunTarget::step_over(emulator) {
+                                Some(target) => self.run_to(target),
+                                // anything else is a single step
+                                None => {
+                                    self.continuous = false;
+                                    return Ok(UIEvent::Tick);
+                                }
+                            },
+                            KeyCode::Char('u') => match RunTarget::step_out(emulator) {
+                                Some(target) => self.run_to(target),
+                                None => self.message = Some("Not inside a call".into()),
+                            },
+                            KeyCode::Char('r') if self.cursor.0 == 1 => {
+                                let addr = self.selected_address(rv_base);
+                                self.run_to(RunTarget::Address(addr));
                             }
                             KeyCode::Char('s') => {
                                 self.continuous = false;
@@ -275,6 +746,78 @@ impl UserInterface {
                             KeyCode::Char('b') => {
                                 self.stop_at_breakpoint = !self.stop_at_breakpoint;
                             }
+                            KeyCode::Char('f') => {
+                                self.set_register_view(RegisterView::Float);
+                            }
+                            KeyCode::Char('c') => {
+                                self.set_register_view(RegisterView::Csr);
+                            }
+                            KeyCode::Tab => {
+                                self.console_input = true;
+                            }
+                            KeyCode::PageUp => {
+                                self.console_scroll += 5;
+                            }
+                            KeyCode::PageDown => {
+                                self.console_scroll = self.console_scroll.saturating_sub(5);
+                            }
+                            KeyCode::Char('x') => {
+                                self.hex_dump = !self.hex_dump;
+                                self.profile_view = false;
+                                self.stats_view = false;
+                            }
+                            KeyCode::Char('P') => {
+                                self.profile_view = !self.profile_view;
+                                self.hex_dump = false;
+                                self.stats_view = false;
+                            }
+                            KeyCode::Char('M') => {
+                                self.stats_view = !self.stats_view;
+                                self.hex_dump = false;
+                                self.profile_view = false;
+                            }
+                            KeyCode::Char('i') => {
+                                self.interpretation = self.interpretation.next();
+                            }
+                            KeyCode::Char('/') => {
+                                self.edit = Some(EditInfo { text: String::new(), index: 0, target: EditTarget::Search });
+                            }
+                            KeyCode::Char('n') => {
+                                self.find_next(rv_base, true);
+                            }
+                            KeyCode::Char('g') => {
+                                self.edit = Some(EditInfo { text: String::new(), index: 0, target: EditTarget::Symbol });
+                            }
+                            KeyCode::Char('S') => {
+                                self.edit = Some(EditInfo { text: SNAPSHOT_FILE.into(), index: 0, target: EditTarget::SaveSnapshot });
+                            }
+                            KeyCode::Char('L') => {
+                                self.edit = Some(EditInfo { text: SNAPSHOT_FILE.into(), index: 0, target: EditTarget::LoadSnapshot });
+                            }
+                            KeyCode::Char('w') => {
+                                let text = if self.cursor.0 == 1 {
+                                    format!("w {:#x}", self.selected_address(rv_base))
+                                } else {
+                                    String::new()
+                                };
+                                self.edit = Some(EditInfo { text, index: 0, target: EditTarget::Watchpoint });
+                            }
+                            KeyCode::Char('t') if self.cursor.0 == 1 => {
+                                let addr = self.selected_address(rv_base);
+                                self.breakpoints.toggle(addr);
+                            }
+                            KeyCode::Char('e') if self.cursor.0 == 1 => {
+                                let addr = self.selected_address(rv_base);
+                                if !self.breakpoints.contains(addr) {
+                                    self.breakpoints.toggle(addr);
+                                }
+
+                                let text = match self.breakpoints.get_mut(addr).and_then(|b| b.condition.as_ref()) {
+                                    Some(condition) => condition.to_string(),
+                                    None => String::new(),
+                                };
+                                self.edit = Some(EditInfo { text, index: addr as usize, target: EditTarget::Condition });
+                            }
 
                             KeyCode::Up => {
                                 self.cursor.1[self.cursor.0 as usize] -= 1;
@@ -282,25 +825,32 @@ impl UserInterface {
                             KeyCode::Down => {
                                 self.cursor.1[self.cursor.0 as usize] += 1;
                             }
-                            KeyCode::Left => if self.cursor.0 > 0 {
+                            KeyCode::Left if self.cursor.0 > 0 => {
                                 self.cursor.0 -= 1;
                             }
-                            KeyCode::Right => if self.cursor.0 < 1 {
+                            KeyCode::Right if self.cursor.0 < 1 => {
                                 self.cursor.0 += 1;
                             }
 
                             KeyCode::Enter => {
                                 let index = if self.cursor.0 == 1 {
                                     self.memory_scroll + self.cursor.1[self.cursor.0 as usize] as usize
+                                } else if self.register_view == RegisterView::Csr {
+                                    // CSRs are edited by number
+                                    CSRS[self.registers_scroll + self.cursor.1[0] as usize].0
                                 } else {
                                     self.registers_scroll + self.cursor.1[self.cursor.0 as usize] as usize
                                 };
-                                let text = if self.cursor.0 == 1 {
-                                    rv_base.bus().dram.load(index * 4, 32).to_string()
+                                let (text, target) = if self.cursor.0 == 1 {
+                                    (rv_base.bus().dram.load(index * 4, 32).to_string(), EditTarget::Memory)
+                                } else if self.register_view == RegisterView::Float {
+                                    (emulator.rv_f.get(index).to_string(), EditTarget::FloatRegister)
+                                } else if self.register_view == RegisterView::Csr {
+                                    (format!("{:#x}", emulator.csr(index)), EditTarget::Csr)
                                 } else {
-                                    rv_base.get(index).to_string()
+                                    (rv_base.get(index).to_string(), EditTarget::Register)
                                 };
-                                self.edit = Some(EditInfo { text, index, is_memory: self.cursor.0 == 1 });
+                                self.edit = Some(EditInfo { text, index, target });
                             }
 
                             _ => (),
@@ -321,12 +871,149 @@ impl UserInterface {
         }
     }
 
-    pub fn tick_event(&mut self, result: TickResult) {
+    /// Switches the registers pane to `view`, or back to the integer registers if it's already shown
+    fn set_register_view(&mut self, view: RegisterView) {
+        self.register_view = if self.register_view == view { RegisterView::Integer } else { view };
+        self.registers_scroll = 0;
+        self.cursor.1[0] = 0;
+    }
+
+    /// Scrolls the memory pane so the word containing `addr` is the top row
+    fn go_to(&mut self, addr: usize, rv_base: &rv32i::RV32I) {
+        let base = rv_base.bus_ref().base();
+        self.cursor = (1, [self.cursor.1[0], 0]);
+        self.memory_scroll = addr.saturating_sub(base) / 4;
+    }
+
+    /// Searches for the last pattern, starting after the previous match if `skip` is set
+    fn find_next(&mut self, rv_base: &rv32i::RV32I, skip: bool) {
+        let Some((pattern, from)) = self.search.take() else {
+            self.message = Some("Nothing to search for, press [/] first".into());
+            return;
+        };
+
+        match rv_base.bus_ref().find(&pattern, from + skip as usize) {
+            Some(addr) => {
+                self.message = Some(format!("Found at {:#x}", addr));
+                self.go_to(addr, rv_base);
+                self.search = Some((pattern, addr));
+            }
+            None => {
+                self.message = Some("Pattern not found".into());
+                self.search = Some((pattern, from));
+            }
+        }
+    }
+
+    /// Appends guest output to the console
+    pub fn write_console(&mut self, output: &[u8]) {
+        if output.is_empty() {
+            return;
+        }
+
+        self.console.push_str(&String::from_utf8_lossy(output).replace('\r', ""));
+        if self.console.len() > CONSOLE_LIMIT {
+            let mut start = self.console.len() - CONSOLE_LIMIT;
+            while !self.console.is_char_boundary(start) {
+                start += 1;
+            }
+            self.console.drain(..start);
+        }
+    }
+
+    /// Forwards a key to the guest while typing into the console
+    fn console_key(&mut self, key: KeyEvent, emulator: &mut Emulator) {
+        let mut buffer = [0; 4];
+        let bytes: &[u8] = match key.code {
+            KeyCode::Esc => {
+                self.console_input = false;
+                return;
+            }
+            // control characters, like ^C or ^D
+            KeyCode::Char(c) if key.modifiers.intersects(KeyModifiers::CONTROL) && c.is_ascii_alphabetic() => {
+                buffer[0] = c.to_ascii_lowercase() as u8 - b'a' + 1;
+                &buffer[..1]
+            }
+            KeyCode::Char(c) => c.encode_utf8(&mut buffer).as_bytes(),
+            KeyCode::Enter => b"\n",
+            KeyCode::Tab => b"\t",
+            KeyCode::Backspace => b"\x7f",
+            _ => return,
+        };
+
+        emulator.push_input(bytes);
+    }
+
+    /// Steps back until a breakpoint or the instruction that triggered a watchpoint
+    fn reverse_continue(&mut self, emulator: &mut Emulator) {
+        self.continuous = false;
+        self.target = None;
+
+        self.message = Some(match self.breakpoints.reverse_continue(emulator, &mut self.resuming) {
+            ReverseStop::HistoryEnd => history_end(emulator),
+            ReverseStop::Watch(pc) => format!("Back before the watchpoint hit at {:#x}", pc),
+            ReverseStop::Breakpoint(pc) => format!("Back at the breakpoint at {:#x}", pc),
+        });
+    }
+
+    fn run_to(&mut self, target: RunTarget) {
+        self.continuous = true;
+        self.target = Some(target);
         self.message = None;
+    }
+
+    /// Instructions to run before drawing again
+    pub fn batch(&self) -> usize {
+        if self.target.is_some() && self.continuous {
+            10_000
+        } else {
+            1
+        }
+    }
+
+    /// Returns true if continuous mode is still running
+    pub fn running(&self) -> bool {
+        self.continuous
+    }
+
+    /// Address of the word under the memory cursor
+    fn selected_address(&self, rv_base: &rv32i::RV32I) -> u32 {
+        let index = self.memory_scroll + self.cursor.1[1] as usize;
+        (rv_base.bus_ref().base() + index * 4) as u32
+    }
+
+    /// Counts breakpoint hits before the instruction at the pc runs, returns true if it shouldn't run yet
+    pub fn check_breakpoints(&mut self, rv_base: &rv32i::RV32I) -> bool {
+        // the instruction we stopped on was already counted
+        if std::mem::take(&mut self.resuming) {
+            return false;
+        }
+
+        // stepping runs the instruction anyway
+        if self.breakpoints.hit(rv_base) && self.continuous {
+            self.continuous = false;
+            self.target = None;
+            self.resuming = true;
+            self.message = Some(format!("Breakpoint at {:#x}", *rv_base.pc() as u32));
+            return true;
+        }
+
+        false
+    }
+
+    pub fn tick_event(&mut self, result: TickResult, emulator: &Emulator) {
+        self.message = None;
+        self.check_target(emulator);
 
         match result {
             TickResult::Nothing => (),
-            TickResult::ECall => todo!("Ecall"),
+            TickResult::ECall => {
+                self.continuous = false;
+                self.message = Some("Unsupported ecall".into());
+            }
+            TickResult::WaitInput => {
+                self.message = Some("Waiting for input, press [Tab] to type into the console".into());
+            }
             TickResult::EBreak => {
                 if self.stop_at_breakpoint {
                     self.continuous = false;
@@ -334,10 +1021,95 @@ impl UserInterface {
 
                 self.message = Some("Breakpoint".into());
             }
+            TickResult::Illegal(ins) => {
+                self.continuous = false;
+                self.message = Some(format!("Illegal instruction {:#010x}", ins));
+            }
+            TickResult::Exit(source, code) => {
+                self.continuous = false;
+                self.message = Some(format!("Exited with code {} ({})", code, source));
+            }
+            TickResult::Watch(pc, hit) => {
+                self.continuous = false;
+
+                let pc = match self.symbols.describe(pc) {
+                    Some(symbol) => format!("{:#x} <{}>", pc, symbol),
+                    None => format!("{:#x}", pc),
+                };
+                self.message = Some(match hit.access {
+                    Access::Read => format!("Read {:#x} from {:#x} at pc {}", hit.new, hit.addr, pc),
+                    Access::Write => format!("Write {:#x} -> {:#x} to {:#x} at pc {}", hit.old, hit.new, hit.addr, pc),
+                });
+            }
+        }
+
+        if !self.continuous {
+            self.target = None;
+        }
+    }
+
+    /// Stops continuous mode once a step over, step out or run to cursor is done
+    fn check_target(&mut self, emulator: &Emulator) {
+        if self.target.as_ref().is_some_and(|target| target.reached(emulator)) {
+            let depth = emulator.calls.len();
+            let pc = *emulator.base.pc() as u32;
+            self.continuous = false;
+            self.target = None;
+            self.message = Some(match self.symbols.describe(pc) {
+                Some(symbol) => format!("Stopped at {:#x} <{}>, call depth {}", pc, symbol, depth),
+                None => format!("Stopped at {:#x}, call depth {}", pc, depth),
+            });
         }
     }
 }
 
+/// Why stepping back didn't go further
+fn history_end(emulator: &Emulator) -> String {
+    match &emulator.history {
+        Some(_) => "Reached the start of the history".into(),
+        None => "Stepping back is disabled by `--history 0`".into(),
+    }
+}
+
+/// The class `fclass.s` would report
+fn float_class(value: f32) -> &'static str {
+    use std::num::FpCategory;
+
+    let negative = value.is_sign_negative();
+    match value.classify() {
+        // the quiet bit is the top bit of the mantissa
+        FpCategory::Nan if value.to_bits() & (1 << 22) != 0 => "qNaN",
+        FpCategory::Nan => "sNaN",
+        FpCategory::Infinite if negative => "-inf",
+        FpCategory::Infinite => "+inf",
+        FpCategory::Zero if negative => "-zero",
+        FpCategory::Zero => "+zero",
+        FpCategory::Subnormal if negative => "-subnormal",
+        FpCategory::Subnormal => "+subnormal",
+        FpCategory::Normal if negative => "-normal",
+        FpCategory::Normal => "+normal",
+    }
+}
+
+/// Parses `KIND ADDRESS [LENGTH]`, the address may be a symbol and the length defaults to a word
+fn parse_watchpoint(text: &str, symbols: &SymbolTable) -> Result<Watchpoint, String> {
+    let mut parts = text.split_whitespace();
+    let kind = parts.next().ok_or("expected `r`, `w` or `rw`")?.parse()?;
+
+    let addr = parts.next().ok_or("expected an address")?;
+    let addr = match symbols.get(addr) {
+        Some(symbol) => symbol.addr,
+        None => parse_u32(addr).ok_or(format!("unknown symbol `{}`", addr))?,
+    };
+
+    let len = match parts.next() {
+        Some(len) => parse_u32(len).filter(|len| *len > 0).ok_or(format!("invalid length `{}`", len))?,
+        None => 4,
+    };
+
+    Ok(Watchpoint { addr: addr as usize, len: len as usize, kind })
+}
+
 impl Drop for UserInterface {
     fn drop(&mut self) {
         stdout()
@@ -346,3 +1118,84 @@ impl Drop for UserInterface {
         disable_raw_mode().expect("failed to disable raw mode");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use clap::Parser;
+
+    use super::*;
+    use crate::{setup, Args};
+
+    fn machine() -> (Emulator, SymbolTable) {
+        let args = Args::try_parse_from(["rvcli"]).unwrap();
+        let program = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scripts/calls.s");
+        setup(&args, args.isa, Some(&program)).unwrap()
+    }
+
+    /// Runs like continuous mode does until the target is reached
+    fn run_to(emulator: &mut Emulator, target: &RunTarget) {
+        for _ in 0..1000 {
+            emulator.tick();
+            if target.reached(emulator) {
+                return;
+            }
+        }
+        panic!("never reached {:?}", target);
+    }
+
+    fn pc(emulator: &Emulator) -> u32 {
+        *emulator.base.pc() as u32
+    }
+
+    #[test]
+    fn step_over() {
+        let (mut emulator, symbols) = machine();
+        let start = symbols.get("_start").unwrap().addr;
+
+        // `li` and the `auipc` of `call` step like any other instruction
+        assert_eq!(RunTarget::step_over(&emulator), None);
+        emulator.tick();
+        assert_eq!(RunTarget::step_over(&emulator), None);
+        emulator.tick();
+
+        // the `jalr` returns after outer and its calls
+        let target = RunTarget::step_over(&emulator).unwrap();
+        assert_eq!(target, RunTarget::Depth(0));
+        run_to(&mut emulator, &target);
+        assert_eq!(pc(&emulator), start + 12);
+        assert_eq!(emulator.base.get(10), 3);
+    }
+
+    #[test]
+    fn step_out() {
+        let (mut emulator, symbols) = machine();
+        let [outer, inner] = ["outer", "inner"].map(|name| symbols.get(name).unwrap().addr);
+        assert_eq!(RunTarget::step_out(&emulator), None);
+
+        // run to cursor
+        run_to(&mut emulator, &RunTarget::Address(inner));
+        assert_eq!(emulator.calls.len(), 2);
+
+        let target = RunTarget::step_out(&emulator).unwrap();
+        assert_eq!(target, RunTarget::Depth(1));
+        run_to(&mut emulator, &target);
+        assert_eq!(pc(&emulator), outer + 16);
+        assert_eq!(emulator.base.get(10), 1);
+
+        // stepping over the `jal` stays in outer
+        let target = RunTarget::step_over(&emulator).unwrap();
+        run_to(&mut emulator, &target);
+        assert_eq!(pc(&emulator), outer + 20);
+        assert_eq!(emulator.base.get(10), 2);
+
+        // the tail call returns to _start, stepping out of inner leaves both frames
+        run_to(&mut emulator, &RunTarget::Address(inner));
+        assert_eq!(emulator.calls.len(), 1);
+        let target = RunTarget::step_out(&emulator).unwrap();
+        run_to(&mut emulator, &target);
+        assert_eq!(pc(&emulator), symbols.get("_start").unwrap().addr + 12);
+        assert!(emulator.calls.is_empty());
+    }
+}
@@ -0,0 +1,21 @@
+# _start calls outer, which calls inner twice and tail calls it once more, exits with 3
+.text
+.globl _start
+_start:
+    li a0, 0
+    call outer
+    li a7, 93
+    ecall
+
+outer:
+    addi sp, sp, -4
+    sw ra, 0(sp)
+    call inner
+    jal inner
+    lw ra, 0(sp)
+    addi sp, sp, 4
+    tail inner
+
+inner:
+    addi a0, a0, 1
+    ret
@@ -0,0 +1,18 @@
+# adds up the numbers from a0 down to 1 and exits with the sum
+.text
+.globl _start
+_start:
+    li a1, 0
+loop:
+    add a1, a1, a0
+    addi a0, a0, -1
+    bnez a0, loop
+done:
+    la t0, result
+    sw a1, 0(t0)
+    mv a0, a1
+    li a7, 93
+    ecall
+
+.bss
+result: .space 4
@@ -0,0 +1,231 @@
+use std::{
+    error::Error,
+    io::{stdout, BufWriter, Write},
+    path::PathBuf,
+};
+
+use clap::Parser;
+use rvasm::{branch_target, decode};
+use rvcore::{
+    elf::{Elf, SymbolTable, PF_X},
+    util::parse_u32,
+};
+
+/// Disassembles a RISC-V ELF or raw binary
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    /// ELF or raw binary to disassemble
+    file: PathBuf,
+
+    /// Load address of raw binaries
+    #[arg(short, long, default_value = "0", value_parser = address)]
+    base: u32,
+
+    /// Only disassemble the named section
+    #[arg(short = 'j', long)]
+    section: Option<String>,
+}
+
+fn address(text: &str) -> Result<u32, String> {
+    parse_u32(text).ok_or(format!("invalid address `{}`", text))
+}
+
+/// A contiguous block of code
+struct Block<'a> {
+    name: String,
+    /// Section index, used to ignore symbols of other sections
+    section: Option<u16>,
+    addr: u32,
+    data: &'a [u8],
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    let bytes = std::fs::read(&args.file)?;
+
+    let mut out = BufWriter::new(stdout().lock());
+    list(&mut out, &args, &bytes)?;
+    Ok(())
+}
+
+/// Writes the listing of `bytes`, the contents of `args.file`
+fn list(out: &mut impl Write, args: &Args, bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+    let elf;
+    let (format, blocks, symbols) = if Elf::is_elf(bytes) {
+        elf = Elf::parse(bytes)?;
+        let mut blocks: Vec<Block> = elf
+            .sections
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.is_executable() && !s.data.is_empty())
+            .map(|(i, s)| Block {
+                name: s.name.clone(),
+                section: Some(i as u16),
+                addr: s.addr,
+                data: &s.data,
+            })
+            .collect();
+
+        // stripped of section headers, fall back to executable segments
+        if blocks.is_empty() && elf.sections.is_empty() {
+            blocks = elf
+                .segments
+                .iter()
+                .filter(|s| s.flags & PF_X != 0)
+                .map(|s| Block {
+                    name: format!("segment@{:#x}", s.addr),
+                    section: None,
+                    addr: s.addr,
+                    data: &s.data,
+                })
+                .collect();
+        }
+
+        ("elf32-littleriscv", blocks, elf.symbols.clone())
+    } else {
+        let block = Block {
+            name: ".data".into(),
+            section: None,
+            addr: args.base,
+            data: bytes,
+        };
+        ("binary", vec![block], SymbolTable::default())
+    };
+
+    writeln!(out)?;
+    writeln!(out, "{}:\tfile format {}", args.file.display(), format)?;
+
+    for block in blocks {
+        if args
+            .section
+            .as_ref()
+            .is_some_and(|name| *name != block.name)
+        {
+            continue;
+        }
+
+        writeln!(out)?;
+        writeln!(out, "Disassembly of section {}:", block.name)?;
+        disassemble(out, &block, &symbols)?;
+    }
+
+    Ok(())
+}
+
+fn disassemble(out: &mut impl Write, block: &Block, symbols: &SymbolTable) -> std::io::Result<()> {
+    let mut offset = 0;
+    while offset < block.data.len() {
+        let addr = block.addr.wrapping_add(offset as u32);
+        let in_block = |section| block.section.is_none_or(|index| index == section);
+        for symbol in symbols.at(addr).filter(|s| in_block(s.section)) {
+            writeln!(out)?;
+            writeln!(out, "{:08x} <{}>:", addr, symbol.name)?;
+        }
+
+        let bytes = &block.data[offset..];
+        // the low two bits of a 32-bit instruction are always set
+        let len = if bytes[0] & 0b11 == 0b11 { 4 } else { 2 };
+        if bytes.len() < len {
+            write!(out, "{:8x}:\t{}\t", addr, hex(bytes))?;
+            writeln!(out, ".byte {}", hex(bytes))?;
+            break;
+        }
+
+        write!(out, "{:8x}:\t{}\t", addr, hex(&bytes[..len]))?;
+        if len == 2 {
+            let value = u16::from_le_bytes([bytes[0], bytes[1]]);
+            writeln!(out, ".half {:#06x}", value)?;
+        } else {
+            let ins = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            match decode(ins) {
+                Some(text) => {
+                    write!(out, "{}", text)?;
+                    let target = branch_target(ins, addr).and_then(|t| symbols.describe(t));
+                    if let Some(target) = target {
+                        write!(out, " <{}>", target)?;
+                    }
+
+                    writeln!(out)?;
+                }
+                None => writeln!(out, ".word {:#010x}", ins)?,
+            }
+        }
+
+        offset += len;
+    }
+
+    Ok(())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let text: Vec<String> = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{:<11}", text.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use rvasm::Assembler;
+    use rvcore::isa::Isa;
+
+    use super::*;
+
+    const SOURCE: &str = "
+.text
+.globl _start
+_start:
+    li a0, 3
+loop:
+    addi a0, a0, -1
+    bnez a0, loop
+    jal done
+    .word 0
+done:
+    ebreak
+";
+
+    /// The listing of `args` for an ELF assembled from `source`
+    fn listing(args: &[&str], source: &str) -> String {
+        let program = Assembler::new(Isa::ALL)
+            .file("prog.s")
+            .assemble(source)
+            .unwrap();
+        let bytes = program.to_elf().to_bytes();
+        let args = Args::try_parse_from([&["rvdis", "prog.elf"], args].concat()).unwrap();
+
+        let mut out = Vec::new();
+        list(&mut out, &args, &bytes).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn elf_listing() {
+        let listing = listing(&[], SOURCE);
+        let expected = [
+            "",
+            "prog.elf:\tfile format elf32-littleriscv",
+            "",
+            "Disassembly of section .text:",
+            "",
+            "00000000 <_start>:",
+            "       0:\t13 05 30 00\taddi x10, x0, 3",
+            "",
+            "00000004 <loop>:",
+            "       4:\t13 05 f5 ff\taddi x10, x10, -1",
+            "       8:\te3 1e 05 fe\tbne x10, x0, -4 <loop>",
+            "       c:\tef 00 80 00\tjal x1, 8 <done>",
+            "      10:\t00 00      \t.half 0x0000",
+            "      12:\t00 00      \t.half 0x0000",
+            "",
+            "00000014 <done>:",
+            "      14:\t73 00 10 00\tebreak",
+        ];
+        assert_eq!(listing.lines().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn section_filter() {
+        assert!(listing(&["-j", ".text"], SOURCE).contains("<_start>:"));
+        assert!(!listing(&["-j", ".data"], SOURCE).contains("Disassembly"));
+    }
+}
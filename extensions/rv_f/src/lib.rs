@@ -3,10 +3,10 @@ mod rv32;
 pub use rv32::RV32F;
 use rvcore::ins::{IType, RType, SType};
 
-const OPCODE_LOADF: u32 = 0b0000111;
-const OPCODE_STOREF: u32 = 0b0100111;
-const OPCODE_OPFP: u32 = 0b1010011;
+pub const OPCODE_LOADF: u32 = 0b0000111;
+pub const OPCODE_STOREF: u32 = 0b0100111;
+pub const OPCODE_OPFP: u32 = 0b1010011;
 
-type TypeLoadF = IType;
-type TypeStoreF = SType;
-type TypeOpFp = RType;
+pub type TypeLoadF = IType;
+pub type TypeStoreF = SType;
+pub type TypeOpFp = RType;
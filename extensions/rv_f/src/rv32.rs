@@ -1,5 +1,5 @@
 use rv32i::RV32I;
-use rvcore::{ins::OPCODE_MASK, EResult, Extension, Volatile};
+use rvcore::{ins::OPCODE_MASK, snapshot::Snapshot, EResult, Extension, Volatile};
 
 use crate::{TypeLoadF, TypeOpFp, TypeStoreF, OPCODE_LOADF, OPCODE_OPFP, OPCODE_STOREF};
 
@@ -25,9 +25,40 @@ fn round(value: f32, rm: u8) -> f32 {
 #[derive(Default)]
 pub struct RV32F {
     registers: [f32; 32],
+    // TODO: raise the exception flags, only the guest sets them for now
     fcsr: u32,
 }
 
+impl RV32F {
+    /// Exception flags in bits 0-4, rounding mode in bits 5-7
+    pub fn fcsr(&self) -> u32 {
+        self.fcsr
+    }
+
+    pub fn set_fcsr(&mut self, value: u32) {
+        self.fcsr = value & 0xff;
+    }
+
+    /// The rounding mode of an instruction, `frm` when it asks for the dynamic mode 7
+    fn rounding(&self, rm: u8) -> u8 {
+        if rm == 7 {
+            (self.fcsr >> 5) as u8 & 0b111
+        } else {
+            rm
+        }
+    }
+
+    pub fn save(&self, snapshot: &mut Snapshot) {
+        snapshot.float_registers = self.registers.map(f32::to_bits);
+        snapshot.fcsr = self.fcsr;
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.registers = snapshot.float_registers.map(f32::from_bits);
+        self.set_fcsr(snapshot.fcsr);
+    }
+}
+
 impl Extension<RV32I> for RV32F {
     fn execute(&mut self, ins: u32, base: &mut RV32I) -> EResult {
         match ins & OPCODE_MASK {
@@ -84,14 +115,10 @@ impl Extension<RV32I> for RV32F {
                         let rs2 = self.get(ins.rs2 as usize);
                         self.set(ins.rd as usize, rs1 / rs2);
                     }
-                    11 => {
-                        if ins.rs2 == 0 {
-                            // fsqrt.s
-                            let rs1 = self.get(ins.rs1 as usize);
-                            self.set(ins.rd as usize, rs1.sqrt());
-                        } else {
-                            return EResult::NotFound;
-                        }
+                    11 if ins.rs2 == 0 => {
+                        // fsqrt.s
+                        let rs1 = self.get(ins.rs1 as usize);
+                        self.set(ins.rd as usize, rs1.sqrt());
                     }
                     4 => {
                         let rs1 = self.get(ins.rs1 as usize);
@@ -133,12 +160,12 @@ impl Extension<RV32I> for RV32F {
                         if ins.rs2 == 0 {
                             // fcvt.w.s
                             let rs1 = self.get(ins.rs1 as usize);
-                            let rs1_r = round(rs1, rm);
+                            let rs1_r = round(rs1, self.rounding(rm));
                             base.set(ins.rd as usize, rs1_r as i32);
                         } else if ins.rs2 == 1 {
                             // rcvt.wu.s
                             let rs1 = self.get(ins.rs1 as usize);
-                            let rs1_r = round(rs1, rm);
+                            let rs1_r = round(rs1, self.rounding(rm));
                             base.set(ins.rd as usize, rs1_r as u32 as i32);
                         } else {
                             return EResult::NotFound;
@@ -204,14 +231,10 @@ impl Extension<RV32I> for RV32F {
                             return EResult::NotFound;
                         }
                     }
-                    30 => {
-                        if ins.rs2 == 0 && rm == 0 {
-                            // fmv.w.x
-                            let rs1 = base.get(ins.rs1 as usize) as u32;
-                            self.set(ins.rd as usize, f32::from_bits(rs1));
-                        } else {
-                            return EResult::NotFound;
-                        }
+                    30 if ins.rs2 == 0 && rm == 0 => {
+                        // fmv.w.x
+                        let rs1 = base.get(ins.rs1 as usize) as u32;
+                        self.set(ins.rd as usize, f32::from_bits(rs1));
                     }
 
                     _ => return EResult::NotFound,
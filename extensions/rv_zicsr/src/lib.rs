@@ -1,23 +1,161 @@
 use rv32i::RV32I;
+use rv_f::RV32F;
 use rvcore::{
-    ins::{IType, OPCODE_MASK},
+    ins::{TypeSystem, OPCODE_MASK, OPCODE_SYSTEM},
+    snapshot::Snapshot,
     EResult, Extension, Volatile,
 };
 
-const OPCODE_SYSTEM: u32 = 0b00011111;
-type TypeSystem = IType;
+pub const FFLAGS: usize = 0x001;
+pub const FRM: usize = 0x002;
+pub const FCSR: usize = 0x003;
+pub const MSTATUS: usize = 0x300;
+pub const MISA: usize = 0x301;
+pub const MIE: usize = 0x304;
+pub const MTVEC: usize = 0x305;
+pub const MSCRATCH: usize = 0x340;
+pub const MEPC: usize = 0x341;
+pub const MCAUSE: usize = 0x342;
+pub const MTVAL: usize = 0x343;
+pub const MIP: usize = 0x344;
+pub const SATP: usize = 0x180;
+pub const MHARTID: usize = 0xf14;
+pub const CYCLE: usize = 0xc00;
+pub const INSTRET: usize = 0xc02;
+pub const CYCLEH: usize = 0xc80;
+pub const INSTRETH: usize = 0xc82;
+
+/// Names of the CSRs the emulator gives a meaning to, any other number is plain storage
+pub const CSRS: [(usize, &str); 18] = [
+    (FFLAGS, "fflags"),
+    (FRM, "frm"),
+    (FCSR, "fcsr"),
+    (MSTATUS, "mstatus"),
+    (MISA, "misa"),
+    (MIE, "mie"),
+    (MTVEC, "mtvec"),
+    (MSCRATCH, "mscratch"),
+    (MEPC, "mepc"),
+    (MCAUSE, "mcause"),
+    (MTVAL, "mtval"),
+    (MIP, "mip"),
+    (SATP, "satp"),
+    (MHARTID, "mhartid"),
+    (CYCLE, "cycle"),
+    (CYCLEH, "cycleh"),
+    (INSTRET, "instret"),
+    (INSTRETH, "instreth"),
+];
 
 pub struct RVZICSR {
     registers: [u32; 4096], // 4096 registers
 }
 
+impl Default for RVZICSR {
+    fn default() -> Self {
+        Self {
+            registers: [0; 4096],
+        }
+    }
+}
+
+impl RVZICSR {
+    /// Updates the read-only counters, the emulator counts a cycle per instruction
+    pub fn set_counters(&mut self, retired: u64) {
+        for (low, high) in [(CYCLE, CYCLEH), (INSTRET, INSTRETH)] {
+            self.registers[low] = retired as u32;
+            self.registers[high] = (retired >> 32) as u32;
+        }
+    }
+
+    /// Saves the CSRs that aren't zero
+    pub fn save(&self, snapshot: &mut Snapshot) {
+        snapshot.csrs = (0..self.registers.len())
+            .filter(|i| self.registers[*i] != 0)
+            .map(|i| (i as u16, self.registers[i]))
+            .collect();
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.registers = [0; 4096];
+        for (csr, value) in &snapshot.csrs {
+            // numbers are only 12 bits
+            self.registers[*csr as usize & 0xfff] = *value;
+        }
+    }
+
+    /// Executes a CSR instruction with the floating point CSRs as views into `float`'s `fcsr`
+    ///
+    /// Without the F extension `fflags`, `frm` and `fcsr` are plain storage like any other
+    /// number, which is what [`Extension::execute`] does.
+    pub fn execute_with_float(&mut self, ins: u32, base: &mut RV32I, float: &mut RV32F) -> EResult {
+        self.run(ins, base, Some(float))
+    }
+
+    /// Reads CSR `csr`, `fflags`, `frm` and `fcsr` from `float` when there is one
+    pub fn read(&self, csr: usize, float: Option<&RV32F>) -> u32 {
+        match (csr, float) {
+            (FFLAGS, Some(float)) => float.fcsr() & 0x1f,
+            (FRM, Some(float)) => float.fcsr() >> 5 & 0b111,
+            (FCSR, Some(float)) => float.fcsr(),
+            _ => self.registers[csr],
+        }
+    }
+
+    /// Writes CSR `csr`, ignoring whether it's read-only
+    pub fn write(&mut self, csr: usize, value: u32, float: Option<&mut RV32F>) {
+        match (csr, float) {
+            (FFLAGS, Some(float)) => float.set_fcsr(float.fcsr() & !0x1f | value & 0x1f),
+            (FRM, Some(float)) => float.set_fcsr(float.fcsr() & 0x1f | (value & 0b111) << 5),
+            (FCSR, Some(float)) => float.set_fcsr(value),
+            _ => self.registers[csr] = value,
+        }
+    }
+
+    fn run(&mut self, ins: u32, base: &mut RV32I, float: Option<&mut RV32F>) -> EResult {
+        if ins & OPCODE_MASK != OPCODE_SYSTEM {
+            return EResult::NotFound;
+        }
+
+        let ins = TypeSystem::decode(ins);
+        let csr = (ins.imm & 0xfff) as usize;
+        // the `i` variants use the rs1 field as a 5 bit immediate
+        let source = if ins.funct3 & 0b100 == 0 {
+            base.get(ins.rs1 as usize) as u32
+        } else {
+            ins.rs1 as u32
+        };
+
+        let old = self.read(csr, float.as_deref());
+        let value = match ins.funct3 {
+            1 | 5 => Some(source),                            // csrrw, csrrwi
+            2 | 6 => (ins.rs1 != 0).then_some(old | source),  // csrrs, csrrsi
+            3 | 7 => (ins.rs1 != 0).then_some(old & !source), // csrrc, csrrci
+
+            _ => return EResult::NotFound,
+        };
+
+        if let Some(value) = value {
+            // the top two bits of the number are `0b11` for read-only CSRs
+            if csr >> 10 == 0b11 {
+                return EResult::NotFound;
+            }
+
+            self.write(csr, value, float);
+        }
+
+        base.set(ins.rd as usize, old as i32);
+        EResult::Found
+    }
+}
+
 impl Volatile<u32> for RVZICSR {
-    /// Sets the bit in register `index` to `value`
+    /// Sets register `index` to `value`, ignoring whether it's read-only
     fn set(&mut self, index: usize, value: u32) {
         self.registers[index] = value;
     }
 
-    /// Gets the bit in register `index`
+    /// Gets register `index`
     fn get(&self, index: usize) -> u32 {
         self.registers[index]
     }
@@ -25,43 +163,6 @@ impl Volatile<u32> for RVZICSR {
 
 impl Extension<RV32I> for RVZICSR {
     fn execute(&mut self, ins: u32, base: &mut RV32I) -> EResult {
-        match ins & OPCODE_MASK {
-            OPCODE_SYSTEM => {
-                let ins = TypeSystem::decode(ins);
-                let csr = ins.imm as usize;
-
-                match ins.funct3 {
-                    1 => {
-                        // csrrw
-                        todo!();
-                    }
-                    2 => {
-                        // csrrs
-                        todo!();
-                    }
-                    3 => {
-                        // csrrc
-                        todo!();
-                    }
-                    5 => {
-                        // csrrwi
-                        todo!();
-                    }
-                    6 => {
-                        // csrrsi
-                        todo!();
-                    }
-                    7 => {
-                        // csrrci
-                        todo!();
-                    }
-
-                    _ => return EResult::NotFound,
-                }
-            }
-            _ => return EResult::NotFound,
-        }
-
-        EResult::Found
+        self.run(ins, base, None)
     }
 }
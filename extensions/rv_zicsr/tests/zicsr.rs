@@ -0,0 +1,96 @@
+use rv32i::RV32I;
+use rv_f::RV32F;
+use rv_zicsr::{CYCLE, FCSR, FFLAGS, FRM, MSCRATCH, MSTATUS, RVZICSR};
+use rvcore::{bus::Bus, DRam, EResult, Extension, Volatile};
+
+fn csr(csr: usize, rs1: u32, funct3: u32, rd: u32) -> u32 {
+    ((csr as u32) << 20) | (rs1 << 15) | (funct3 << 12) | (rd << 7) | 0b1110011
+}
+
+#[test]
+fn read_modify_write() {
+    let mut base = RV32I::new(Bus::new(DRam::new(0x100)));
+    let mut zicsr = RVZICSR::default();
+    base.set(6, 0x88);
+
+    // csrrw x5, mscratch, x6
+    assert!(matches!(
+        zicsr.execute(csr(MSCRATCH, 6, 1, 5), &mut base),
+        EResult::Found
+    ));
+    assert_eq!(zicsr.get(MSCRATCH), 0x88);
+    assert_eq!(base.get(5), 0);
+
+    // csrrsi x5, mscratch, 3 then csrrc x7, mscratch, x6
+    assert!(matches!(
+        zicsr.execute(csr(MSCRATCH, 3, 6, 5), &mut base),
+        EResult::Found
+    ));
+    assert_eq!(base.get(5), 0x88);
+    assert!(matches!(
+        zicsr.execute(csr(MSCRATCH, 6, 3, 7), &mut base),
+        EResult::Found
+    ));
+    assert_eq!((base.get(7), zicsr.get(MSCRATCH)), (0x8b, 3));
+
+    // csrrs with x0 only reads
+    zicsr.set(MSTATUS, 0x1800);
+    assert!(matches!(
+        zicsr.execute(csr(MSTATUS, 0, 2, 8), &mut base),
+        EResult::Found
+    ));
+    assert_eq!(base.get(8), 0x1800);
+}
+
+#[test]
+fn read_only_counters() {
+    let mut base = RV32I::new(Bus::new(DRam::new(0x100)));
+    let mut zicsr = RVZICSR::default();
+    zicsr.set_counters(42);
+
+    // csrr x5, cycle
+    assert!(matches!(
+        zicsr.execute(csr(CYCLE, 0, 2, 5), &mut base),
+        EResult::Found
+    ));
+    assert_eq!(base.get(5), 42);
+
+    // csrw cycle, x5
+    assert!(matches!(
+        zicsr.execute(csr(CYCLE, 5, 1, 0), &mut base),
+        EResult::NotFound
+    ));
+}
+
+#[test]
+fn float_csrs() {
+    let mut base = RV32I::new(Bus::new(DRam::new(0x100)));
+    let mut zicsr = RVZICSR::default();
+    let mut float = RV32F::default();
+
+    // csrwi frm, 3 then csrr x5, fcsr
+    let found = zicsr.execute_with_float(csr(FRM, 3, 5, 0), &mut base, &mut float);
+    assert!(matches!(found, EResult::Found));
+    let found = zicsr.execute_with_float(csr(FCSR, 0, 2, 5), &mut base, &mut float);
+    assert!(matches!(found, EResult::Found));
+    assert_eq!(base.get(5), 3 << 5);
+    assert_eq!(float.fcsr(), 3 << 5);
+
+    // csrsi fflags, 1 leaves the rounding mode alone, csrr x6, frm reads it back
+    zicsr.execute_with_float(csr(FFLAGS, 1, 6, 0), &mut base, &mut float);
+    zicsr.execute_with_float(csr(FRM, 0, 2, 6), &mut base, &mut float);
+    assert_eq!(float.fcsr(), 3 << 5 | 1);
+    assert_eq!(base.get(6), 3);
+    assert_eq!(zicsr.get(FCSR), 0);
+
+    // fcvt.w.s x7, f1, dyn rounds up with frm set to RUP
+    float.set(1, 1.25);
+    let fcvt = (0b1100000 << 25) | (1 << 15) | (7 << 12) | (7 << 7) | 0b1010011;
+    assert!(matches!(float.execute(fcvt, &mut base), EResult::Found));
+    assert_eq!(base.get(7), 2);
+
+    // csrwi frm, 2 rounds down
+    zicsr.execute_with_float(csr(FRM, 2, 5, 0), &mut base, &mut float);
+    assert!(matches!(float.execute(fcvt, &mut base), EResult::Found));
+    assert_eq!(base.get(7), 1);
+}
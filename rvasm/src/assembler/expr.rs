@@ -0,0 +1,174 @@
+use rvcore::util::sign_extend;
+
+use super::{
+    lexer::{Tok, Token},
+    LineError,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Number(i64),
+    /// A symbol and the column it was written at
+    Symbol(String, usize),
+    Neg(Box<Expr>),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    /// `%hi(expr)`, rounded so that adding `%lo` gives back `expr`
+    Hi(Box<Expr>),
+    /// `%lo(expr)`
+    Lo(Box<Expr>),
+    /// Relative to the address of the instruction
+    PcRel(Box<Expr>),
+    /// `%pcrel_hi(expr)`
+    PcRelHi(Box<Expr>),
+    /// Low part of a pc-relative address, paired with the `auipc` right before it
+    PcRelLo(Box<Expr>),
+}
+
+impl Expr {
+    pub fn has_symbol(&self) -> bool {
+        match self {
+            Expr::Number(_) => false,
+            Expr::Symbol(..) => true,
+            Expr::Add(a, b) | Expr::Sub(a, b) => a.has_symbol() || b.has_symbol(),
+            Expr::Neg(e)
+            | Expr::Hi(e)
+            | Expr::Lo(e)
+            | Expr::PcRel(e)
+            | Expr::PcRelHi(e)
+            | Expr::PcRelLo(e) => e.has_symbol(),
+        }
+    }
+
+    /// Evaluates the expression for an instruction at `pc`
+    pub fn eval(&self, pc: u32, symbol: &dyn Fn(&str) -> Option<i64>) -> Result<i64, LineError> {
+        let eval = |e: &Expr| e.eval(pc, symbol);
+        Ok(match self {
+            Expr::Number(value) => *value,
+            Expr::Symbol(name, column) => symbol(name).ok_or_else(|| LineError {
+                column: *column,
+                token: name.clone(),
+                message: "undefined symbol".into(),
+            })?,
+            Expr::Neg(e) => -eval(e)?,
+            Expr::Add(a, b) => eval(a)? + eval(b)?,
+            Expr::Sub(a, b) => eval(a)? - eval(b)?,
+            Expr::Hi(e) => hi(eval(e)?),
+            Expr::Lo(e) => lo(eval(e)?),
+            Expr::PcRel(e) => eval(e)? - pc as i64,
+            Expr::PcRelHi(e) => hi(eval(e)? - pc as i64),
+            Expr::PcRelLo(e) => lo(eval(e)? - (pc as i64 - 4)),
+        })
+    }
+}
+
+/// The upper 20 bits for `lui`/`auipc`
+pub fn hi(value: i64) -> i64 {
+    ((value + 0x800) >> 12) & 0xfffff
+}
+
+/// The sign-extended lower 12 bits
+pub fn lo(value: i64) -> i64 {
+    sign_extend(value as u32 & 0xfff, 12) as i64
+}
+
+// ---- Parser ----
+
+/// Parses all of `tokens` as a single expression
+pub fn parse(tokens: &[Token], column: usize) -> Result<Expr, LineError> {
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.expr(column)?;
+    match parser.tokens.get(parser.pos) {
+        Some(token) => Err(LineError::at(token, "unexpected token")),
+        None => Ok(expr),
+    }
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn next(&mut self) -> Option<&Token> {
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        token
+    }
+
+    fn peek_punct(&self, c: char) -> bool {
+        self.tokens.get(self.pos).is_some_and(|t| t.is_punct(c))
+    }
+
+    /// `column` is used for errors at the end of the tokens
+    fn expr(&mut self, column: usize) -> Result<Expr, LineError> {
+        let mut expr = self.unary(column)?;
+        loop {
+            if self.peek_punct('+') {
+                self.pos += 1;
+                expr = Expr::Add(Box::new(expr), Box::new(self.unary(column)?));
+            } else if self.peek_punct('-') {
+                self.pos += 1;
+                expr = Expr::Sub(Box::new(expr), Box::new(self.unary(column)?));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn unary(&mut self, column: usize) -> Result<Expr, LineError> {
+        let Some(token) = self.next().cloned() else {
+            return Err(LineError {
+                column,
+                token: String::new(),
+                message: "expected an expression".into(),
+            });
+        };
+
+        Ok(match &token.tok {
+            Tok::Number(value) => Expr::Number(*value),
+            Tok::Ident(name) => Expr::Symbol(name.clone(), token.column),
+            Tok::Punct('-') => Expr::Neg(Box::new(self.unary(column)?)),
+            Tok::Punct('+') => self.unary(column)?,
+            Tok::Punct('(') => {
+                let expr = self.expr(column)?;
+                self.close(&token)?;
+                expr
+            }
+            Tok::Punct('%') => {
+                let function = self.next().cloned();
+                let wrap: fn(Box<Expr>) -> Expr = match function.as_ref().and_then(|t| t.ident()) {
+                    Some("hi") => Expr::Hi,
+                    Some("lo") => Expr::Lo,
+                    Some("pcrel_hi") => Expr::PcRelHi,
+                    _ => {
+                        let token = function.as_ref().unwrap_or(&token);
+                        return Err(LineError::at(token, "unknown relocation function"));
+                    }
+                };
+
+                let open = self.next().cloned();
+                if !open.as_ref().is_some_and(|t| t.is_punct('(')) {
+                    return Err(LineError::at(
+                        open.as_ref().unwrap_or(&token),
+                        "expected `(`",
+                    ));
+                }
+
+                let expr = self.expr(column)?;
+                self.close(&token)?;
+                wrap(Box::new(expr))
+            }
+
+            _ => return Err(LineError::at(&token, "expected an expression")),
+        })
+    }
+
+    fn close(&mut self, open: &Token) -> Result<(), LineError> {
+        match self.next() {
+            Some(token) if token.is_punct(')') => Ok(()),
+            Some(token) => Err(LineError::at(token, "expected `)`")),
+            None => Err(LineError::at(open, "unclosed `(`")),
+        }
+    }
+}
@@ -0,0 +1,140 @@
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tok {
+    Ident(String),
+    Number(i64),
+    Str(Vec<u8>),
+    Punct(char),
+}
+
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub tok: Tok,
+    /// Source text of the token
+    pub text: String,
+    /// 1-based column
+    pub column: usize,
+}
+
+impl Token {
+    pub fn is_punct(&self, c: char) -> bool {
+        self.tok == Tok::Punct(c)
+    }
+
+    pub fn ident(&self) -> Option<&str> {
+        match &self.tok {
+            Tok::Ident(ident) => Some(ident),
+            _ => None,
+        }
+    }
+}
+
+/// A lexing error with its column and the offending text
+pub type LexError = (usize, String, &'static str);
+
+/// Splits a line into tokens, stopping at `#` or `//` comments
+pub fn tokenize(line: &str) -> Result<Vec<Token>, LexError> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        } else if c == '#' || (c == '/' && chars.get(i + 1) == Some(&'/')) {
+            break;
+        }
+
+        let tok = if c.is_ascii_alphabetic() || matches!(c, '_' | '.' | '$') {
+            while i < chars.len()
+                && (chars[i].is_ascii_alphanumeric() || matches!(chars[i], '_' | '.' | '$'))
+            {
+                i += 1;
+            }
+
+            Tok::Ident(chars[start..i].iter().collect())
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+
+            let text: String = chars[start..i].iter().collect();
+            match rvcore::util::parse_u32(&text) {
+                Some(value) => Tok::Number(value as i64),
+                None => return Err((start + 1, text, "invalid number")),
+            }
+        } else if c == '"' {
+            i += 1;
+            let mut bytes = Vec::new();
+            loop {
+                match chars.get(i) {
+                    None => {
+                        let text = chars[start..].iter().collect();
+                        return Err((start + 1, text, "unterminated string"));
+                    }
+                    Some('"') => break,
+                    Some('\\') => {
+                        i += 1;
+                        match chars.get(i).and_then(|c| escape(*c)) {
+                            Some(c) => bytes.push(c),
+                            None => {
+                                let text = chars[i - 1..=i.min(chars.len() - 1)].iter().collect();
+                                return Err((i, text, "unknown escape sequence"));
+                            }
+                        }
+                    }
+                    Some(c) => {
+                        let mut buf = [0; 4];
+                        bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                    }
+                }
+
+                i += 1;
+            }
+
+            i += 1;
+            Tok::Str(bytes)
+        } else if c == '\'' {
+            let (value, len) = match (chars.get(i + 1), chars.get(i + 2), chars.get(i + 3)) {
+                (Some('\\'), Some(e), Some('\'')) => (escape(*e), 4),
+                (Some(c), Some('\''), _) if *c != '\\' => (Some(*c as u8), 3),
+                _ => (None, 1),
+            };
+
+            i += len;
+            match value {
+                Some(value) => Tok::Number(value as i64),
+                None => return Err((start + 1, "'".into(), "invalid character literal")),
+            }
+        } else if matches!(c, ',' | '(' | ')' | ':' | '+' | '-' | '%' | '@') {
+            i += 1;
+            Tok::Punct(c)
+        } else {
+            return Err((start + 1, c.into(), "unexpected character"));
+        };
+
+        tokens.push(Token {
+            tok,
+            text: chars[start..i].iter().collect(),
+            column: start + 1,
+        });
+    }
+
+    Ok(tokens)
+}
+
+fn escape(c: char) -> Option<u8> {
+    Some(match c {
+        'n' => b'\n',
+        't' => b'\t',
+        'r' => b'\r',
+        '0' => 0,
+        '\\' => b'\\',
+        '"' => b'"',
+        '\'' => b'\'',
+        _ => return None,
+    })
+}
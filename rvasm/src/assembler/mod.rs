@@ -0,0 +1,1124 @@
+//! A two-pass assembler for RV32 source files
+//!
+//! The first pass lays out every section, expanding pseudo-instructions into a fixed number of
+//! real ones, so that the second pass can resolve symbols and encode everything in one go.
+
+mod expr;
+mod lexer;
+mod program;
+
+pub use program::{Program, BSS_SECTION, DATA_SECTION, TEXT_SECTION};
+
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt::Display,
+};
+
+use rvcore::{
+    bus::DRAM_ADDR,
+    elf::{Symbol, SymbolKind},
+    isa::Isa,
+};
+
+use crate::encode::{encode, Arg, EncodeError};
+use expr::Expr;
+use lexer::{Tok, Token};
+
+/// `addi x0, x0, 0`
+const NOP: u32 = 0x0000_0013;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub file: String,
+    /// 1-based line
+    pub line: usize,
+    /// 1-based column
+    pub column: usize,
+    /// The offending source text, may be empty
+    pub token: String,
+    pub message: String,
+}
+
+impl Display for AsmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: error: {}",
+            self.file, self.line, self.column, self.message
+        )?;
+        if !self.token.is_empty() {
+            write!(f, " `{}`", self.token)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for AsmError {}
+
+/// An error within a single line
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LineError {
+    column: usize,
+    token: String,
+    message: String,
+}
+
+impl LineError {
+    fn new(column: usize, token: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            column,
+            token: token.into(),
+            message: message.into(),
+        }
+    }
+
+    fn at(token: &Token, message: impl Into<String>) -> Self {
+        Self::new(token.column, token.text.clone(), message)
+    }
+}
+
+// ---- Assembler ----
+
+pub struct Assembler {
+    isa: Isa,
+    origin: u32,
+    file: String,
+}
+
+impl Assembler {
+    pub fn new(isa: Isa) -> Self {
+        Self {
+            isa,
+            origin: DRAM_ADDR as u32,
+            file: "<input>".into(),
+        }
+    }
+
+    /// The address `.text` is placed at
+    pub fn origin(mut self, origin: u32) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// The file name used in errors
+    pub fn file(mut self, file: impl Into<String>) -> Self {
+        self.file = file.into();
+        self
+    }
+
+    pub fn assemble(&self, source: &str) -> Result<Program, Vec<AsmError>> {
+        let mut pass = Pass::default();
+        for (i, line) in source.lines().enumerate() {
+            pass.line = i + 1;
+            if let Err(error) = pass.parse_line(line) {
+                pass.errors.push((pass.line, error));
+            }
+        }
+
+        let program = pass.finish(self.isa, self.origin);
+        match program {
+            Ok(program) => Ok(program),
+            Err(mut errors) => {
+                errors.sort_by_key(|(line, error)| (*line, error.column));
+                errors.dedup();
+                Err(errors
+                    .into_iter()
+                    .map(|(line, error)| AsmError {
+                        file: self.file.clone(),
+                        line,
+                        column: error.column,
+                        token: error.token,
+                        message: error.message,
+                    })
+                    .collect())
+            }
+        }
+    }
+}
+
+// ---- First Pass ----
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Section {
+    #[default]
+    Text,
+    Data,
+    Bss,
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    X(u8),
+    F(u8),
+    Expr(Expr),
+    /// `offset(base)`
+    Mem(Expr, u8),
+}
+
+#[derive(Debug, Clone)]
+struct Operand {
+    value: Value,
+    column: usize,
+    text: String,
+}
+
+#[derive(Debug, Clone)]
+enum Item {
+    /// A single real instruction, `column` and `text` refer to the mnemonic as written
+    Ins {
+        mnemonic: String,
+        column: usize,
+        text: String,
+        operands: Vec<Operand>,
+    },
+    /// A `.word`, `.half` or `.byte` value
+    Data {
+        size: u32,
+        value: Operand,
+    },
+    Bytes(Vec<u8>),
+}
+
+#[derive(Debug)]
+struct Placed {
+    line: usize,
+    section: Section,
+    offset: u32,
+    item: Item,
+}
+
+#[derive(Default)]
+struct Pass {
+    line: usize,
+    section: Section,
+    /// Current size of each section
+    sizes: [u32; 3],
+    /// Largest alignment requested in each section
+    aligns: [u32; 3],
+    items: Vec<Placed>,
+    labels: Vec<(String, Section, u32)>,
+    /// `.equ` symbols, resolved after layout
+    equs: Vec<(usize, String, Expr)>,
+    /// `.equ` symbols which are known during the first pass
+    constants: HashMap<String, i64>,
+    defined: HashSet<String>,
+    globals: HashSet<String>,
+    kinds: HashMap<String, SymbolKind>,
+    /// `.size` directives with the line and location they were written at
+    symbol_sizes: Vec<(usize, String, Expr, Section, u32)>,
+    errors: Vec<(usize, LineError)>,
+}
+
+impl Pass {
+    fn parse_line(&mut self, line: &str) -> Result<(), LineError> {
+        let chars: Vec<char> = line.chars().collect();
+        let tokens = lexer::tokenize(line)
+            .map_err(|(column, token, message)| LineError::new(column, token, message))?;
+
+        let mut tokens = tokens.as_slice();
+        while let [label, colon, rest @ ..] = tokens {
+            let Some(name) = label.ident().filter(|_| colon.is_punct(':')) else {
+                break;
+            };
+
+            self.define(label, name)?;
+            self.labels
+                .push((name.into(), self.section, self.sizes[self.section as usize]));
+            tokens = rest;
+        }
+
+        let Some((first, rest)) = tokens.split_first() else {
+            return Ok(());
+        };
+        let Some(name) = first.ident() else {
+            return Err(LineError::at(first, "expected an instruction or directive"));
+        };
+
+        let end = chars.len() + 1;
+        let groups = split_operands(rest, end)?;
+        if name.starts_with('.') {
+            self.directive(first, &name.to_ascii_lowercase(), &groups, &chars)
+        } else {
+            let operands = groups
+                .iter()
+                .map(|group| parse_operand(group, &chars))
+                .collect::<Result<Vec<_>, _>>()?;
+            self.instruction(first, &name.to_ascii_lowercase(), operands)
+        }
+    }
+
+    fn define(&mut self, token: &Token, name: &str) -> Result<(), LineError> {
+        if self.defined.insert(name.into()) {
+            Ok(())
+        } else {
+            Err(LineError::at(token, "symbol already defined"))
+        }
+    }
+
+    fn push(&mut self, size: u32, item: Item) {
+        let offset = self.sizes[self.section as usize];
+        self.items.push(Placed {
+            line: self.line,
+            section: self.section,
+            offset,
+            item,
+        });
+        self.sizes[self.section as usize] += size;
+    }
+
+    fn align(&mut self, to: u32) {
+        let section = self.section as usize;
+        self.aligns[section] = self.aligns[section].max(to);
+
+        let offset = self.sizes[section];
+        let padding = offset.next_multiple_of(to) - offset;
+        if padding == 0 {
+        } else if self.section == Section::Bss {
+            self.sizes[section] += padding;
+        } else if self.section == Section::Text && offset.is_multiple_of(4) {
+            let bytes = NOP.to_le_bytes().repeat(padding as usize / 4);
+            self.push(padding, Item::Bytes(bytes));
+        } else {
+            self.push(padding, Item::Bytes(vec![0; padding as usize]));
+        }
+    }
+
+    /// Evaluates an expression using the constants defined so far
+    fn constant(&self, tokens: &[Token], end: usize) -> Result<i64, LineError> {
+        let expr = expr::parse(tokens, end)?;
+        expr.eval(0, &|name| self.constants.get(name).copied())
+    }
+
+    // ---- Directives ----
+
+    fn directive(
+        &mut self,
+        token: &Token,
+        name: &str,
+        args: &[&[Token]],
+        chars: &[char],
+    ) -> Result<(), LineError> {
+        let end = chars.len() + 1;
+        let count = |n: usize, form: &str| {
+            if args.len() == n {
+                Ok(())
+            } else {
+                Err(LineError::at(
+                    token,
+                    format!("expected `{} {}`", name, form),
+                ))
+            }
+        };
+        let symbol = |group: &[Token]| match group {
+            [token] => token
+                .ident()
+                .map(String::from)
+                .ok_or_else(|| LineError::at(token, "expected a symbol")),
+            _ => Err(LineError::new(
+                group.first().map_or(end, |t| t.column),
+                span(chars, group),
+                "expected a symbol",
+            )),
+        };
+
+        let data = matches!(
+            name,
+            ".word"
+                | ".4byte"
+                | ".long"
+                | ".half"
+                | ".2byte"
+                | ".short"
+                | ".byte"
+                | ".ascii"
+                | ".asciz"
+                | ".string"
+        );
+        if data && self.section == Section::Bss {
+            return Err(LineError::at(token, "only `.zero` is allowed in .bss"));
+        }
+
+        match name {
+            ".text" => self.section = Section::Text,
+            ".data" | ".rodata" | ".sdata" => self.section = Section::Data,
+            ".bss" | ".sbss" => self.section = Section::Bss,
+            ".section" => {
+                let Some(group) = args.first() else {
+                    return Err(LineError::at(token, "expected a section name"));
+                };
+                let section = symbol(group)?;
+                let prefix = section.split('.').nth(1).unwrap_or_default();
+                self.section = match prefix {
+                    "text" => Section::Text,
+                    "data" | "rodata" | "sdata" | "srodata" => Section::Data,
+                    "bss" | "sbss" => Section::Bss,
+                    _ => return Err(LineError::at(&group[0], "unknown section")),
+                };
+            }
+
+            ".globl" | ".global" | ".local" => {
+                for group in args {
+                    let symbol = symbol(group)?;
+                    if name == ".local" {
+                        self.globals.remove(&symbol);
+                    } else {
+                        self.globals.insert(symbol);
+                    }
+                }
+            }
+            ".type" => {
+                count(2, "symbol, @type")?;
+                let symbol = symbol(args[0])?;
+                let kind = match args[1] {
+                    [prefix, kind] if prefix.is_punct('@') || prefix.is_punct('%') => kind,
+                    [kind] => kind,
+                    group => {
+                        let text = span(chars, group);
+                        return Err(LineError::new(group[0].column, text, "unknown symbol type"));
+                    }
+                };
+
+                let kind = match kind.ident() {
+                    Some("function") => SymbolKind::Func,
+                    Some("object") => SymbolKind::Object,
+                    Some("notype") => SymbolKind::NoType,
+                    _ => return Err(LineError::at(kind, "unknown symbol type")),
+                };
+                self.kinds.insert(symbol, kind);
+            }
+            ".size" => {
+                count(2, "symbol, size")?;
+                let symbol = symbol(args[0])?;
+                let size = expr::parse(args[1], end)?;
+                let offset = self.sizes[self.section as usize];
+                self.symbol_sizes
+                    .push((self.line, symbol, size, self.section, offset));
+            }
+            ".equ" | ".set" => {
+                count(2, "symbol, value")?;
+                let symbol = symbol(args[0])?;
+                self.define(&args[0][0], &symbol)?;
+
+                let value = expr::parse(args[1], end)?;
+                if let Ok(constant) = value.eval(0, &|name| self.constants.get(name).copied()) {
+                    self.constants.insert(symbol.clone(), constant);
+                }
+                self.equs.push((self.line, symbol, value));
+            }
+
+            // ---- Data ----
+            ".word" | ".4byte" | ".long" | ".half" | ".2byte" | ".short" | ".byte" => {
+                let size = match name {
+                    ".half" | ".2byte" | ".short" => 2,
+                    ".byte" => 1,
+                    _ => 4,
+                };
+
+                for group in args {
+                    let value = Operand {
+                        value: Value::Expr(expr::parse(group, end)?),
+                        column: group[0].column,
+                        text: span(chars, group),
+                    };
+                    self.push(size, Item::Data { size, value });
+                }
+            }
+            ".ascii" | ".asciz" | ".string" => {
+                for group in args {
+                    let [Token {
+                        tok: Tok::Str(bytes),
+                        ..
+                    }] = group
+                    else {
+                        let text = span(chars, group);
+                        return Err(LineError::new(group[0].column, text, "expected a string"));
+                    };
+
+                    let mut bytes = bytes.clone();
+                    if name != ".ascii" {
+                        bytes.push(0);
+                    }
+                    self.push(bytes.len() as u32, Item::Bytes(bytes));
+                }
+            }
+            ".align" | ".p2align" | ".balign" => {
+                let Some(group) = args.first() else {
+                    return Err(LineError::at(token, "expected an alignment"));
+                };
+
+                let value = self.constant(group, end)?;
+                let to = if name == ".balign" {
+                    value
+                } else if (0..16).contains(&value) {
+                    1 << value
+                } else {
+                    0
+                };
+
+                if to <= 0 || to > 1 << 15 || to & (to - 1) != 0 {
+                    let text = span(chars, group);
+                    return Err(LineError::new(group[0].column, text, "invalid alignment"));
+                }
+                self.align(to as u32);
+            }
+            ".zero" | ".space" | ".skip" => {
+                let (size, fill) = match args {
+                    [size] => (self.constant(size, end)?, 0),
+                    [size, fill] => (self.constant(size, end)?, self.constant(fill, end)?),
+                    _ => return Err(LineError::at(token, format!("expected `{} size`", name))),
+                };
+
+                if !(0..1 << 24).contains(&size) {
+                    let text = span(chars, args[0]);
+                    return Err(LineError::new(args[0][0].column, text, "invalid size"));
+                }
+
+                if self.section == Section::Bss {
+                    self.sizes[Section::Bss as usize] += size as u32;
+                } else {
+                    self.push(size as u32, Item::Bytes(vec![fill as u8; size as usize]));
+                }
+            }
+
+            ".option" | ".file" | ".ident" | ".attribute" | ".addrsig" => (),
+            _ => return Err(LineError::at(token, "unknown directive")),
+        }
+
+        Ok(())
+    }
+
+    // ---- Instructions ----
+
+    fn instruction(
+        &mut self,
+        token: &Token,
+        mnemonic: &str,
+        operands: Vec<Operand>,
+    ) -> Result<(), LineError> {
+        if self.section == Section::Bss {
+            return Err(LineError::at(token, "instructions are not allowed in .bss"));
+        }
+
+        for (mnemonic, mut operands) in self.expand(token, mnemonic, operands)? {
+            let last = operands.last_mut().map(|o| &mut o.value);
+            match (mnemonic.as_str(), last) {
+                // symbolic targets are relative to the instruction
+                ("jal" | "beq" | "bne" | "blt" | "bge" | "bltu" | "bgeu", Some(Value::Expr(e)))
+                    if e.has_symbol() =>
+                {
+                    *e = Expr::PcRel(Box::new(e.clone()));
+                }
+
+                ("csrrw" | "csrrs" | "csrrc" | "csrrwi" | "csrrsi" | "csrrci", _) => {
+                    if let Some(Value::Expr(Expr::Symbol(name, _))) =
+                        operands.get(1).map(|o| &o.value)
+                    {
+                        if let Some(csr) = csr(name) {
+                            operands[1].value = Value::Expr(Expr::Number(csr));
+                        }
+                    }
+                }
+
+                _ => (),
+            }
+
+            self.push(
+                4,
+                Item::Ins {
+                    mnemonic,
+                    column: token.column,
+                    text: token.text.clone(),
+                    operands,
+                },
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Expands pseudo-instructions, real instructions are returned as is
+    fn expand(
+        &self,
+        token: &Token,
+        mnemonic: &str,
+        operands: Vec<Operand>,
+    ) -> Result<Vec<(String, Vec<Operand>)>, LineError> {
+        let synth = |value| Operand {
+            value,
+            column: token.column,
+            text: token.text.clone(),
+        };
+        let x = |reg| synth(Value::X(reg));
+        let num = |value| synth(Value::Expr(Expr::Number(value)));
+        let with = |operand: &Operand, value| Operand {
+            value,
+            ..operand.clone()
+        };
+        let ins = |mnemonic: &str, operands: Vec<Operand>| (mnemonic.to_string(), operands);
+
+        Ok(match (mnemonic, operands.as_slice()) {
+            ("nop", []) => vec![ins("addi", vec![x(0), x(0), num(0)])],
+            ("mv", [rd, rs]) => vec![ins("addi", vec![rd.clone(), rs.clone(), num(0)])],
+            ("not", [rd, rs]) => vec![ins("xori", vec![rd.clone(), rs.clone(), num(-1)])],
+            ("neg", [rd, rs]) => vec![ins("sub", vec![rd.clone(), x(0), rs.clone()])],
+            ("seqz", [rd, rs]) => vec![ins("sltiu", vec![rd.clone(), rs.clone(), num(1)])],
+            ("snez", [rd, rs]) => vec![ins("sltu", vec![rd.clone(), x(0), rs.clone()])],
+            ("sltz", [rd, rs]) => vec![ins("slt", vec![rd.clone(), rs.clone(), x(0)])],
+            ("sgtz", [rd, rs]) => vec![ins("slt", vec![rd.clone(), x(0), rs.clone()])],
+
+            // ---- Branches ----
+            ("beqz", [rs, off]) => vec![ins("beq", vec![rs.clone(), x(0), off.clone()])],
+            ("bnez", [rs, off]) => vec![ins("bne", vec![rs.clone(), x(0), off.clone()])],
+            ("blez", [rs, off]) => vec![ins("bge", vec![x(0), rs.clone(), off.clone()])],
+            ("bgez", [rs, off]) => vec![ins("bge", vec![rs.clone(), x(0), off.clone()])],
+            ("bltz", [rs, off]) => vec![ins("blt", vec![rs.clone(), x(0), off.clone()])],
+            ("bgtz", [rs, off]) => vec![ins("blt", vec![x(0), rs.clone(), off.clone()])],
+            ("bgt" | "ble" | "bgtu" | "bleu", [a, b, off]) => {
+                let real = match mnemonic {
+                    "bgt" => "blt",
+                    "ble" => "bge",
+                    "bgtu" => "bltu",
+                    _ => "bgeu",
+                };
+                vec![ins(real, vec![b.clone(), a.clone(), off.clone()])]
+            }
+
+            // ---- Jumps ----
+            ("j", [off]) => vec![ins("jal", vec![x(0), off.clone()])],
+            ("jal", [off]) => vec![ins("jal", vec![x(1), off.clone()])],
+            (
+                "jr",
+                [rs @ Operand {
+                    value: Value::X(reg),
+                    ..
+                }],
+            ) => {
+                vec![ins(
+                    "jalr",
+                    vec![x(0), with(rs, Value::Mem(Expr::Number(0), *reg))],
+                )]
+            }
+            (
+                "jalr",
+                [rs @ Operand {
+                    value: Value::X(reg),
+                    ..
+                }],
+            ) => {
+                vec![ins(
+                    "jalr",
+                    vec![x(1), with(rs, Value::Mem(Expr::Number(0), *reg))],
+                )]
+            }
+            (
+                "jalr",
+                [rd, rs @ Operand {
+                    value: Value::X(reg),
+                    ..
+                }],
+            ) => {
+                let base = with(rs, Value::Mem(Expr::Number(0), *reg));
+                vec![ins("jalr", vec![rd.clone(), base])]
+            }
+            (
+                "jalr",
+                [rd, Operand {
+                    value: Value::X(reg),
+                    ..
+                }, off @ Operand {
+                    value: Value::Expr(e),
+                    ..
+                }],
+            ) => {
+                let base = with(off, Value::Mem(e.clone(), *reg));
+                vec![ins("jalr", vec![rd.clone(), base])]
+            }
+            ("ret", []) => vec![ins(
+                "jalr",
+                vec![x(0), synth(Value::Mem(Expr::Number(0), 1))],
+            )],
+            (
+                "call" | "tail",
+                [target @ Operand {
+                    value: Value::Expr(e),
+                    ..
+                }],
+            ) => {
+                let (rd, tmp) = if mnemonic == "call" { (1, 1) } else { (0, 6) };
+                let hi = with(target, Value::Expr(Expr::PcRelHi(Box::new(e.clone()))));
+                let lo = with(target, Value::Mem(Expr::PcRelLo(Box::new(e.clone())), tmp));
+                vec![ins("auipc", vec![x(tmp), hi]), ins("jalr", vec![x(rd), lo])]
+            }
+
+            // ---- Addresses ----
+            (
+                "la" | "lla",
+                [rd, target @ Operand {
+                    value: Value::Expr(e),
+                    ..
+                }],
+            ) => {
+                let hi = with(target, Value::Expr(Expr::PcRelHi(Box::new(e.clone()))));
+                let lo = with(target, Value::Expr(Expr::PcRelLo(Box::new(e.clone()))));
+                vec![
+                    ins("auipc", vec![rd.clone(), hi]),
+                    ins("addi", vec![rd.clone(), rd.clone(), lo]),
+                ]
+            }
+            (
+                "li",
+                [rd, value @ Operand {
+                    value: Value::Expr(e),
+                    ..
+                }],
+            ) => {
+                match e.eval(0, &|name| self.constants.get(name).copied()) {
+                    Ok(constant) => {
+                        if !(i32::MIN as i64..=u32::MAX as i64).contains(&constant) {
+                            let text = value.text.clone();
+                            return Err(LineError::new(
+                                value.column,
+                                text,
+                                "immediate out of range",
+                            ));
+                        }
+
+                        let constant = constant as u32 as i32 as i64;
+                        let (hi, lo) = (expr::hi(constant), expr::lo(constant));
+                        if (-2048..2048).contains(&constant) {
+                            vec![ins(
+                                "addi",
+                                vec![
+                                    rd.clone(),
+                                    x(0),
+                                    with(value, Value::Expr(Expr::Number(constant))),
+                                ],
+                            )]
+                        } else if lo == 0 {
+                            vec![ins(
+                                "lui",
+                                vec![rd.clone(), with(value, Value::Expr(Expr::Number(hi)))],
+                            )]
+                        } else {
+                            vec![
+                                ins(
+                                    "lui",
+                                    vec![rd.clone(), with(value, Value::Expr(Expr::Number(hi)))],
+                                ),
+                                ins(
+                                    "addi",
+                                    vec![
+                                        rd.clone(),
+                                        rd.clone(),
+                                        with(value, Value::Expr(Expr::Number(lo))),
+                                    ],
+                                ),
+                            ]
+                        }
+                    }
+
+                    // resolved in the second pass, so the size can't depend on the value
+                    Err(_) => vec![
+                        ins(
+                            "lui",
+                            vec![
+                                rd.clone(),
+                                with(value, Value::Expr(Expr::Hi(Box::new(e.clone())))),
+                            ],
+                        ),
+                        ins(
+                            "addi",
+                            vec![
+                                rd.clone(),
+                                rd.clone(),
+                                with(value, Value::Expr(Expr::Lo(Box::new(e.clone())))),
+                            ],
+                        ),
+                    ],
+                }
+            }
+
+            // ---- Floating-Point ----
+            ("fmv.s" | "fabs.s" | "fneg.s", [rd, rs]) => {
+                let real = match mnemonic {
+                    "fmv.s" => "fsgnj.s",
+                    "fabs.s" => "fsgnjx.s",
+                    _ => "fsgnjn.s",
+                };
+                vec![ins(real, vec![rd.clone(), rs.clone(), rs.clone()])]
+            }
+
+            // ---- Zicsr ----
+            ("csrr", [rd, csr]) => vec![ins("csrrs", vec![rd.clone(), csr.clone(), x(0)])],
+            ("csrw" | "csrs" | "csrc" | "csrwi" | "csrsi" | "csrci", [csr, value]) => {
+                let real = format!("csrr{}", &mnemonic[3..]);
+                vec![(real, vec![x(0), csr.clone(), value.clone()])]
+            }
+
+            // ---- Fence ----
+            ("fence", _) => {
+                let operands = operands
+                    .iter()
+                    .map(|operand| match &operand.value {
+                        Value::Expr(Expr::Symbol(name, _)) => match fence_set(name) {
+                            Some(set) => with(operand, Value::Expr(Expr::Number(set))),
+                            None => operand.clone(),
+                        },
+                        _ => operand.clone(),
+                    })
+                    .collect();
+                vec![ins("fence", operands)]
+            }
+
+            _ => vec![ins(mnemonic, operands)],
+        })
+    }
+
+    // ---- Second Pass ----
+
+    fn finish(mut self, isa: Isa, origin: u32) -> Result<Program, Vec<(usize, LineError)>> {
+        let text = Section::Text as usize;
+        let data = Section::Data as usize;
+        let bss = Section::Bss as usize;
+
+        let mut bases = [origin; 3];
+        bases[data] = (bases[text] + self.sizes[text]).next_multiple_of(self.aligns[data].max(4));
+        bases[bss] = (bases[data] + self.sizes[data]).next_multiple_of(self.aligns[bss].max(4));
+
+        // ---- Symbols ----
+        let mut values: HashMap<String, i64> = self
+            .labels
+            .iter()
+            .map(|(name, section, offset)| {
+                (name.clone(), (bases[*section as usize] + offset) as i64)
+            })
+            .collect();
+
+        for (line, name, expr) in &self.equs {
+            match expr.eval(0, &|name| values.get(name).copied()) {
+                Ok(value) => {
+                    values.insert(name.clone(), value);
+                }
+                Err(error) => self.errors.push((*line, error)),
+            }
+        }
+
+        let lookup = |name: &str, pc: u32| {
+            if name == "." {
+                Some(pc as i64)
+            } else {
+                values.get(name).copied()
+            }
+        };
+
+        let mut symbol_sizes = HashMap::new();
+        for (line, name, expr, section, offset) in &self.symbol_sizes {
+            let pc = bases[*section as usize] + offset;
+            match expr.eval(pc, &|name| lookup(name, pc)) {
+                Ok(size) => {
+                    symbol_sizes.insert(name.clone(), size as u32);
+                }
+                Err(error) => self.errors.push((*line, error)),
+            }
+        }
+
+        // ---- Encode ----
+        let mut out = [Vec::new(), Vec::new()];
+        for placed in &self.items {
+            let pc = bases[placed.section as usize] + placed.offset;
+            let eval = |operand: &Operand, expr: &Expr| {
+                let value = expr.eval(pc, &|name| lookup(name, pc))?;
+                i32::try_from(value).map_err(|_| {
+                    LineError::new(
+                        operand.column,
+                        operand.text.clone(),
+                        "immediate out of range",
+                    )
+                })
+            };
+
+            let bytes = match &placed.item {
+                Item::Bytes(bytes) => Ok(bytes.clone()),
+                Item::Data { size, value } => {
+                    let Value::Expr(expr) = &value.value else {
+                        unreachable!()
+                    };
+
+                    expr.eval(pc, &|name| lookup(name, pc)).and_then(|v| {
+                        let bits = size * 8;
+                        if (-(1 << (bits - 1))..1 << bits).contains(&v) {
+                            Ok(v.to_le_bytes()[..*size as usize].to_vec())
+                        } else {
+                            Err(LineError::new(
+                                value.column,
+                                value.text.clone(),
+                                "value out of range",
+                            ))
+                        }
+                    })
+                }
+                Item::Ins {
+                    mnemonic,
+                    column,
+                    text,
+                    operands,
+                } => operands
+                    .iter()
+                    .map(|operand| match &operand.value {
+                        Value::X(reg) => Ok(Arg::X(*reg)),
+                        Value::F(reg) => Ok(Arg::F(*reg)),
+                        Value::Expr(expr) => eval(operand, expr).map(Arg::Imm),
+                        Value::Mem(expr, base) => eval(operand, expr).map(|v| Arg::Mem(v, *base)),
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .and_then(|args| {
+                        let (binary, extension) = encode(mnemonic, &args).map_err(|error| {
+                            let operand = match error {
+                                EncodeError::Range(i) | EncodeError::Misaligned(i) => {
+                                    operands.get(i)
+                                }
+                                _ => None,
+                            };
+
+                            match (operand, error) {
+                                (Some(operand), error) => LineError::new(
+                                    operand.column,
+                                    operand.text.clone(),
+                                    error.to_string(),
+                                ),
+                                (None, EncodeError::Operands("")) => {
+                                    LineError::new(*column, text.clone(), "expected no operands")
+                                }
+                                (None, error) => {
+                                    LineError::new(*column, text.clone(), error.to_string())
+                                }
+                            }
+                        })?;
+
+                        if !isa.supports(extension) {
+                            let message =
+                                format!("instruction requires the {} extension", extension);
+                            return Err(LineError::new(*column, text.clone(), message));
+                        }
+
+                        Ok(binary.to_le_bytes().to_vec())
+                    }),
+            };
+
+            match bytes {
+                Ok(bytes) => {
+                    let out = &mut out[placed.section as usize];
+                    out.resize(placed.offset as usize, 0);
+                    out.extend_from_slice(&bytes);
+                }
+                Err(error) => self.errors.push((placed.line, error)),
+            }
+        }
+
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+
+        let [mut text_bytes, mut data_bytes] = out;
+        text_bytes.resize(self.sizes[text] as usize, 0);
+        data_bytes.resize(self.sizes[data] as usize, 0);
+
+        let symbols = self
+            .labels
+            .iter()
+            .map(|(name, section, offset)| Symbol {
+                name: name.clone(),
+                addr: bases[*section as usize] + offset,
+                size: symbol_sizes.get(name).copied().unwrap_or_default(),
+                kind: self.kinds.get(name).copied().unwrap_or(SymbolKind::NoType),
+                global: self.globals.contains(name),
+                section: match section {
+                    Section::Text => TEXT_SECTION,
+                    Section::Data => DATA_SECTION,
+                    Section::Bss => BSS_SECTION,
+                },
+            })
+            .collect::<Vec<_>>();
+
+        let entry = symbols
+            .iter()
+            .find(|s| s.name == "_start")
+            .map_or(origin, |s| s.addr);
+
+        Ok(Program {
+            entry,
+            text_addr: bases[text],
+            text: text_bytes,
+            data_addr: bases[data],
+            data: data_bytes,
+            bss_addr: bases[bss],
+            bss_size: self.sizes[bss],
+            symbols,
+        })
+    }
+}
+
+// ---- Operands ----
+
+/// Splits operands on commas outside of parentheses
+fn split_operands(tokens: &[Token], end: usize) -> Result<Vec<&[Token]>, LineError> {
+    if tokens.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut groups = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token.tok {
+            Tok::Punct('(') => depth += 1,
+            Tok::Punct(')') => depth -= 1,
+            Tok::Punct(',') if depth == 0 => {
+                if start == i {
+                    return Err(LineError::at(token, "expected an operand"));
+                }
+
+                groups.push(&tokens[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+
+    if start == tokens.len() {
+        return Err(LineError::new(end, "", "expected an operand"));
+    }
+    groups.push(&tokens[start..]);
+    Ok(groups)
+}
+
+fn parse_operand(group: &[Token], chars: &[char]) -> Result<Operand, LineError> {
+    let column = group[0].column;
+    let text = span(chars, group);
+    let end = column + text.chars().count();
+
+    let value = match group {
+        [token] if token.ident().and_then(xreg).is_some() => {
+            Value::X(token.ident().and_then(xreg).unwrap())
+        }
+        [token] if token.ident().and_then(freg).is_some() => {
+            Value::F(token.ident().and_then(freg).unwrap())
+        }
+        [offset @ .., open, base, close]
+            if open.is_punct('(')
+                && close.is_punct(')')
+                && base.ident().is_some_and(|b| xreg(b).is_some()) =>
+        {
+            let offset = if offset.is_empty() {
+                Expr::Number(0)
+            } else {
+                expr::parse(offset, open.column)?
+            };
+            Value::Mem(offset, base.ident().and_then(xreg).unwrap())
+        }
+        [.., open, base, close]
+            if open.is_punct('(') && close.is_punct(')') && base.ident().is_some() =>
+        {
+            return Err(LineError::at(base, "unknown register"));
+        }
+        _ => Value::Expr(expr::parse(group, end)?),
+    };
+
+    Ok(Operand {
+        value,
+        column,
+        text,
+    })
+}
+
+/// The source text covered by `tokens`
+fn span(chars: &[char], tokens: &[Token]) -> String {
+    match (tokens.first(), tokens.last()) {
+        (Some(first), Some(last)) => {
+            let end = last.column - 1 + last.text.chars().count();
+            chars[first.column - 1..end].iter().collect()
+        }
+        _ => String::new(),
+    }
+}
+
+/// ABI names of the integer registers, by number
+pub const XREG_ABI: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+    "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+    "t5", "t6",
+];
+
+/// Parses an integer register like `x10` or `a0`
+pub fn xreg(name: &str) -> Option<u8> {
+    if name == "fp" {
+        return Some(8);
+    }
+
+    register(name, 'x').or_else(|| XREG_ABI.iter().position(|r| *r == name).map(|i| i as u8))
+}
+
+/// Parses a floating-point register like `f10` or `fa0`
+pub fn freg(name: &str) -> Option<u8> {
+    const ABI: [&str; 32] = [
+        "ft0", "ft1", "ft2", "ft3", "ft4", "ft5", "ft6", "ft7", "fs0", "fs1", "fa0", "fa1", "fa2",
+        "fa3", "fa4", "fa5", "fa6", "fa7", "fs2", "fs3", "fs4", "fs5", "fs6", "fs7", "fs8", "fs9",
+        "fs10", "fs11", "ft8", "ft9", "ft10", "ft11",
+    ];
+
+    register(name, 'f').or_else(|| ABI.iter().position(|r| *r == name).map(|i| i as u8))
+}
+
+/// `x0`..`x31` style registers
+fn register(name: &str, prefix: char) -> Option<u8> {
+    let index = name.strip_prefix(prefix)?;
+    if index.len() > 1 && index.starts_with('0') {
+        return None;
+    }
+
+    index.parse().ok().filter(|i| *i < 32)
+}
+
+/// CSR numbers by name
+fn csr(name: &str) -> Option<i64> {
+    Some(match name {
+        "fflags" => 0x001,
+        "frm" => 0x002,
+        "fcsr" => 0x003,
+        "cycle" => 0xc00,
+        "time" => 0xc01,
+        "instret" => 0xc02,
+        "cycleh" => 0xc80,
+        "timeh" => 0xc81,
+        "instreth" => 0xc82,
+        "mstatus" => 0x300,
+        "misa" => 0x301,
+        "mie" => 0x304,
+        "mtvec" => 0x305,
+        "mscratch" => 0x340,
+        "mepc" => 0x341,
+        "mcause" => 0x342,
+        "mtval" => 0x343,
+        "mip" => 0x344,
+        "mhartid" => 0xf14,
+        _ => return None,
+    })
+}
+
+/// A `fence` predecessor or successor set like `iorw`
+fn fence_set(name: &str) -> Option<i64> {
+    let mut set = 0;
+    for c in name.chars() {
+        set |= match c {
+            'i' => 8,
+            'o' => 4,
+            'r' => 2,
+            'w' => 1,
+            _ => return None,
+        };
+    }
+
+    Some(set)
+}
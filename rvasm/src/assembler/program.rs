@@ -0,0 +1,100 @@
+use rvcore::elf::{
+    Elf, Section, Segment, Symbol, SymbolTable, PF_R, PF_W, PF_X, SHF_ALLOC, SHF_EXECINSTR,
+    SHF_WRITE, SHT_NOBITS, SHT_PROGBITS,
+};
+
+/// Section indices used by [`Program::to_elf`]
+pub const TEXT_SECTION: u16 = 1;
+pub const DATA_SECTION: u16 = 2;
+pub const BSS_SECTION: u16 = 3;
+
+/// An assembled program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    pub entry: u32,
+    pub text_addr: u32,
+    pub text: Vec<u8>,
+    pub data_addr: u32,
+    pub data: Vec<u8>,
+    pub bss_addr: u32,
+    pub bss_size: u32,
+    pub symbols: Vec<Symbol>,
+}
+
+impl Program {
+    pub fn to_elf(&self) -> Elf {
+        let sections = vec![
+            Section {
+                name: String::new(),
+                kind: 0,
+                flags: 0,
+                addr: 0,
+                size: 0,
+                data: Vec::new(),
+            },
+            Section {
+                name: ".text".into(),
+                kind: SHT_PROGBITS,
+                flags: SHF_ALLOC | SHF_EXECINSTR,
+                addr: self.text_addr,
+                size: self.text.len() as u32,
+                data: self.text.clone(),
+            },
+            Section {
+                name: ".data".into(),
+                kind: SHT_PROGBITS,
+                flags: SHF_ALLOC | SHF_WRITE,
+                addr: self.data_addr,
+                size: self.data.len() as u32,
+                data: self.data.clone(),
+            },
+            Section {
+                name: ".bss".into(),
+                kind: SHT_NOBITS,
+                flags: SHF_ALLOC | SHF_WRITE,
+                addr: self.bss_addr,
+                size: self.bss_size,
+                data: Vec::new(),
+            },
+        ];
+
+        let segments = vec![
+            Segment {
+                addr: self.text_addr,
+                size: self.text.len() as u32,
+                flags: PF_R | PF_X,
+                data: self.text.clone(),
+            },
+            Segment {
+                addr: self.data_addr,
+                size: self.data.len() as u32,
+                flags: PF_R | PF_W,
+                data: self.data.clone(),
+            },
+            Segment {
+                addr: self.bss_addr,
+                size: self.bss_size,
+                flags: PF_R | PF_W,
+                data: Vec::new(),
+            },
+        ];
+
+        Elf {
+            entry: self.entry,
+            segments,
+            sections,
+            symbols: SymbolTable::new(self.symbols.clone()),
+        }
+    }
+
+    /// A flat image starting at `text_addr`, `.bss` isn't included
+    pub fn to_binary(&self) -> Vec<u8> {
+        let mut out = self.text.clone();
+        if !self.data.is_empty() {
+            out.resize((self.data_addr - self.text_addr) as usize, 0);
+            out.extend_from_slice(&self.data);
+        }
+
+        out
+    }
+}
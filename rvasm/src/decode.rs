@@ -0,0 +1,270 @@
+use rv_f::{TypeLoadF, TypeOpFp, TypeStoreF, OPCODE_LOADF, OPCODE_OPFP, OPCODE_STOREF};
+use rvcore::ins::{
+    TypeAuiPc, TypeBranch, TypeJal, TypeJalR, TypeLoad, TypeLui, TypeMiscMem, TypeOp, TypeOpImm,
+    TypeStore, TypeSystem, OPCODE_AUIPC, OPCODE_BRANCH, OPCODE_JAL, OPCODE_JALR, OPCODE_LOAD,
+    OPCODE_LUI, OPCODE_MASK, OPCODE_MISCMEM, OPCODE_OP, OPCODE_OPIMM, OPCODE_STORE, OPCODE_SYSTEM,
+};
+
+pub fn decode_instruction(binary: u32) -> String {
+    decode(binary).unwrap_or("?".into())
+}
+
+/// Returns None if the instruction isn't supported
+pub fn decode(binary: u32) -> Option<String> {
+    decode_rv32i(binary)
+        .or_else(|| decode_rv32m(binary))
+        .or_else(|| decode_rv32f(binary))
+        .or_else(|| decode_zicsr(binary))
+}
+
+/// The address a `jal` or branch at `pc` jumps to
+pub fn branch_target(ins: u32, pc: u32) -> Option<u32> {
+    match ins & OPCODE_MASK {
+        OPCODE_JAL => Some(pc.wrapping_add_signed(TypeJal::decode(ins).imm)),
+        OPCODE_BRANCH => Some(pc.wrapping_add_signed(TypeBranch::decode(ins).imm)),
+
+        _ => None,
+    }
+}
+
+fn decode_rv32i(ins: u32) -> Option<String> {
+    Some(match ins & OPCODE_MASK {
+        OPCODE_OPIMM => {
+            let data = TypeOpImm::decode(ins);
+            let imm11_0 = data.imm;
+            let shamt = imm11_0 & 0b11111;
+            match (data.funct3, imm11_0 >> 5) {
+                (0, _) => format!("addi x{}, x{}, {}", data.rd, data.rs1, imm11_0), // addi
+                (2, _) => format!("slti x{}, x{}, {}", data.rd, data.rs1, imm11_0), // slti
+                (3, _) => format!("sltiu x{}, x{}, {}", data.rd, data.rs1, imm11_0), // sltiu
+                (4, _) => format!("xori x{}, x{}, {}", data.rd, data.rs1, imm11_0), // xori
+                (6, _) => format!("ori x{}, x{}, {}", data.rd, data.rs1, imm11_0),  // ori
+                (7, _) => format!("andi x{}, x{}, {}", data.rd, data.rs1, imm11_0), // andi
+                (1, 0) => format!("slli x{}, x{}, {}", data.rd, data.rs1, shamt),   // slli
+                (5, 0) => format!("srli x{}, x{}, {}", data.rd, data.rs1, shamt),   // srli
+                (5, 32) => format!("srai x{}, x{}, {}", data.rd, data.rs1, shamt),  // srai
+
+                _ => return None,
+            }
+        }
+        OPCODE_LUI => {
+            let data = TypeLui::decode(ins);
+            format!("lui x{}, {}", data.rd, data.imm)
+        }
+        OPCODE_AUIPC => {
+            let data = TypeAuiPc::decode(ins);
+            format!("auipc x{}, {}", data.rd, data.imm)
+        }
+        OPCODE_OP => {
+            let data = TypeOp::decode(ins);
+            match (data.funct7, data.funct3) {
+                (0, 0) => format!("add x{}, x{}, x{}", data.rd, data.rs1, data.rs2), // add
+                (32, 0) => format!("sub x{}, x{}, x{}", data.rd, data.rs1, data.rs2), // sub
+                (0, 1) => format!("sll x{}, x{}, x{}", data.rd, data.rs1, data.rs2), // sll
+                (0, 2) => format!("slt x{}, x{}, x{}", data.rd, data.rs1, data.rs2), // slt
+                (0, 3) => format!("sltu x{}, x{}, x{}", data.rd, data.rs1, data.rs2), // sltu
+                (0, 4) => format!("xor x{}, x{}, x{}", data.rd, data.rs1, data.rs2), // xor
+                (0, 5) => format!("srl x{}, x{}, x{}", data.rd, data.rs1, data.rs2), // srl
+                (32, 5) => format!("sra x{}, x{}, x{}", data.rd, data.rs1, data.rs2), // sra
+                (0, 6) => format!("or x{}, x{}, x{}", data.rd, data.rs1, data.rs2),  // or
+                (0, 7) => format!("and x{}, x{}, x{}", data.rd, data.rs1, data.rs2), // and
+
+                _ => return None,
+            }
+        }
+        OPCODE_JAL => {
+            let data = TypeJal::decode(ins);
+            format!("jal x{}, {}", data.rd, data.imm)
+        }
+        OPCODE_JALR => {
+            let data = TypeJalR::decode(ins);
+            format!("jalr x{}, {}(x{})", data.rd, data.imm, data.rs1)
+        }
+        OPCODE_BRANCH => {
+            let data = TypeBranch::decode(ins);
+            match data.funct3 {
+                0 => format!("beq x{}, x{}, {}", data.rs1, data.rs2, data.imm), // beq
+                1 => format!("bne x{}, x{}, {}", data.rs1, data.rs2, data.imm), // bne
+                4 => format!("blt x{}, x{}, {}", data.rs1, data.rs2, data.imm), // blt
+                5 => format!("bge x{}, x{}, {}", data.rs1, data.rs2, data.imm), // bge
+                6 => format!("bltu x{}, x{}, {}", data.rs1, data.rs2, data.imm), // bltu
+                7 => format!("bgeu x{}, x{}, {}", data.rs1, data.rs2, data.imm), // bgeu
+
+                _ => return None,
+            }
+        }
+        OPCODE_LOAD => {
+            let data = TypeLoad::decode(ins);
+            match data.funct3 {
+                0 => format!("lb x{}, {}(x{})", data.rd, data.imm, data.rs1), // lb
+                1 => format!("lh x{}, {}(x{})", data.rd, data.imm, data.rs1), // lh
+                2 => format!("lw x{}, {}(x{})", data.rd, data.imm, data.rs1), // lw
+                4 => format!("lbu x{}, {}(x{})", data.rd, data.imm, data.rs1), // lbu
+                5 => format!("lhu x{}, {}(x{})", data.rd, data.imm, data.rs1), // lhu
+
+                _ => return None,
+            }
+        }
+        OPCODE_STORE => {
+            let data = TypeStore::decode(ins);
+            match data.funct3 {
+                0 => format!("sb x{}, {}(x{})", data.rs2, data.imm, data.rs1), // sb
+                1 => format!("sh x{}, {}(x{})", data.rs2, data.imm, data.rs1), // sh
+                2 => format!("sw x{}, {}(x{})", data.rs2, data.imm, data.rs1), // sw
+
+                _ => return None,
+            }
+        }
+        OPCODE_SYSTEM => {
+            let data = TypeSystem::decode(ins);
+            let funct12 = data.imm;
+            match (funct12, data.funct3) {
+                (0, 0) => "ecall".into(),  // ecall
+                (1, 0) => "ebreak".into(), // ebreak
+
+                _ => return None,
+            }
+        }
+        OPCODE_MISCMEM => {
+            let data = TypeMiscMem::decode(ins);
+            let fm = (data.imm >> 8) & 0b1111;
+            let pred = (data.imm >> 4) & 0b1111;
+            let succ = data.imm & 0b1111;
+            match data.funct3 {
+                0 => {
+                    if fm == 8 && pred == 0b0011 && succ == 0b0011 {
+                        "fence.tso".into() // fence.tso
+                    } else if fm == 0 && pred == 0b0001 && succ == 0 && data.rs1 == 0 {
+                        "pause".into() // pause
+                    } else {
+                        format!("fence {}, {}", fence_set(pred), fence_set(succ))
+                        // fence
+                    }
+                }
+
+                _ => return None,
+            }
+        }
+
+        _ => return None,
+    })
+}
+
+fn decode_rv32m(ins: u32) -> Option<String> {
+    Some(match ins & OPCODE_MASK {
+        OPCODE_OP => {
+            let data = TypeOp::decode(ins);
+            let name = match (data.funct7, data.funct3) {
+                (1, 0) => "mul",
+                (1, 1) => "mulh",
+                (1, 2) => "mulhsu",
+                (1, 3) => "mulhu",
+                (1, 4) => "div",
+                (1, 5) => "divu",
+                (1, 6) => "rem",
+                (1, 7) => "remu",
+
+                _ => return None,
+            };
+
+            format!("{} x{}, x{}, x{}", name, data.rd, data.rs1, data.rs2)
+        }
+
+        _ => return None,
+    })
+}
+
+fn decode_rv32f(ins: u32) -> Option<String> {
+    Some(match ins & OPCODE_MASK {
+        OPCODE_LOADF => {
+            let data = TypeLoadF::decode(ins);
+            match data.funct3 {
+                2 => format!("flw f{}, {}(x{})", data.rd, data.imm, data.rs1), // flw
+
+                _ => return None,
+            }
+        }
+        OPCODE_STOREF => {
+            let data = TypeStoreF::decode(ins);
+            match data.funct3 {
+                2 => format!("fsw f{}, {}(x{})", data.rs2, data.imm, data.rs1), // fsw
+
+                _ => return None,
+            }
+        }
+        OPCODE_OPFP => {
+            let data = TypeOpFp::decode(ins);
+            let funct5 = data.funct7 >> 2;
+            let fmt = data.funct7 & 0b11;
+            let rm = data.funct3;
+            if fmt != 0 {
+                return None;
+            }
+
+            let (rd, rs1, rs2) = (data.rd, data.rs1, data.rs2);
+            match (funct5, rs2, rm) {
+                (0, _, _) => format!("fadd.s f{}, f{}, f{}", rd, rs1, rs2), // fadd.s
+                (1, _, _) => format!("fsub.s f{}, f{}, f{}", rd, rs1, rs2), // fsub.s
+                (2, _, _) => format!("fmul.s f{}, f{}, f{}", rd, rs1, rs2), // fmul.s
+                (3, _, _) => format!("fdiv.s f{}, f{}, f{}", rd, rs1, rs2), // fdiv.s
+                (11, 0, _) => format!("fsqrt.s f{}, f{}", rd, rs1),         // fsqrt.s
+                (4, _, 0) => format!("fsgnj.s f{}, f{}, f{}", rd, rs1, rs2), // fsgnj.s
+                (4, _, 1) => format!("fsgnjn.s f{}, f{}, f{}", rd, rs1, rs2), // fsgnjn.s
+                (4, _, 2) => format!("fsgnjx.s f{}, f{}, f{}", rd, rs1, rs2), // fsgnjx.s
+                (5, _, 0) => format!("fmin.s f{}, f{}, f{}", rd, rs1, rs2), // fmin.s
+                (5, _, 1) => format!("fmax.s f{}, f{}, f{}", rd, rs1, rs2), // fmax.s
+                (24, 0, _) => format!("fcvt.w.s x{}, f{}", rd, rs1),        // fcvt.w.s
+                (24, 1, _) => format!("fcvt.wu.s x{}, f{}", rd, rs1),       // fcvt.wu.s
+                (28, 0, 0) => format!("fmv.x.w x{}, f{}", rd, rs1),         // fmv.x.w
+                (28, 0, 1) => format!("fclass.s x{}, f{}", rd, rs1),        // fclass.s
+                (20, _, 2) => format!("feq.s x{}, f{}, f{}", rd, rs1, rs2), // feq.s
+                (20, _, 1) => format!("flt.s x{}, f{}, f{}", rd, rs1, rs2), // flt.s
+                (20, _, 0) => format!("fle.s x{}, f{}, f{}", rd, rs1, rs2), // fle.s
+                (26, 0, _) => format!("fcvt.s.w f{}, x{}", rd, rs1),        // fcvt.s.w
+                (26, 1, _) => format!("fcvt.s.wu f{}, x{}", rd, rs1),       // fcvt.s.wu
+                (30, 0, 0) => format!("fmv.w.x f{}, x{}", rd, rs1),         // fmv.w.x
+
+                _ => return None,
+            }
+        }
+
+        _ => return None,
+    })
+}
+
+fn decode_zicsr(ins: u32) -> Option<String> {
+    Some(match ins & OPCODE_MASK {
+        OPCODE_SYSTEM => {
+            let data = TypeSystem::decode(ins);
+            let csr = data.imm & 0xfff;
+            match data.funct3 {
+                1 => format!("csrrw x{}, {:#x}, x{}", data.rd, csr, data.rs1), // csrrw
+                2 => format!("csrrs x{}, {:#x}, x{}", data.rd, csr, data.rs1), // csrrs
+                3 => format!("csrrc x{}, {:#x}, x{}", data.rd, csr, data.rs1), // csrrc
+                5 => format!("csrrwi x{}, {:#x}, {}", data.rd, csr, data.rs1), // csrrwi
+                6 => format!("csrrsi x{}, {:#x}, {}", data.rd, csr, data.rs1), // csrrsi
+                7 => format!("csrrci x{}, {:#x}, {}", data.rd, csr, data.rs1), // csrrci
+
+                _ => return None,
+            }
+        }
+
+        _ => return None,
+    })
+}
+
+/// Formats a fence predecessor/successor set as `iorw`
+fn fence_set(set: i32) -> String {
+    let text: String = ['i', 'o', 'r', 'w']
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| set & (0b1000 >> i) != 0)
+        .map(|(_, c)| c)
+        .collect();
+
+    if text.is_empty() {
+        "0".into()
+    } else {
+        text
+    }
+}
@@ -0,0 +1,534 @@
+use std::fmt::Display;
+
+use rv_f::{TypeLoadF, TypeOpFp, TypeStoreF, OPCODE_LOADF, OPCODE_OPFP, OPCODE_STOREF};
+use rvcore::{
+    ins::{
+        TypeBranch, TypeJal, TypeJalR, TypeLoad, TypeLui, TypeMiscMem, TypeOp, TypeOpImm,
+        TypeStore, TypeSystem, OPCODE_AUIPC, OPCODE_BRANCH, OPCODE_JAL, OPCODE_JALR, OPCODE_LOAD,
+        OPCODE_LUI, OPCODE_MISCMEM, OPCODE_OP, OPCODE_OPIMM, OPCODE_STORE, OPCODE_SYSTEM,
+    },
+    isa::{Extension, Isa},
+    util::sign_extend,
+};
+
+use crate::assembler::Assembler;
+
+/// An instruction operand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arg {
+    /// Integer register
+    X(u8),
+    /// Floating-point register
+    F(u8),
+    Imm(i32),
+    /// `offset(base)`
+    Mem(i32, u8),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodeError {
+    UnknownInstruction,
+    /// Wrong number or kind of operands, with the expected form
+    Operands(&'static str),
+    /// Operand `index` doesn't fit in its field
+    Range(usize),
+    /// Operand `index` must be a multiple of 2
+    Misaligned(usize),
+}
+
+impl Display for EncodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncodeError::UnknownInstruction => write!(f, "unknown instruction"),
+            EncodeError::Operands(form) => write!(f, "expected operands `{}`", form),
+            EncodeError::Range(_) => write!(f, "immediate out of range"),
+            EncodeError::Misaligned(_) => write!(f, "offset must be a multiple of 2"),
+        }
+    }
+}
+
+// ---- Encode ----
+
+/// Assembles a single line of text, like `addi x5, x6, 1`
+/// Returns None if the line doesn't assemble to exactly one instruction
+pub fn encode_instruction(text: &str) -> Option<u32> {
+    let program = Assembler::new(Isa::ALL).assemble(text).ok()?;
+    match program.text.as_slice() {
+        [a, b, c, d] => Some(u32::from_le_bytes([*a, *b, *c, *d])),
+        _ => None,
+    }
+}
+
+/// Encodes an instruction, also returning the extension it belongs to
+pub fn encode(ins: &str, args: &[Arg]) -> Result<(u32, Extension), EncodeError> {
+    let encoded = [
+        (Extension::I, encode_rv32i(ins, args)),
+        (Extension::M, encode_rv32m(ins, args)),
+        (Extension::F, encode_rv32f(ins, args)),
+        (Extension::Zicsr, encode_zicsr(ins, args)),
+    ];
+
+    for (extension, result) in encoded {
+        if let Some(result) = result {
+            return result.map(|binary| (binary, extension));
+        }
+    }
+
+    Err(EncodeError::UnknownInstruction)
+}
+
+fn encode_rv32i(ins: &str, args: &[Arg]) -> Option<Result<u32, EncodeError>> {
+    Some(match ins {
+        "addi" | "slti" | "sltiu" | "xori" | "ori" | "andi" => {
+            let funct3 = match ins {
+                "addi" => 0,
+                "slti" => 2,
+                "sltiu" => 3,
+                "xori" => 4,
+                "ori" => 6,
+                "andi" => 7,
+                _ => unreachable!(),
+            };
+
+            let form = "rd, rs1, imm";
+            operands(args, 3, form).and_then(|_| {
+                Ok(OPCODE_OPIMM
+                    | TypeOpImm {
+                        rd: x(args, 0, form)?,
+                        rs1: x(args, 1, form)?,
+                        imm: signed(args, 2, 12, form)?,
+                        funct3,
+                    }
+                    .encode())
+            })
+        }
+        "slli" | "srli" | "srai" => {
+            let (funct7, funct3) = match ins {
+                "slli" => (0, 1),
+                "srli" => (0, 5),
+                "srai" => (32, 5),
+                _ => unreachable!(),
+            };
+
+            let form = "rd, rs1, shamt";
+            operands(args, 3, form).and_then(|_| {
+                Ok(OPCODE_OPIMM
+                    | TypeOpImm {
+                        rd: x(args, 0, form)?,
+                        rs1: x(args, 1, form)?,
+                        imm: (funct7 << 5) | unsigned(args, 2, 5, form)?,
+                        funct3,
+                    }
+                    .encode())
+            })
+        }
+        "lui" | "auipc" => {
+            let form = "rd, imm";
+            let opcode = if ins == "lui" {
+                OPCODE_LUI
+            } else {
+                OPCODE_AUIPC
+            };
+
+            operands(args, 2, form).and_then(|_| {
+                Ok(opcode
+                    | TypeLui {
+                        rd: x(args, 0, form)?,
+                        imm: upper(args, 1, form)?,
+                    }
+                    .encode())
+            })
+        }
+        "add" | "sub" | "sll" | "slt" | "sltu" | "xor" | "srl" | "sra" | "or" | "and" => {
+            let (funct7, funct3) = match ins {
+                "add" => (0, 0),
+                "sub" => (32, 0),
+                "sll" => (0, 1),
+                "slt" => (0, 2),
+                "sltu" => (0, 3),
+                "xor" => (0, 4),
+                "srl" => (0, 5),
+                "sra" => (32, 5),
+                "or" => (0, 6),
+                "and" => (0, 7),
+                _ => unreachable!(),
+            };
+
+            op(args, funct7, funct3)
+        }
+        "jal" => {
+            let form = "rd, offset";
+            operands(args, 2, form).and_then(|_| {
+                Ok(OPCODE_JAL
+                    | TypeJal {
+                        rd: x(args, 0, form)?,
+                        imm: offset(args, 1, 21, form)?,
+                    }
+                    .encode())
+            })
+        }
+        "jalr" => {
+            let form = "rd, offset(rs1)";
+            operands(args, 2, form).and_then(|_| {
+                let (imm, rs1) = mem(args, 1, form)?;
+                Ok(OPCODE_JALR
+                    | TypeJalR {
+                        rd: x(args, 0, form)?,
+                        imm,
+                        rs1,
+                        funct3: 0,
+                    }
+                    .encode())
+            })
+        }
+        "beq" | "bne" | "blt" | "bge" | "bltu" | "bgeu" => {
+            let funct3 = match ins {
+                "beq" => 0,
+                "bne" => 1,
+                "blt" => 4,
+                "bge" => 5,
+                "bltu" => 6,
+                "bgeu" => 7,
+                _ => unreachable!(),
+            };
+
+            let form = "rs1, rs2, offset";
+            operands(args, 3, form).and_then(|_| {
+                Ok(OPCODE_BRANCH
+                    | TypeBranch {
+                        rs1: x(args, 0, form)?,
+                        rs2: x(args, 1, form)?,
+                        imm: offset(args, 2, 13, form)?,
+                        funct3,
+                    }
+                    .encode())
+            })
+        }
+        "lb" | "lh" | "lw" | "lbu" | "lhu" => {
+            let funct3 = match ins {
+                "lb" => 0,
+                "lh" => 1,
+                "lw" => 2,
+                "lbu" => 4,
+                "lhu" => 5,
+                _ => unreachable!(),
+            };
+
+            let form = "rd, offset(rs1)";
+            operands(args, 2, form).and_then(|_| {
+                let (imm, rs1) = mem(args, 1, form)?;
+                Ok(OPCODE_LOAD
+                    | TypeLoad {
+                        rd: x(args, 0, form)?,
+                        imm,
+                        rs1,
+                        funct3,
+                    }
+                    .encode())
+            })
+        }
+        "sb" | "sh" | "sw" => {
+            let funct3 = match ins {
+                "sb" => 0,
+                "sh" => 1,
+                "sw" => 2,
+                _ => unreachable!(),
+            };
+
+            let form = "rs2, offset(rs1)";
+            operands(args, 2, form).and_then(|_| {
+                let (imm, rs1) = mem(args, 1, form)?;
+                Ok(OPCODE_STORE
+                    | TypeStore {
+                        rs2: x(args, 0, form)?,
+                        imm,
+                        rs1,
+                        funct3,
+                    }
+                    .encode())
+            })
+        }
+        "ecall" | "ebreak" => {
+            let funct12 = if ins == "ecall" { 0 } else { 1 };
+            operands(args, 0, "").map(|_| {
+                OPCODE_SYSTEM
+                    | TypeSystem {
+                        imm: funct12,
+                        funct3: 0,
+                        rd: 0,
+                        rs1: 0,
+                    }
+                    .encode()
+            })
+        }
+
+        // --- Fence ---
+        "fence" | "fence.tso" | "pause" => {
+            let form = "pred, succ";
+            let fields = match ins {
+                "fence.tso" => operands(args, 0, "").map(|_| (8, 0b0011, 0b0011)),
+                "pause" => operands(args, 0, "").map(|_| (0, 0b0001, 0)),
+                _ if args.is_empty() => Ok((0, 0b1111, 0b1111)),
+                _ => operands(args, 2, form).and_then(|_| {
+                    Ok((0, unsigned(args, 0, 4, form)?, unsigned(args, 1, 4, form)?))
+                }),
+            };
+
+            fields.map(|(fm, pred, succ)| {
+                OPCODE_MISCMEM
+                    | TypeMiscMem {
+                        imm: sign_extend(((fm << 8) | (pred << 4) | succ) as u32, 12),
+                        funct3: 0,
+                        rs1: 0,
+                        rd: 0,
+                    }
+                    .encode()
+            })
+        }
+
+        _ => return None,
+    })
+}
+
+fn encode_rv32m(ins: &str, args: &[Arg]) -> Option<Result<u32, EncodeError>> {
+    Some(match ins {
+        "mul" | "mulh" | "mulhsu" | "mulhu" | "div" | "divu" | "rem" | "remu" => {
+            let funct3 = match ins {
+                "mul" => 0,
+                "mulh" => 1,
+                "mulhsu" => 2,
+                "mulhu" => 3,
+                "div" => 4,
+                "divu" => 5,
+                "rem" => 6,
+                "remu" => 7,
+                _ => unreachable!(),
+            };
+
+            op(args, 1, funct3)
+        }
+
+        _ => return None,
+    })
+}
+
+fn encode_rv32f(ins: &str, args: &[Arg]) -> Option<Result<u32, EncodeError>> {
+    Some(match ins {
+        "flw" => {
+            let form = "rd, offset(rs1)";
+            operands(args, 2, form).and_then(|_| {
+                let (imm, rs1) = mem(args, 1, form)?;
+                Ok(OPCODE_LOADF
+                    | TypeLoadF {
+                        rd: f(args, 0, form)?,
+                        imm,
+                        rs1,
+                        funct3: 2,
+                    }
+                    .encode())
+            })
+        }
+        "fsw" => {
+            let form = "rs2, offset(rs1)";
+            operands(args, 2, form).and_then(|_| {
+                let (imm, rs1) = mem(args, 1, form)?;
+                Ok(OPCODE_STOREF
+                    | TypeStoreF {
+                        rs2: f(args, 0, form)?,
+                        imm,
+                        rs1,
+                        funct3: 2,
+                    }
+                    .encode())
+            })
+        }
+        _ => {
+            // (funct5, rs2, rm, destination, sources)
+            // `None` means the field is a register operand, `true` an integer register
+            let (funct5, rs2, rm, rd_x, rs_x) = match ins {
+                "fadd.s" => (0, None, 7, false, false),
+                "fsub.s" => (1, None, 7, false, false),
+                "fmul.s" => (2, None, 7, false, false),
+                "fdiv.s" => (3, None, 7, false, false),
+                "fsqrt.s" => (11, Some(0), 7, false, false),
+                "fsgnj.s" => (4, None, 0, false, false),
+                "fsgnjn.s" => (4, None, 1, false, false),
+                "fsgnjx.s" => (4, None, 2, false, false),
+                "fmin.s" => (5, None, 0, false, false),
+                "fmax.s" => (5, None, 1, false, false),
+                "fcvt.w.s" => (24, Some(0), 7, true, false),
+                "fcvt.wu.s" => (24, Some(1), 7, true, false),
+                "fmv.x.w" => (28, Some(0), 0, true, false),
+                "fclass.s" => (28, Some(0), 1, true, false),
+                "feq.s" => (20, None, 2, true, false),
+                "flt.s" => (20, None, 1, true, false),
+                "fle.s" => (20, None, 0, true, false),
+                "fcvt.s.w" => (26, Some(0), 7, false, true),
+                "fcvt.s.wu" => (26, Some(1), 7, false, true),
+                "fmv.w.x" => (30, Some(0), 0, false, true),
+
+                _ => return None,
+            };
+
+            let form = match (rs2, rd_x, rs_x) {
+                (None, false, _) => "fd, fs1, fs2",
+                (None, true, _) => "rd, fs1, fs2",
+                (Some(_), true, _) => "rd, fs1",
+                (Some(_), false, true) => "fd, rs1",
+                (Some(_), false, false) => "fd, fs1",
+            };
+            let count = if rs2.is_some() { 2 } else { 3 };
+
+            operands(args, count, form).and_then(|_| {
+                let reg = |i| {
+                    if (i == 0 && rd_x) || (i > 0 && rs_x) {
+                        x(args, i, form)
+                    } else {
+                        f(args, i, form)
+                    }
+                };
+
+                Ok(OPCODE_OPFP
+                    | TypeOpFp {
+                        funct7: funct5 << 2,
+                        rs2: match rs2 {
+                            Some(rs2) => rs2,
+                            None => reg(2)?,
+                        },
+                        rs1: reg(1)?,
+                        funct3: rm,
+                        rd: reg(0)?,
+                    }
+                    .encode())
+            })
+        }
+    })
+}
+
+fn encode_zicsr(ins: &str, args: &[Arg]) -> Option<Result<u32, EncodeError>> {
+    let (funct3, immediate) = match ins {
+        "csrrw" => (1, false),
+        "csrrs" => (2, false),
+        "csrrc" => (3, false),
+        "csrrwi" => (5, true),
+        "csrrsi" => (6, true),
+        "csrrci" => (7, true),
+
+        _ => return None,
+    };
+
+    let form = if immediate {
+        "rd, csr, uimm"
+    } else {
+        "rd, csr, rs1"
+    };
+
+    Some(operands(args, 3, form).and_then(|_| {
+        Ok(OPCODE_SYSTEM
+            | TypeSystem {
+                rd: x(args, 0, form)?,
+                imm: sign_extend(unsigned(args, 1, 12, form)? as u32, 12),
+                rs1: if immediate {
+                    unsigned(args, 2, 5, form)? as u8
+                } else {
+                    x(args, 2, form)?
+                },
+                funct3,
+            }
+            .encode())
+    }))
+}
+
+// ---- Operands ----
+
+fn op(args: &[Arg], funct7: u8, funct3: u8) -> Result<u32, EncodeError> {
+    let form = "rd, rs1, rs2";
+    operands(args, 3, form)?;
+    Ok(OPCODE_OP
+        | TypeOp {
+            rd: x(args, 0, form)?,
+            rs1: x(args, 1, form)?,
+            rs2: x(args, 2, form)?,
+            funct7,
+            funct3,
+        }
+        .encode())
+}
+
+fn operands(args: &[Arg], count: usize, form: &'static str) -> Result<(), EncodeError> {
+    if args.len() == count {
+        Ok(())
+    } else {
+        Err(EncodeError::Operands(form))
+    }
+}
+
+fn x(args: &[Arg], i: usize, form: &'static str) -> Result<u8, EncodeError> {
+    match args[i] {
+        Arg::X(reg) => Ok(reg),
+        _ => Err(EncodeError::Operands(form)),
+    }
+}
+
+fn f(args: &[Arg], i: usize, form: &'static str) -> Result<u8, EncodeError> {
+    match args[i] {
+        Arg::F(reg) => Ok(reg),
+        _ => Err(EncodeError::Operands(form)),
+    }
+}
+
+fn imm(args: &[Arg], i: usize, form: &'static str) -> Result<i32, EncodeError> {
+    match args[i] {
+        Arg::Imm(value) => Ok(value),
+        _ => Err(EncodeError::Operands(form)),
+    }
+}
+
+/// A signed immediate of `bits` bits
+fn signed(args: &[Arg], i: usize, bits: u32, form: &'static str) -> Result<i32, EncodeError> {
+    let value = imm(args, i, form)?;
+    let limit = 1i64 << (bits - 1);
+    if (-limit..limit).contains(&(value as i64)) {
+        Ok(value)
+    } else {
+        Err(EncodeError::Range(i))
+    }
+}
+
+/// An unsigned immediate of `bits` bits
+fn unsigned(args: &[Arg], i: usize, bits: u32, form: &'static str) -> Result<i32, EncodeError> {
+    let value = imm(args, i, form)?;
+    if (0..1i64 << bits).contains(&(value as i64)) {
+        Ok(value)
+    } else {
+        Err(EncodeError::Range(i))
+    }
+}
+
+/// A 20-bit upper immediate, written either signed or unsigned
+fn upper(args: &[Arg], i: usize, form: &'static str) -> Result<i32, EncodeError> {
+    let value = imm(args, i, form)?;
+    if (-(1 << 19)..1 << 20).contains(&value) {
+        Ok(sign_extend(value as u32 & 0xfffff, 20))
+    } else {
+        Err(EncodeError::Range(i))
+    }
+}
+
+/// A pc-relative offset, the lowest bit is implied
+fn offset(args: &[Arg], i: usize, bits: u32, form: &'static str) -> Result<i32, EncodeError> {
+    let value = signed(args, i, bits, form)?;
+    if value % 2 == 0 {
+        Ok(value)
+    } else {
+        Err(EncodeError::Misaligned(i))
+    }
+}
+
+fn mem(args: &[Arg], i: usize, form: &'static str) -> Result<(i32, u8), EncodeError> {
+    match args[i] {
+        Arg::Mem(offset, base) if (-2048..2048).contains(&offset) => Ok((offset, base)),
+        Arg::Mem(..) => Err(EncodeError::Range(i)),
+        _ => Err(EncodeError::Operands(form)),
+    }
+}
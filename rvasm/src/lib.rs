@@ -0,0 +1,7 @@
+pub mod assembler;
+mod decode;
+mod encode;
+
+pub use assembler::{freg, xreg, AsmError, Assembler, Program, XREG_ABI};
+pub use decode::{branch_target, decode, decode_instruction};
+pub use encode::{encode, encode_instruction, Arg, EncodeError};
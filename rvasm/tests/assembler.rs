@@ -0,0 +1,141 @@
+use rvasm::{decode, encode_instruction, Assembler};
+use rvcore::{
+    elf::{Elf, SymbolKind},
+    isa::Isa,
+};
+
+fn words(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .chunks(4)
+        .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect()
+}
+
+fn assemble(source: &str) -> rvasm::Program {
+    match Assembler::new(Isa::ALL).assemble(source) {
+        Ok(program) => program,
+        Err(errors) => panic!("{}", errors[0]),
+    }
+}
+
+#[test]
+fn pseudo_instructions() {
+    let program = assemble(
+        "
+        _start:
+            li a0, 5
+            li a1, 0x12345678
+            la a2, value
+            call func
+            j _start
+        func:
+            ret
+            .data
+        value: .word 42
+        ",
+    );
+
+    // reference encodings from llvm-mc
+    assert_eq!(
+        words(&program.text),
+        [
+            0x00500513, // addi a0, zero, 5
+            0x123455b7, // lui a1, 0x12345
+            0x67858593, // addi a1, a1, 0x678
+            0x00000617, // auipc a2, 0
+            0x01860613, // addi a2, a2, 24
+            0x00000097, // auipc ra, 0
+            0x00c080e7, // jalr ra, 12(ra)
+            0xfe5ff06f, // jal zero, -28
+            0x00008067, // jalr zero, 0(ra)
+        ]
+    );
+    assert_eq!(program.data_addr, 0x24);
+    assert_eq!(program.data, [42, 0, 0, 0]);
+}
+
+#[test]
+fn branches_are_pc_relative() {
+    let program = assemble(
+        "
+        loop:
+            addi t0, t0, -1
+            bnez t0, loop
+            beq t0, t1, 8
+        ",
+    );
+
+    assert_eq!(decode(words(&program.text)[1]).unwrap(), "bne x5, x0, -4");
+    assert_eq!(decode(words(&program.text)[2]).unwrap(), "beq x5, x6, 8");
+}
+
+#[test]
+fn decode_agrees_with_encode() {
+    for text in [
+        "addi x5, x6, -1",
+        "lui x1, -1",
+        "sw x2, -4(x3)",
+        "jalr x1, 8(x5)",
+        "mulhu x1, x2, x3",
+        "fadd.s f1, f2, f3",
+        "flw f1, 4(x2)",
+        "csrrw x1, 0x300, x2",
+    ] {
+        let binary = encode_instruction(text).unwrap();
+        assert_eq!(decode(binary).as_deref(), Some(text));
+    }
+
+    assert_eq!(encode_instruction("li x1, 0x12345678"), None);
+}
+
+#[test]
+fn elf_round_trip() {
+    let program = Assembler::new(Isa::ALL)
+        .origin(0x1000)
+        .assemble(
+            "
+            .globl _start
+            .type _start, @function
+        _start:
+            nop
+            ebreak
+            .size _start, . - _start
+            .data
+        msg: .asciz \"hi\"
+            .bss
+        buf: .zero 8
+        ",
+        )
+        .unwrap();
+
+    let elf = Elf::parse(&program.to_elf().to_bytes()).unwrap();
+    assert_eq!(elf.entry, 0x1000);
+    assert_eq!(elf.section(".text").unwrap().data, program.text);
+    assert_eq!(elf.section(".data").unwrap().data, b"hi\0");
+    assert_eq!(elf.section(".bss").unwrap().size, 8);
+
+    let start = elf.symbols.get("_start").unwrap();
+    assert_eq!(start.addr, 0x1000);
+    assert_eq!(start.size, 8);
+    assert_eq!(start.kind, SymbolKind::Func);
+    assert!(start.global);
+    assert_eq!(elf.symbols.get("buf").unwrap().addr, 0x100c);
+}
+
+#[test]
+fn errors_have_locations() {
+    let errors = Assembler::new(Isa::RV32I)
+        .file("test.s")
+        .assemble("  addi a0, a0, 5000\n  mul a0, a1, a2\n  j missing\n")
+        .unwrap_err();
+
+    let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+    assert_eq!(
+        errors,
+        [
+            "test.s:1:16: error: immediate out of range `5000`",
+            "test.s:2:3: error: instruction requires the M extension `mul`",
+            "test.s:3:5: error: undefined symbol `missing`",
+        ]
+    );
+}
@@ -1,37 +1,358 @@
-use std::fmt::Debug;
+use std::{
+    cell::{Cell, RefCell},
+    error::Error,
+    fmt::Debug,
+    fmt::Display,
+};
 
-use crate::DRam;
+use crate::{
+    devices::Device,
+    snapshot::{Snapshot, SnapshotError},
+    trace::{AccessKind, MemoryAccess},
+    watch::{Access, WatchHit, Watchpoint},
+    DRam,
+};
 
 pub const DRAM_ADDR: usize = 0x0; //0x8000_0000;
 
+/// A range of addresses that isn't backed by memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfRange {
+    pub addr: usize,
+    pub len: usize,
+}
+
+impl Display for OutOfRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:#x}..{:#x} is outside of memory",
+            self.addr,
+            self.addr.saturating_add(self.len)
+        )
+    }
+}
+
+impl Error for OutOfRange {}
+
+/// A write to memory and the value it replaced
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryWrite {
+    pub addr: usize,
+    pub size: u8,
+    pub old: u32,
+}
+
 pub struct Bus {
     pub dram: DRam,
+    /// Address of the first byte of `dram`
+    base: usize,
+    /// Devices and the address they're attached at, these take priority over `dram`
+    devices: Vec<(usize, Box<dyn Device>)>,
+
+    watchpoints: Vec<Watchpoint>,
+    /// The first access that triggered a watchpoint since the last `take_watch_hit`
+    watch_hit: Cell<Option<WatchHit>>,
+
+    /// Memory writes since the last `take_writes`, while recording
+    journal: Option<Vec<MemoryWrite>>,
+
+    /// Accesses since the last `take_accesses`, while tracing
+    accesses: Option<RefCell<Vec<MemoryAccess>>>,
+    trace_fetches: bool,
 }
 
 impl Bus {
     pub fn new(dram: DRam) -> Self {
-        Self { dram }
+        Self::with_base(dram, DRAM_ADDR)
+    }
+
+    pub fn with_base(dram: DRam, base: usize) -> Self {
+        Self {
+            dram,
+            base,
+            devices: Vec::new(),
+
+            watchpoints: Vec::new(),
+            watch_hit: Cell::new(None),
+
+            journal: None,
+
+            accesses: None,
+            trace_fetches: false,
+        }
+    }
+
+    pub fn base(&self) -> usize {
+        self.base
+    }
+
+    /// Returns the `dram` offset of an access of `size` bits at `addr`
+    fn offset(&self, addr: usize, size: u8) -> Option<usize> {
+        addr.checked_sub(self.base)
+            .filter(|offset| offset + size as usize / 8 <= self.dram.size())
+    }
+
+    /// Returns the index of the device at `addr` and the offset into it
+    fn device_at(&self, addr: usize) -> Option<(usize, usize)> {
+        self.devices
+            .iter()
+            .enumerate()
+            .find_map(|(i, (base, device))| {
+                let offset = addr.checked_sub(*base)?;
+                (offset < device.size()).then_some((i, offset))
+            })
     }
 
     pub fn load(&self, addr: usize, size: u8) -> u32 {
-        if addr < DRAM_ADDR + self.dram.size() {
-            self.dram.load(addr - DRAM_ADDR, size)
-        } else {
-            0
+        let value = self.read(addr, size);
+        self.watch(addr, size, Access::Read, value, value);
+        self.trace(AccessKind::Read, addr, size, value);
+        value
+    }
+
+    /// Loads an instruction, fetches don't trigger watchpoints
+    pub fn fetch(&self, addr: usize) -> u32 {
+        let value = self.read(addr, 32);
+        if self.trace_fetches {
+            self.trace(AccessKind::Fetch, addr, 32, value);
         }
+        value
     }
 
     pub fn store(&mut self, addr: usize, size: u8, value: u32) {
-        if addr < DRAM_ADDR + self.dram.size() {
-            self.dram.store(addr - DRAM_ADDR, size, value);
+        self.trace(AccessKind::Write, addr, size, value);
+        if !self.watchpoints.is_empty() {
+            // reading a device could have side effects, like popping a UART's input
+            let old = match self.device_at(addr) {
+                Some(_) => 0,
+                None => self.read(addr, size),
+            };
+            self.watch(addr, size, Access::Write, old, value);
+        }
+
+        if let Some((i, offset)) = self.device_at(addr) {
+            self.devices[i].1.store(offset, size, value);
+        } else if let Some(offset) = self.offset(addr, size) {
+            if let Some(journal) = &mut self.journal {
+                let old = self.dram.load(offset, size);
+                journal.push(MemoryWrite { addr, size, old });
+            }
+
+            self.dram.store(offset, size, value);
+        }
+    }
+
+    fn read(&self, addr: usize, size: u8) -> u32 {
+        if let Some((i, offset)) = self.device_at(addr) {
+            return self.devices[i].1.load(offset, size);
+        }
+
+        match self.offset(addr, size) {
+            Some(offset) => self.dram.load(offset, size),
+            None => 0,
         }
     }
+
+    // ---- Watchpoints ----
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        self.watchpoints.push(watchpoint);
+    }
+
+    /// Removes a watchpoint, returns false if there wasn't one
+    pub fn remove_watchpoint(&mut self, watchpoint: &Watchpoint) -> bool {
+        let len = self.watchpoints.len();
+        self.watchpoints.retain(|w| w != watchpoint);
+        self.watchpoints.len() != len
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    /// Returns and clears the first access that triggered a watchpoint
+    pub fn take_watch_hit(&mut self) -> Option<WatchHit> {
+        self.watch_hit.take()
+    }
+
+    fn watch(&self, addr: usize, size: u8, access: Access, old: u32, new: u32) {
+        if self.watchpoints.is_empty() || self.watch_hit.get().is_some() {
+            return;
+        }
+
+        let watchpoint = self
+            .watchpoints
+            .iter()
+            .find(|w| w.matches(addr, size, access));
+        if let Some(watchpoint) = watchpoint {
+            self.watch_hit.set(Some(WatchHit {
+                watchpoint: *watchpoint,
+                access,
+                addr,
+                size,
+                old,
+                new,
+            }));
+        }
+    }
+
+    // ---- Journal ----
+
+    /// Starts or stops recording memory writes, device writes aren't recorded
+    pub fn record_writes(&mut self, enable: bool) {
+        self.journal = enable.then(Vec::new);
+    }
+
+    /// Returns and clears the recorded writes, oldest first
+    pub fn take_writes(&mut self) -> Vec<MemoryWrite> {
+        self.journal
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Restores the old values of `writes`, newest first, without triggering watchpoints
+    pub fn undo_writes(&mut self, writes: &[MemoryWrite]) {
+        for write in writes.iter().rev() {
+            if let Some(offset) = self.offset(write.addr, write.size) {
+                self.dram.store(offset, write.size, write.old);
+            }
+        }
+    }
+
+    // ---- Trace ----
+
+    /// Starts or stops recording data accesses, and instruction fetches if `fetches` is set
+    ///
+    /// Bulk accesses through `load_bytes` and `store_bytes` aren't recorded.
+    pub fn trace_accesses(&mut self, enable: bool, fetches: bool) {
+        self.accesses = enable.then(RefCell::default);
+        self.trace_fetches = enable && fetches;
+    }
+
+    /// Returns and clears the recorded accesses, oldest first
+    pub fn take_accesses(&mut self) -> Vec<MemoryAccess> {
+        self.accesses
+            .as_mut()
+            .map(|accesses| std::mem::take(accesses.get_mut()))
+            .unwrap_or_default()
+    }
+
+    fn trace(&self, kind: AccessKind, addr: usize, size: u8, mut value: u32) {
+        if let Some(accesses) = &self.accesses {
+            // stores get the whole register
+            if size < 32 {
+                value &= (1 << size) - 1;
+            }
+            accesses.borrow_mut().push(MemoryAccess {
+                kind,
+                addr: addr as u32,
+                size,
+                value,
+            });
+        }
+    }
+
+    // ---- Devices ----
+
+    pub fn attach(&mut self, addr: usize, device: Box<dyn Device>) {
+        self.devices.push((addr, device));
+    }
+
+    /// The first attached device of type `T`
+    pub fn device<T: Device + 'static>(&self) -> Option<&T> {
+        self.devices
+            .iter()
+            .find_map(|(_, device)| device.as_any().downcast_ref())
+    }
+
+    pub fn device_mut<T: Device + 'static>(&mut self) -> Option<&mut T> {
+        self.devices
+            .iter_mut()
+            .find_map(|(_, device)| device.as_any_mut().downcast_mut())
+    }
+
+    // ---- Snapshot ----
+
+    /// Saves memory and the state of the devices
+    pub fn save(&self, snapshot: &mut Snapshot) {
+        snapshot.memory_base = self.base as u32;
+        snapshot.memory = self.dram.read(0, self.dram.size()).unwrap().to_vec();
+        snapshot.devices = self
+            .devices
+            .iter()
+            .map(|(addr, device)| (*addr as u32, device.save()))
+            .collect();
+    }
+
+    /// Replaces memory and the state of the devices, the same devices have to be attached
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), SnapshotError> {
+        let addrs = snapshot.devices.iter().map(|(addr, _)| *addr as usize);
+        if !addrs.eq(self.devices.iter().map(|(addr, _)| *addr)) {
+            return Err(SnapshotError::DeviceMismatch);
+        }
+        if !snapshot.memory.len().is_multiple_of(4) {
+            return Err(SnapshotError::Malformed);
+        }
+
+        for ((addr, device), (_, state)) in self.devices.iter_mut().zip(&snapshot.devices) {
+            device
+                .restore(state)
+                .map_err(|reason| SnapshotError::Device(*addr as u32, reason))?;
+        }
+
+        // the snapshot may have been taken with a different amount of memory
+        if snapshot.memory.len() != self.dram.size() {
+            self.dram = DRam::new(snapshot.memory.len());
+        }
+        self.dram.write(0, &snapshot.memory);
+        self.base = snapshot.memory_base as usize;
+
+        Ok(())
+    }
+
+    // ---- Bulk ----
+
+    pub fn load_bytes(&self, addr: usize, len: usize) -> Result<&[u8], OutOfRange> {
+        addr.checked_sub(self.base)
+            .and_then(|i| self.dram.read(i, len))
+            .ok_or(OutOfRange { addr, len })
+    }
+
+    pub fn store_bytes(&mut self, addr: usize, bytes: &[u8]) -> Result<(), OutOfRange> {
+        let len = bytes.len();
+        addr.checked_sub(self.base)
+            .and_then(|i| self.dram.write(i, bytes))
+            .ok_or(OutOfRange { addr, len })
+    }
+
+    /// Address of the first `pattern` in memory at or after `from`, wrapping around to the start
+    pub fn find(&self, pattern: &[u8], from: usize) -> Option<usize> {
+        if pattern.is_empty() {
+            return None;
+        }
+
+        let memory = self.dram.read(0, self.dram.size())?;
+        let start = from.saturating_sub(self.base).min(memory.len());
+        let position = |memory: &[u8]| memory.windows(pattern.len()).position(|w| w == pattern);
+
+        // the wrapped search may overlap `start` for a match that begins before it
+        let wrapped = &memory[..(start + pattern.len() - 1).min(memory.len())];
+        position(&memory[start..])
+            .map(|i| start + i)
+            .or_else(|| position(wrapped))
+            .map(|offset| self.base + offset)
+    }
 }
 
 impl Debug for Bus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Bus")
             .field("dram", &self.dram.size())
+            .field("base", &self.base)
+            .field("devices", &self.devices)
+            .field("watchpoints", &self.watchpoints)
             .finish()
     }
 }
@@ -0,0 +1,414 @@
+//! A cache hierarchy model, fed the accesses the [`Bus`](crate::bus::Bus) traces
+//!
+//! Only tags are modelled, memory always holds the data. Split L1 instruction and data caches
+//! sit in front of an optional unified L2, which sits in front of memory.
+
+use std::{fmt::Display, str::FromStr};
+
+use crate::{
+    trace::{AccessKind, MemoryAccess},
+    util::parse_u32,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Replacement {
+    /// Evicts the least recently used line
+    Lru,
+    /// Evicts the line that was filled first
+    Fifo,
+    Random,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WritePolicy {
+    /// Writes allocate a line and mark it dirty, dirty lines are written back when evicted
+    WriteBack,
+    /// Writes go on to the next level, a miss doesn't allocate a line
+    WriteThrough,
+}
+
+/// The geometry and policies of one cache
+///
+/// Parsed from `key=value` pairs like `size=16K,ways=4,line=32,replace=lru,write=back,latency=1`,
+/// keys that are left out keep their default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheConfig {
+    /// Capacity in bytes
+    pub size: u32,
+    pub ways: u32,
+    /// Line size in bytes
+    pub line: u32,
+    pub replacement: Replacement,
+    pub write: WritePolicy,
+    /// Cycles to look up a line
+    pub latency: u32,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            size: 16 * 1024,
+            ways: 4,
+            line: 32,
+            replacement: Replacement::Lru,
+            write: WritePolicy::WriteBack,
+            latency: 1,
+        }
+    }
+}
+
+impl CacheConfig {
+    pub fn sets(&self) -> u32 {
+        self.size / (self.ways * self.line)
+    }
+}
+
+impl FromStr for CacheConfig {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut config = CacheConfig::default();
+        for pair in text
+            .split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+        {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or(format!("expected `key=value`, got `{}`", pair))?;
+            let number = || bytes(value).ok_or(format!("invalid {} `{}`", key, value));
+
+            match key {
+                "size" => config.size = number()?,
+                "ways" => config.ways = number()?,
+                "line" => config.line = number()?,
+                "latency" => config.latency = number()?,
+                "replace" => {
+                    config.replacement = match value {
+                        "lru" => Replacement::Lru,
+                        "fifo" => Replacement::Fifo,
+                        "random" => Replacement::Random,
+                        _ => return Err(format!("unknown replacement policy `{}`", value)),
+                    }
+                }
+                "write" => {
+                    config.write = match value {
+                        "back" => WritePolicy::WriteBack,
+                        "through" => WritePolicy::WriteThrough,
+                        _ => return Err(format!("unknown write policy `{}`", value)),
+                    }
+                }
+                _ => return Err(format!("unknown cache option `{}`", key)),
+            }
+        }
+
+        if !config.line.is_power_of_two() || config.line < 4 {
+            return Err("the line size has to be a power of two of at least 4".into());
+        }
+        if config.ways == 0 {
+            return Err("a cache needs at least one way".into());
+        }
+        let way_bytes = config.ways.checked_mul(config.line);
+        if way_bytes.is_none_or(|bytes| !config.size.is_multiple_of(bytes)) {
+            return Err("the size has to be a multiple of ways * line".into());
+        }
+        if !config.sets().is_power_of_two() {
+            return Err("size / (ways * line) has to be a power of two".into());
+        }
+        Ok(config)
+    }
+}
+
+impl Display for CacheConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let replacement = match self.replacement {
+            Replacement::Lru => "LRU",
+            Replacement::Fifo => "FIFO",
+            Replacement::Random => "random",
+        };
+        let write = match self.write {
+            WritePolicy::WriteBack => "write-back",
+            WritePolicy::WriteThrough => "write-through",
+        };
+        write!(
+            f,
+            "{}K, {}-way, {}B lines, {}, {}",
+            self.size as f64 / 1024.0,
+            self.ways,
+            self.line,
+            replacement,
+            write
+        )
+    }
+}
+
+/// A number with an optional `K` or `M` suffix
+fn bytes(text: &str) -> Option<u32> {
+    let (number, unit) = match text.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&text[..i], 1024),
+        Some((i, 'm' | 'M')) => (&text[..i], 1024 * 1024),
+        _ => (text, 1),
+    };
+    parse_u32(number)?.checked_mul(unit)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub reads: u64,
+    pub read_misses: u64,
+    pub writes: u64,
+    pub write_misses: u64,
+    /// Dirty lines written to the next level when evicted
+    pub writebacks: u64,
+}
+
+impl CacheStats {
+    pub fn accesses(&self) -> u64 {
+        self.reads + self.writes
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.read_misses + self.write_misses
+    }
+
+    pub fn miss_rate(&self) -> f64 {
+        self.misses() as f64 / self.accesses().max(1) as f64
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Line {
+    valid: bool,
+    dirty: bool,
+    tag: u32,
+    /// When the line was last used for LRU, or filled for FIFO
+    stamp: u64,
+}
+
+/// What an access did to a cache
+struct Outcome {
+    hit: bool,
+    /// Address of a dirty line the access evicted
+    writeback: Option<u32>,
+}
+
+/// One set-associative cache
+#[derive(Debug, Clone)]
+pub struct Cache {
+    pub config: CacheConfig,
+    pub stats: CacheStats,
+    /// `ways` lines per set
+    lines: Vec<Line>,
+    /// Counts accesses, for the line stamps
+    clock: u64,
+    /// xorshift state for random replacement, seeded so runs repeat
+    seed: u32,
+}
+
+impl Cache {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            stats: CacheStats::default(),
+            lines: vec![Line::default(); (config.sets() * config.ways) as usize],
+            clock: 0,
+            seed: 0x2545_f491,
+        }
+    }
+
+    /// Looks up the line holding `addr`, filling it on a miss unless it's a write-through write
+    fn access(&mut self, addr: u32, write: bool) -> Outcome {
+        self.clock += 1;
+        let line_bits = self.config.line.trailing_zeros();
+        let set = (addr >> line_bits) & (self.config.sets() - 1);
+        let tag = addr >> line_bits >> self.config.sets().trailing_zeros();
+
+        if write {
+            self.stats.writes += 1;
+        } else {
+            self.stats.reads += 1;
+        }
+
+        let ways = self.config.ways as usize;
+        let start = set as usize * ways;
+        let lines = &mut self.lines[start..start + ways];
+
+        if let Some(line) = lines.iter_mut().find(|l| l.valid && l.tag == tag) {
+            if self.config.replacement == Replacement::Lru {
+                line.stamp = self.clock;
+            }
+            line.dirty |= write && self.config.write == WritePolicy::WriteBack;
+            return Outcome {
+                hit: true,
+                writeback: None,
+            };
+        }
+
+        if write {
+            self.stats.write_misses += 1;
+            if self.config.write == WritePolicy::WriteThrough {
+                return Outcome {
+                    hit: false,
+                    writeback: None,
+                };
+            }
+        } else {
+            self.stats.read_misses += 1;
+        }
+
+        let victim = match lines.iter().position(|l| !l.valid) {
+            Some(way) => way,
+            None => match self.config.replacement {
+                Replacement::Lru | Replacement::Fifo => {
+                    let oldest = lines.iter().enumerate().min_by_key(|(_, l)| l.stamp);
+                    oldest.map_or(0, |(way, _)| way)
+                }
+                Replacement::Random => {
+                    self.seed ^= self.seed << 13;
+                    self.seed ^= self.seed >> 17;
+                    self.seed ^= self.seed << 5;
+                    self.seed as usize % ways
+                }
+            },
+        };
+
+        let line = &mut lines[victim];
+        let writeback = (line.valid && line.dirty).then(|| {
+            let sets_bits = self.config.sets().trailing_zeros();
+            ((line.tag << sets_bits) | set) << line_bits
+        });
+        if writeback.is_some() {
+            self.stats.writebacks += 1;
+        }
+
+        *line = Line {
+            valid: true,
+            dirty: write,
+            tag,
+            stamp: self.clock,
+        };
+        Outcome {
+            hit: false,
+            writeback,
+        }
+    }
+}
+
+/// Split L1 caches, an optional L2 and memory
+#[derive(Debug, Clone)]
+pub struct CacheHierarchy {
+    pub l1i: Cache,
+    pub l1d: Cache,
+    pub l2: Option<Cache>,
+    /// Cycles to reach memory after missing every cache
+    pub memory_latency: u32,
+    /// Line reads and writes that reached memory
+    pub memory_reads: u64,
+    pub memory_writes: u64,
+    /// Total cycles the accesses took, writes to the next level are buffered and free
+    pub cycles: u64,
+    pub accesses: u64,
+}
+
+impl CacheHierarchy {
+    pub fn new(
+        l1i: CacheConfig,
+        l1d: CacheConfig,
+        l2: Option<CacheConfig>,
+        memory_latency: u32,
+    ) -> Self {
+        Self {
+            l1i: Cache::new(l1i),
+            l1d: Cache::new(l1d),
+            l2: l2.map(Cache::new),
+            memory_latency,
+            memory_reads: 0,
+            memory_writes: 0,
+            cycles: 0,
+            accesses: 0,
+        }
+    }
+
+    /// Runs an access through the caches, returns the cycles it took
+    pub fn access(&mut self, access: &MemoryAccess) -> u32 {
+        let write = access.kind == AccessKind::Write;
+        let line = match access.kind {
+            AccessKind::Fetch => self.l1i.config.line,
+            AccessKind::Read | AccessKind::Write => self.l1d.config.line,
+        };
+
+        // a misaligned access can touch two lines
+        let first = access.addr & !(line - 1);
+        let end = access
+            .addr
+            .wrapping_add((access.size as u32 / 8).max(1) - 1);
+        let last = end & !(line - 1);
+
+        let mut cycles = 0;
+        let mut addr = first;
+        loop {
+            cycles += self.l1_access(access.kind, addr, write);
+            if addr == last {
+                break;
+            }
+            addr = addr.wrapping_add(line);
+        }
+
+        self.accesses += 1;
+        self.cycles += cycles as u64;
+        cycles
+    }
+
+    fn l1_access(&mut self, kind: AccessKind, addr: u32, write: bool) -> u32 {
+        let l1 = match kind {
+            AccessKind::Fetch => &mut self.l1i,
+            AccessKind::Read | AccessKind::Write => &mut self.l1d,
+        };
+        let outcome = l1.access(addr, write);
+        let latency = l1.config.latency;
+        let through = write && l1.config.write == WritePolicy::WriteThrough;
+
+        if let Some(victim) = outcome.writeback {
+            self.l2_access(victim, true);
+        }
+        if through {
+            self.l2_access(addr, true);
+            latency
+        } else if outcome.hit {
+            latency
+        } else {
+            latency + self.l2_access(addr, false)
+        }
+    }
+
+    /// A line fill or write from an L1, returns the cycles it took
+    fn l2_access(&mut self, addr: u32, write: bool) -> u32 {
+        let Some(l2) = &mut self.l2 else {
+            return self.memory(write);
+        };
+        let outcome = l2.access(addr, write);
+        let latency = l2.config.latency;
+        let through = write && l2.config.write == WritePolicy::WriteThrough;
+
+        if outcome.writeback.is_some() {
+            self.memory(true);
+        }
+        if through {
+            self.memory(true);
+            latency
+        } else if outcome.hit {
+            latency
+        } else {
+            latency + self.memory(false)
+        }
+    }
+
+    fn memory(&mut self, write: bool) -> u32 {
+        if write {
+            self.memory_writes += 1;
+        } else {
+            self.memory_reads += 1;
+        }
+        self.memory_latency
+    }
+}
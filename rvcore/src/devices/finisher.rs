@@ -0,0 +1,67 @@
+use std::any::Any;
+
+use super::Device;
+
+const FINISHER_FAIL: u32 = 0x3333;
+const FINISHER_PASS: u32 = 0x5555;
+
+/// The SiFive test finisher, as found at `0x100000` on QEMU's `virt` machine
+///
+/// Writing `0x5555` exits with code 0, `(code << 16) | 0x3333` exits with `code`.
+#[derive(Debug, Default)]
+pub struct TestFinisher {
+    exit: Option<u32>,
+}
+
+impl TestFinisher {
+    /// Takes the exit code the guest requested, if any
+    pub fn take_exit(&mut self) -> Option<u32> {
+        self.exit.take()
+    }
+}
+
+impl Device for TestFinisher {
+    fn size(&self) -> usize {
+        0x1000
+    }
+
+    fn load(&self, _offset: usize, _size: u8) -> u32 {
+        0
+    }
+
+    fn store(&mut self, offset: usize, _size: u8, value: u32) {
+        if offset != 0 {
+            return;
+        }
+
+        match value & 0xffff {
+            FINISHER_PASS => self.exit = Some(0),
+            FINISHER_FAIL => self.exit = Some(value >> 16),
+            _ => (),
+        }
+    }
+
+    /// An exit code the guest requested but nobody took yet
+    fn save(&self) -> Vec<u8> {
+        self.exit
+            .map(|code| code.to_le_bytes().to_vec())
+            .unwrap_or_default()
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), &'static str> {
+        self.exit = match state.len() {
+            0 => None,
+            4 => Some(u32::from_le_bytes(state.try_into().unwrap())),
+            _ => return Err("test finisher state is malformed"),
+        };
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,32 @@
+//! Memory-mapped devices that can be attached to the [`Bus`](crate::bus::Bus)
+
+mod finisher;
+mod uart;
+
+pub use finisher::TestFinisher;
+pub use uart::Uart;
+
+use std::{any::Any, fmt::Debug};
+
+pub trait Device: Debug {
+    /// Size of the address range the device responds to
+    fn size(&self) -> usize;
+
+    /// `offset` is relative to the address the device is attached at
+    fn load(&self, offset: usize, size: u8) -> u32;
+
+    fn store(&mut self, offset: usize, size: u8, value: u32);
+
+    /// State to keep in a [`Snapshot`](crate::snapshot::Snapshot), nothing by default
+    fn save(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// Restores the state [`Device::save`] returned
+    fn restore(&mut self, _state: &[u8]) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
@@ -0,0 +1,95 @@
+use std::{any::Any, cell::RefCell, collections::VecDeque};
+
+use super::Device;
+
+const RBR_THR: usize = 0;
+const LSR: usize = 5;
+const LCR: usize = 3;
+
+/// Line status: a byte is waiting in the receive buffer
+const LSR_DATA_READY: u32 = 1 << 0;
+/// Line status: the transmitter can take a byte, and has finished sending
+const LSR_THR_EMPTY: u32 = (1 << 5) | (1 << 6);
+/// Line control: registers 0 and 1 hold the baud rate divisor
+const LCR_DLAB: u32 = 1 << 7;
+
+/// A 16550 UART, as found at `0x10000000` on QEMU's `virt` machine
+///
+/// Bytes written to the transmit register are kept until taken with [`Uart::take_output`],
+/// bytes given to [`Uart::push_input`] are read from the receive register.
+#[derive(Debug, Default)]
+pub struct Uart {
+    /// Reading the receive register pops a byte, but loads only borrow the device
+    input: RefCell<VecDeque<u8>>,
+    output: Vec<u8>,
+    registers: [u8; 8],
+}
+
+impl Uart {
+    pub fn push_input(&mut self, bytes: &[u8]) {
+        self.input.get_mut().extend(bytes);
+    }
+
+    /// Takes everything the guest transmitted since the last call
+    pub fn take_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.output)
+    }
+
+    fn dlab(&self) -> bool {
+        self.registers[LCR] as u32 & LCR_DLAB != 0
+    }
+}
+
+impl Device for Uart {
+    fn size(&self) -> usize {
+        0x100
+    }
+
+    fn load(&self, offset: usize, _size: u8) -> u32 {
+        match offset {
+            RBR_THR if !self.dlab() => self.input.borrow_mut().pop_front().unwrap_or(0) as u32,
+            LSR => {
+                let ready = !self.input.borrow().is_empty();
+                LSR_THR_EMPTY | if ready { LSR_DATA_READY } else { 0 }
+            }
+            0..=7 => self.registers[offset] as u32,
+            _ => 0,
+        }
+    }
+
+    fn store(&mut self, offset: usize, _size: u8, value: u32) {
+        match offset {
+            RBR_THR if !self.dlab() => self.output.push(value as u8),
+            // the line status is read-only
+            LSR => (),
+            0..=7 => self.registers[offset] = value as u8,
+            _ => (),
+        }
+    }
+
+    /// The registers, then the bytes waiting to be received
+    fn save(&self) -> Vec<u8> {
+        let mut state = self.registers.to_vec();
+        state.extend(self.input.borrow().iter());
+        state
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), &'static str> {
+        if state.len() < self.registers.len() {
+            return Err("UART state is too short");
+        }
+
+        let (registers, input) = state.split_at(self.registers.len());
+        self.registers.copy_from_slice(registers);
+        *self.input.get_mut() = input.iter().copied().collect();
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -5,7 +5,7 @@ pub struct DRam {
 
 impl DRam {
     pub fn new(len: usize) -> Self {
-        assert!(len % 4 == 0);
+        assert!(len.is_multiple_of(4));
 
         Self {
             inner: vec![0; len],
@@ -39,6 +39,11 @@ impl DRam {
         }
     }
 
+    /// Returns None if the range is out of bounds
+    pub fn read(&self, i: usize, len: usize) -> Option<&[u8]> {
+        self.inner.get(i..i.checked_add(len)?)
+    }
+
     // Store
     pub fn store(&mut self, i: usize, size: u8, value: u32) {
         match size {
@@ -58,4 +63,11 @@ impl DRam {
             _ => unimplemented!(),
         }
     }
+
+    /// Returns None if the range is out of bounds
+    pub fn write(&mut self, i: usize, bytes: &[u8]) -> Option<()> {
+        let end = i.checked_add(bytes.len())?;
+        self.inner.get_mut(i..end)?.copy_from_slice(bytes);
+        Some(())
+    }
 }
@@ -0,0 +1,446 @@
+use super::{Elf, ElfError};
+
+/// `DW_LNS_*` standard opcodes
+const LNS_COPY: u8 = 1;
+const LNS_ADVANCE_PC: u8 = 2;
+const LNS_ADVANCE_LINE: u8 = 3;
+const LNS_SET_FILE: u8 = 4;
+const LNS_CONST_ADD_PC: u8 = 8;
+const LNS_FIXED_ADVANCE_PC: u8 = 9;
+
+/// `DW_LNE_*` extended opcodes
+const LNE_END_SEQUENCE: u8 = 1;
+const LNE_SET_ADDRESS: u8 = 2;
+const LNE_DEFINE_FILE: u8 = 3;
+
+/// `DW_LNCT_path`, the only entry content of DWARF 5 file tables we need
+const LNCT_PATH: u64 = 1;
+const LNCT_DIRECTORY_INDEX: u64 = 2;
+
+/// `DW_FORM_*` that can appear in DWARF 5 directory and file tables
+const FORM_BLOCK: u64 = 0x09;
+const FORM_DATA1: u64 = 0x0b;
+const FORM_DATA2: u64 = 0x05;
+const FORM_DATA4: u64 = 0x06;
+const FORM_DATA8: u64 = 0x07;
+const FORM_DATA16: u64 = 0x1e;
+const FORM_STRING: u64 = 0x08;
+const FORM_STRP: u64 = 0x0e;
+const FORM_LINE_STRP: u64 = 0x1f;
+const FORM_UDATA: u64 = 0x0f;
+
+/// Instructions from `start` up to `end` that were generated for one source line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineRange {
+    pub start: u32,
+    pub end: u32,
+    /// Index into [`LineTable::files`]
+    pub file: usize,
+    pub line: u32,
+}
+
+/// The DWARF line tables of a program, mapping addresses back to source lines
+#[derive(Debug, Clone, Default)]
+pub struct LineTable {
+    files: Vec<String>,
+    /// Sorted by address
+    ranges: Vec<LineRange>,
+}
+
+impl LineTable {
+    /// Parses a `.debug_line` section, DWARF 2 to 5 with 32-bit offsets
+    ///
+    /// DWARF 5 file names may point into `.debug_line_str` or `.debug_str`.
+    pub fn parse(debug_line: &[u8], line_str: &[u8], str: &[u8]) -> Result<Self, ElfError> {
+        let mut table = LineTable::default();
+
+        let mut reader = Reader::new(debug_line);
+        while !reader.is_empty() {
+            let length = reader.u32()?;
+            if length == 0xffff_ffff {
+                return Err(ElfError::Unsupported("64-bit DWARF"));
+            }
+            let unit = reader.take(length as usize)?;
+            table.parse_unit(Reader::new(unit), line_str, str)?;
+        }
+
+        table.ranges.sort_by_key(|range| range.start);
+        Ok(table)
+    }
+
+    fn parse_unit(
+        &mut self,
+        mut reader: Reader,
+        line_str: &[u8],
+        str: &[u8],
+    ) -> Result<(), ElfError> {
+        let version = reader.u16()?;
+        if !(2..=5).contains(&version) {
+            return Err(ElfError::Unsupported("DWARF version"));
+        }
+        if version >= 5 {
+            // address and segment selector size
+            reader.take(2)?;
+        }
+
+        let header_length = reader.u32()? as usize;
+        let mut program = reader.clone();
+        program.take(header_length)?;
+
+        let min_inst_length = reader.u8()? as u32;
+        if version >= 4 {
+            // maximum operations per instruction, only for VLIW
+            reader.u8()?;
+        }
+        // every row counts, whether or not it's a recommended breakpoint
+        reader.u8()?;
+        let line_base = reader.u8()? as i8 as i64;
+        let line_range = reader.u8()?;
+        let opcode_base = reader.u8()?;
+        if line_range == 0 || opcode_base == 0 {
+            return Err(ElfError::Unsupported("line table header"));
+        }
+        let opcode_lengths = reader.take(opcode_base as usize - 1)?.to_vec();
+
+        // this unit's file numbers, as indices into `self.files`
+        let mut files = Vec::new();
+        if version >= 5 {
+            let directories = entries(&mut reader, line_str, str)?
+                .into_iter()
+                .map(|(path, _)| path)
+                .collect::<Vec<_>>();
+            for (name, directory) in entries(&mut reader, line_str, str)? {
+                let directory = directories.get(directory).map_or("", String::as_str);
+                files.push(self.file(join(directory, &name)));
+            }
+        } else {
+            let mut directories = Vec::new();
+            loop {
+                let directory = reader.string()?;
+                if directory.is_empty() {
+                    break;
+                }
+                directories.push(directory);
+            }
+
+            // numbered from 1, 0 is the compilation directory and not in this table
+            files.push(usize::MAX);
+            loop {
+                let name = reader.string()?;
+                if name.is_empty() {
+                    break;
+                }
+                let directory = reader.uleb()? as usize;
+                // modification time and length
+                reader.uleb()?;
+                reader.uleb()?;
+
+                let directory = directory
+                    .checked_sub(1)
+                    .and_then(|i| directories.get(i))
+                    .map_or("", String::as_str);
+                files.push(self.file(join(directory, &name)));
+            }
+        }
+
+        let mut reader = program;
+        let mut row = Row::new();
+        // the row before, its range ends where the next one starts
+        let mut previous: Option<Row> = None;
+        while !reader.is_empty() {
+            let opcode = reader.u8()?;
+            let mut emit = false;
+
+            if opcode >= opcode_base {
+                let adjusted = opcode - opcode_base;
+                row.address = row
+                    .address
+                    .wrapping_add((adjusted / line_range) as u32 * min_inst_length);
+                row.line = row
+                    .line
+                    .wrapping_add(line_base + (adjusted % line_range) as i64);
+                emit = true;
+            } else if opcode == 0 {
+                let length = reader.uleb()? as usize;
+                let mut extended = Reader::new(reader.take(length)?);
+                match extended.u8()? {
+                    LNE_END_SEQUENCE => {
+                        if let Some(previous) = previous.take() {
+                            self.range(&previous, row.address, &files);
+                        }
+                        row = Row::new();
+                        continue;
+                    }
+                    LNE_SET_ADDRESS => row.address = extended.u32()?,
+                    LNE_DEFINE_FILE => {
+                        let name = extended.string()?;
+                        files.push(self.file(name));
+                    }
+                    // discriminators and vendor extensions
+                    _ => (),
+                }
+            } else {
+                match opcode {
+                    LNS_COPY => emit = true,
+                    LNS_ADVANCE_PC => {
+                        let advance = (reader.uleb()? as u32).wrapping_mul(min_inst_length);
+                        row.address = row.address.wrapping_add(advance);
+                    }
+                    LNS_ADVANCE_LINE => row.line = row.line.wrapping_add(reader.sleb()?),
+                    LNS_SET_FILE => row.file = reader.uleb()? as usize,
+                    LNS_CONST_ADD_PC => {
+                        let advance = ((255 - opcode_base) / line_range) as u32 * min_inst_length;
+                        row.address = row.address.wrapping_add(advance);
+                    }
+                    LNS_FIXED_ADVANCE_PC => {
+                        row.address = row.address.wrapping_add(reader.u16()? as u32);
+                    }
+                    // columns, statement and block flags, and anything newer
+                    _ => {
+                        for _ in 0..opcode_lengths[opcode as usize - 1] {
+                            reader.uleb()?;
+                        }
+                    }
+                }
+            }
+
+            if emit {
+                if let Some(previous) = previous.replace(row) {
+                    self.range(&previous, row.address, &files);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds the range a row starts, unless it's empty or has no source line
+    fn range(&mut self, row: &Row, end: u32, files: &[usize]) {
+        let Some(file) = files.get(row.file).filter(|file| **file != usize::MAX) else {
+            return;
+        };
+        if row.line <= 0 || row.address >= end {
+            return;
+        }
+
+        self.ranges.push(LineRange {
+            start: row.address,
+            end,
+            file: *file,
+            line: row.line as u32,
+        });
+    }
+
+    /// Index of a file name, adding it if it's new
+    fn file(&mut self, name: String) -> usize {
+        match self.files.iter().position(|file| *file == name) {
+            Some(i) => i,
+            None => {
+                self.files.push(name);
+                self.files.len() - 1
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Source file names, as the compiler saw them
+    ///
+    /// Before DWARF 5 the compilation directory isn't part of the line table, so names can be
+    /// relative to it.
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    pub fn ranges(&self) -> &[LineRange] {
+        &self.ranges
+    }
+
+    /// The source file and line an address was generated for
+    pub fn lookup(&self, addr: u32) -> Option<(&str, u32)> {
+        let i = self.ranges.partition_point(|range| range.start <= addr);
+        let range = self.ranges[..i]
+            .iter()
+            .rev()
+            .find(|range| addr < range.end)?;
+        Some((&self.files[range.file], range.line))
+    }
+}
+
+impl Elf {
+    /// The line tables in `.debug_line`, empty for programs built without debug info
+    pub fn lines(&self) -> Result<LineTable, ElfError> {
+        let data = |name: &str| self.section(name).map_or(&[][..], |s| s.data.as_slice());
+        LineTable::parse(
+            data(".debug_line"),
+            data(".debug_line_str"),
+            data(".debug_str"),
+        )
+    }
+}
+
+/// State of the line number program
+#[derive(Clone, Copy)]
+struct Row {
+    address: u32,
+    file: usize,
+    line: i64,
+}
+
+impl Row {
+    fn new() -> Self {
+        Self {
+            address: 0,
+            file: 1,
+            line: 1,
+        }
+    }
+}
+
+/// Reads a DWARF 5 directory or file table, as paths and directory indices
+fn entries(
+    reader: &mut Reader,
+    line_str: &[u8],
+    str: &[u8],
+) -> Result<Vec<(String, usize)>, ElfError> {
+    let mut format = Vec::new();
+    for _ in 0..reader.u8()? {
+        format.push((reader.uleb()?, reader.uleb()?));
+    }
+
+    let mut entries = Vec::new();
+    for _ in 0..reader.uleb()? {
+        let (mut path, mut directory) = (String::new(), 0);
+        for (content, form) in &format {
+            let value = match *form {
+                FORM_STRING => Value::String(reader.string()?),
+                FORM_LINE_STRP => Value::String(super::string(line_str, reader.u32()? as usize)),
+                FORM_STRP => Value::String(super::string(str, reader.u32()? as usize)),
+                FORM_UDATA => Value::Number(reader.uleb()?),
+                FORM_DATA1 => Value::Number(reader.u8()? as u64),
+                FORM_DATA2 => Value::Number(reader.u16()? as u64),
+                FORM_DATA4 => Value::Number(reader.u32()? as u64),
+                FORM_DATA8 => Value::Number(u64::from_le_bytes(reader.array()?)),
+                // MD5 checksums
+                FORM_DATA16 => {
+                    reader.take(16)?;
+                    continue;
+                }
+                FORM_BLOCK => {
+                    let length = reader.uleb()? as usize;
+                    reader.take(length)?;
+                    continue;
+                }
+                _ => return Err(ElfError::Unsupported("line table entry form")),
+            };
+
+            match (*content, value) {
+                (LNCT_PATH, Value::String(value)) => path = value,
+                (LNCT_DIRECTORY_INDEX, Value::Number(value)) => directory = value as usize,
+                _ => (),
+            }
+        }
+        entries.push((path, directory));
+    }
+
+    Ok(entries)
+}
+
+enum Value {
+    String(String),
+    Number(u64),
+}
+
+fn join(directory: &str, name: &str) -> String {
+    if directory.is_empty() || name.starts_with('/') {
+        name.to_string()
+    } else {
+        format!("{}/{}", directory.trim_end_matches('/'), name)
+    }
+}
+
+/// Reads through a section from the front
+#[derive(Clone)]
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ElfError> {
+        if len > self.bytes.len() {
+            return Err(ElfError::Truncated);
+        }
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], ElfError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, ElfError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, ElfError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, ElfError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn uleb(&mut self) -> Result<u64, ElfError> {
+        let (mut value, mut shift) = (0u64, 0);
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                value |= ((byte & 0x7f) as u64) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn sleb(&mut self) -> Result<i64, ElfError> {
+        let (mut value, mut shift) = (0i64, 0);
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                value |= ((byte & 0x7f) as i64) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    value |= -1 << shift;
+                }
+                return Ok(value);
+            }
+        }
+    }
+
+    /// A null terminated string
+    fn string(&mut self) -> Result<String, ElfError> {
+        let end = self
+            .bytes
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or(ElfError::Truncated)?;
+        let string = String::from_utf8_lossy(&self.bytes[..end]).into_owned();
+        self.bytes = &self.bytes[end + 1..];
+        Ok(string)
+    }
+}
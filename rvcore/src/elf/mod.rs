@@ -0,0 +1,272 @@
+mod lines;
+mod symbols;
+mod writer;
+
+pub use lines::*;
+pub use symbols::*;
+
+use std::{error::Error, fmt::Display};
+
+pub const ELF_MAGIC: [u8; 4] = [0x7f, b'E', b'L', b'F'];
+pub const EM_RISCV: u16 = 243;
+
+pub const PT_LOAD: u32 = 1;
+pub const PF_X: u32 = 0b001;
+pub const PF_W: u32 = 0b010;
+pub const PF_R: u32 = 0b100;
+
+pub const SHT_PROGBITS: u32 = 1;
+pub const SHT_SYMTAB: u32 = 2;
+pub const SHT_STRTAB: u32 = 3;
+pub const SHT_NOBITS: u32 = 8;
+pub const SHF_WRITE: u32 = 0b001;
+pub const SHF_ALLOC: u32 = 0b010;
+pub const SHF_EXECINSTR: u32 = 0b100;
+
+// ---- Error ----
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ElfError {
+    /// The file doesn't start with `\x7fELF`
+    NotElf,
+    /// Only 32-bit little-endian RISC-V files are supported
+    Unsupported(&'static str),
+    /// A header or table points outside of the file
+    Truncated,
+}
+
+impl Display for ElfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ElfError::NotElf => write!(f, "not an ELF file"),
+            ElfError::Unsupported(what) => write!(f, "unsupported ELF file: {}", what),
+            ElfError::Truncated => write!(f, "truncated ELF file"),
+        }
+    }
+}
+
+impl Error for ElfError {}
+
+// ---- Elf ----
+
+/// A loadable segment
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub addr: u32,
+    /// Size in memory, bytes past `data` are zero filled
+    pub size: u32,
+    pub flags: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Section {
+    pub name: String,
+    pub kind: u32,
+    pub flags: u32,
+    pub addr: u32,
+    pub size: u32,
+    /// Empty for `SHT_NOBITS` sections
+    pub data: Vec<u8>,
+}
+
+impl Section {
+    pub fn is_executable(&self) -> bool {
+        self.flags & SHF_EXECINSTR != 0
+    }
+}
+
+/// A parsed 32-bit little-endian RISC-V ELF file
+#[derive(Debug, Clone)]
+pub struct Elf {
+    pub entry: u32,
+    pub segments: Vec<Segment>,
+    pub sections: Vec<Section>,
+    pub symbols: SymbolTable,
+}
+
+impl Elf {
+    pub fn is_elf(bytes: &[u8]) -> bool {
+        bytes.starts_with(&ELF_MAGIC)
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self, ElfError> {
+        if !Self::is_elf(bytes) {
+            return Err(ElfError::NotElf);
+        }
+
+        let reader = Reader { bytes };
+        if reader.u8(4)? != 1 {
+            return Err(ElfError::Unsupported("not 32-bit"));
+        }
+        if reader.u8(5)? != 1 {
+            return Err(ElfError::Unsupported("not little-endian"));
+        }
+        if reader.u16(18)? != EM_RISCV {
+            return Err(ElfError::Unsupported("not RISC-V"));
+        }
+
+        let entry = reader.u32(24)?;
+        let phoff = reader.u32(28)? as usize;
+        let shoff = reader.u32(32)? as usize;
+        let phentsize = reader.u16(42)? as usize;
+        let phnum = reader.u16(44)? as usize;
+        let shentsize = reader.u16(46)? as usize;
+        let shnum = reader.u16(48)? as usize;
+        let shstrndx = reader.u16(50)? as usize;
+
+        // ---- Program Headers ----
+        let mut segments = Vec::new();
+        for i in 0..phnum {
+            let header = phoff + i * phentsize;
+            if reader.u32(header)? != PT_LOAD {
+                continue;
+            }
+
+            let offset = reader.u32(header + 4)? as usize;
+            let filesz = reader.u32(header + 16)? as usize;
+            segments.push(Segment {
+                addr: reader.u32(header + 8)?,
+                size: reader.u32(header + 20)?,
+                flags: reader.u32(header + 24)?,
+                data: reader.slice(offset, filesz)?.to_vec(),
+            });
+        }
+
+        // ---- Section Headers ----
+        struct RawSection {
+            name: u32,
+            kind: u32,
+            flags: u32,
+            addr: u32,
+            offset: usize,
+            size: usize,
+            link: usize,
+        }
+
+        let mut raw_sections = Vec::with_capacity(shnum);
+        for i in 0..shnum {
+            let header = shoff + i * shentsize;
+            raw_sections.push(RawSection {
+                name: reader.u32(header)?,
+                kind: reader.u32(header + 4)?,
+                flags: reader.u32(header + 8)?,
+                addr: reader.u32(header + 12)?,
+                offset: reader.u32(header + 16)? as usize,
+                size: reader.u32(header + 20)? as usize,
+                link: reader.u32(header + 24)? as usize,
+            });
+        }
+
+        let section_data = |section: &RawSection| -> Result<&[u8], ElfError> {
+            if section.kind == SHT_NOBITS {
+                Ok(&[])
+            } else {
+                reader.slice(section.offset, section.size)
+            }
+        };
+
+        let shstrtab = match raw_sections.get(shstrndx) {
+            Some(section) => section_data(section)?,
+            None => &[],
+        };
+
+        let mut sections = Vec::with_capacity(shnum);
+        for section in &raw_sections {
+            sections.push(Section {
+                name: string(shstrtab, section.name as usize),
+                kind: section.kind,
+                flags: section.flags,
+                addr: section.addr,
+                size: section.size as u32,
+                data: section_data(section)?.to_vec(),
+            });
+        }
+
+        // ---- Symbols ----
+        let mut symbols = Vec::new();
+        for section in raw_sections.iter().filter(|s| s.kind == SHT_SYMTAB) {
+            let table = section_data(section)?;
+            let strtab = match raw_sections.get(section.link) {
+                Some(strtab) => section_data(strtab)?,
+                None => &[],
+            };
+
+            let table = Reader { bytes: table };
+            // the first entry is always the null symbol
+            for i in 1..section.size / 16 {
+                let entry = i * 16;
+                let info = table.u8(entry + 12)?;
+                let index = table.u16(entry + 14)?;
+                let kind = match info & 0xf {
+                    0 => SymbolKind::NoType,
+                    1 => SymbolKind::Object,
+                    2 => SymbolKind::Func,
+                    _ => continue, // sections, files, ...
+                };
+
+                let name = string(strtab, table.u32(entry)? as usize);
+                // undefined symbols, and `$x`/`$d` mapping symbols
+                if index == 0 || name.is_empty() || name.starts_with('$') {
+                    continue;
+                }
+
+                symbols.push(Symbol {
+                    name,
+                    addr: table.u32(entry + 4)?,
+                    size: table.u32(entry + 8)?,
+                    kind,
+                    global: info >> 4 != 0,
+                    section: index,
+                });
+            }
+        }
+
+        Ok(Self {
+            entry,
+            segments,
+            sections,
+            symbols: SymbolTable::new(symbols),
+        })
+    }
+
+    pub fn section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|s| s.name == name)
+    }
+}
+
+// ---- Util ----
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn slice(&self, offset: usize, len: usize) -> Result<&'a [u8], ElfError> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.bytes.get(offset..end))
+            .ok_or(ElfError::Truncated)
+    }
+
+    fn u8(&self, offset: usize) -> Result<u8, ElfError> {
+        Ok(self.slice(offset, 1)?[0])
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16, ElfError> {
+        let b = self.slice(offset, 2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, ElfError> {
+        let b = self.slice(offset, 4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+}
+
+/// Reads a null terminated string from a string table
+fn string(table: &[u8], offset: usize) -> String {
+    let bytes = table.get(offset..).unwrap_or(&[]);
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
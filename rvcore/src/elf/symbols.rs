@@ -0,0 +1,99 @@
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    NoType,
+    Object,
+    Func,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub addr: u32,
+    pub size: u32,
+    pub kind: SymbolKind,
+    pub global: bool,
+    /// Index of the section the symbol is defined in
+    pub section: u16,
+}
+
+impl Symbol {
+    pub fn contains(&self, addr: u32) -> bool {
+        addr >= self.addr && addr - self.addr < self.size.max(1)
+    }
+}
+
+// ---- Symbol Table ----
+
+/// Symbols sorted by address
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,
+}
+
+impl SymbolTable {
+    pub fn new(mut symbols: Vec<Symbol>) -> Self {
+        // functions first, so they win over labels at the same address
+        symbols.sort_by_key(|s| (s.addr, s.kind != SymbolKind::Func, !s.global));
+        symbols.dedup_by(|a, b| a.name == b.name && a.addr == b.addr);
+        Self { symbols }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols.iter()
+    }
+
+    /// Finds a symbol by name
+    pub fn get(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|s| s.name == name)
+    }
+
+    /// Symbols that start exactly at `addr`
+    pub fn at(&self, addr: u32) -> impl Iterator<Item = &Symbol> {
+        let start = self.symbols.partition_point(|s| s.addr < addr);
+        self.symbols[start..]
+            .iter()
+            .take_while(move |s| s.addr == addr)
+    }
+
+    /// Finds the symbol `addr` is inside of
+    /// Sized symbols only match their own range, labels match until the next symbol
+    pub fn lookup(&self, addr: u32) -> Option<(&Symbol, u32)> {
+        let end = self.symbols.partition_point(|s| s.addr <= addr);
+        let last = self.symbols[..end].last()?;
+        let symbol = self.at(last.addr).next()?;
+        if symbol.size != 0 && !symbol.contains(addr) {
+            return None;
+        }
+
+        Some((symbol, addr - symbol.addr))
+    }
+
+    /// Formats `addr` as `symbol+0x10`, or `None` if it isn't inside a symbol
+    pub fn describe(&self, addr: u32) -> Option<SymbolOffset<'_>> {
+        self.lookup(addr)
+            .map(|(symbol, offset)| SymbolOffset { symbol, offset })
+    }
+}
+
+// ---- Display ----
+
+pub struct SymbolOffset<'a> {
+    pub symbol: &'a Symbol,
+    pub offset: u32,
+}
+
+impl Display for SymbolOffset<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.offset == 0 {
+            write!(f, "{}", self.symbol.name)
+        } else {
+            write!(f, "{}+{:#x}", self.symbol.name, self.offset)
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use super::{
+    Elf, SymbolKind, ELF_MAGIC, EM_RISCV, PF_R, PF_W, PF_X, PT_LOAD, SHF_ALLOC, SHF_EXECINSTR,
+    SHF_WRITE, SHT_NOBITS, SHT_STRTAB, SHT_SYMTAB,
+};
+
+const EHDR_SIZE: usize = 52;
+const PHDR_SIZE: usize = 32;
+const SHDR_SIZE: usize = 40;
+const SYM_SIZE: usize = 16;
+
+#[derive(Default)]
+struct SectionHeader {
+    name: u32,
+    kind: u32,
+    flags: u32,
+    addr: u32,
+    offset: u32,
+    size: u32,
+    link: u32,
+    info: u32,
+    align: u32,
+    entsize: u32,
+}
+
+impl Elf {
+    /// Serializes an executable ELF file
+    ///
+    /// Program headers are derived from the `SHF_ALLOC` sections, `segments` is ignored.
+    /// Symbol and string tables are generated from `symbols`, any in `sections` are dropped.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut shstrtab = StringTable::new();
+
+        // index 0 is always the null section
+        let mut remap = vec![0u16; self.sections.len()];
+        let sections: Vec<(usize, &super::Section)> = self
+            .sections
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| !matches!(s.kind, 0 | SHT_SYMTAB | SHT_STRTAB))
+            .collect();
+        for (new, (old, _)) in sections.iter().enumerate() {
+            remap[*old] = new as u16 + 1;
+        }
+
+        let alloc_count = sections
+            .iter()
+            .filter(|(_, s)| s.flags & SHF_ALLOC != 0)
+            .count();
+        let mut out = vec![0u8; EHDR_SIZE + alloc_count * PHDR_SIZE];
+
+        // ---- Section Data ----
+        let mut headers = vec![SectionHeader::default()];
+        let mut segments = 0;
+        for (_, section) in &sections {
+            align(&mut out, 4);
+            let offset = out.len() as u32;
+            let (filesz, size) = if section.kind == SHT_NOBITS {
+                (0, section.size)
+            } else {
+                out.extend_from_slice(&section.data);
+                (section.data.len() as u32, section.data.len() as u32)
+            };
+
+            let alloc = section.flags & SHF_ALLOC != 0;
+            headers.push(SectionHeader {
+                name: shstrtab.add(&section.name),
+                kind: section.kind,
+                flags: section.flags,
+                addr: section.addr,
+                offset,
+                size,
+                align: if alloc { 4 } else { 1 },
+                ..Default::default()
+            });
+
+            // ---- Program Header ----
+            if alloc {
+                let mut flags = PF_R;
+                if section.flags & SHF_EXECINSTR != 0 {
+                    flags |= PF_X;
+                }
+                if section.flags & SHF_WRITE != 0 {
+                    flags |= PF_W;
+                }
+
+                let at = EHDR_SIZE + segments * PHDR_SIZE;
+                put_u32(&mut out, at, PT_LOAD);
+                put_u32(&mut out, at + 4, offset);
+                put_u32(&mut out, at + 8, section.addr);
+                put_u32(&mut out, at + 12, section.addr);
+                put_u32(&mut out, at + 16, filesz);
+                put_u32(&mut out, at + 20, size);
+                put_u32(&mut out, at + 24, flags);
+                put_u32(&mut out, at + 28, 4);
+                segments += 1;
+            }
+        }
+
+        // ---- Symbols ----
+        // local symbols have to come before global ones
+        let mut symbols: Vec<_> = self.symbols.iter().collect();
+        symbols.sort_by_key(|s| s.global);
+
+        let mut strtab = StringTable::new();
+        align(&mut out, 4);
+        let symtab_offset = out.len() as u32;
+        out.extend_from_slice(&[0; SYM_SIZE]);
+        for symbol in &symbols {
+            let kind = match symbol.kind {
+                SymbolKind::NoType => 0,
+                SymbolKind::Object => 1,
+                SymbolKind::Func => 2,
+            };
+            let section = match remap.get(symbol.section as usize) {
+                Some(index) if *index != 0 => *index,
+                _ => symbol.section,
+            };
+
+            out.extend_from_slice(&strtab.add(&symbol.name).to_le_bytes());
+            out.extend_from_slice(&symbol.addr.to_le_bytes());
+            out.extend_from_slice(&symbol.size.to_le_bytes());
+            out.push(((symbol.global as u8) << 4) | kind);
+            out.push(0);
+            out.extend_from_slice(&section.to_le_bytes());
+        }
+
+        let symtab_index = headers.len() as u32;
+        headers.push(SectionHeader {
+            name: shstrtab.add(".symtab"),
+            kind: SHT_SYMTAB,
+            offset: symtab_offset,
+            size: ((symbols.len() + 1) * SYM_SIZE) as u32,
+            link: symtab_index + 1,
+            info: 1 + symbols.iter().filter(|s| !s.global).count() as u32,
+            align: 4,
+            entsize: SYM_SIZE as u32,
+            ..Default::default()
+        });
+
+        headers.push(SectionHeader {
+            name: shstrtab.add(".strtab"),
+            kind: SHT_STRTAB,
+            offset: out.len() as u32,
+            size: strtab.bytes.len() as u32,
+            align: 1,
+            ..Default::default()
+        });
+        out.extend_from_slice(&strtab.bytes);
+
+        let name = shstrtab.add(".shstrtab");
+        headers.push(SectionHeader {
+            name,
+            kind: SHT_STRTAB,
+            offset: out.len() as u32,
+            size: shstrtab.bytes.len() as u32,
+            align: 1,
+            ..Default::default()
+        });
+        out.extend_from_slice(&shstrtab.bytes);
+
+        // ---- Section Headers ----
+        align(&mut out, 4);
+        let shoff = out.len() as u32;
+        for header in &headers {
+            for value in [
+                header.name,
+                header.kind,
+                header.flags,
+                header.addr,
+                header.offset,
+                header.size,
+                header.link,
+                header.info,
+                header.align,
+                header.entsize,
+            ] {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        // ---- ELF Header ----
+        out[..4].copy_from_slice(&ELF_MAGIC);
+        out[4] = 1; // 32-bit
+        out[5] = 1; // little-endian
+        out[6] = 1; // version
+        put_u16(&mut out, 16, 2); // executable
+        put_u16(&mut out, 18, EM_RISCV);
+        put_u32(&mut out, 20, 1);
+        put_u32(&mut out, 24, self.entry);
+        put_u32(&mut out, 28, if segments == 0 { 0 } else { EHDR_SIZE as u32 });
+        put_u32(&mut out, 32, shoff);
+        put_u16(&mut out, 40, EHDR_SIZE as u16);
+        put_u16(&mut out, 42, PHDR_SIZE as u16);
+        put_u16(&mut out, 44, segments as u16);
+        put_u16(&mut out, 46, SHDR_SIZE as u16);
+        put_u16(&mut out, 48, headers.len() as u16);
+        put_u16(&mut out, 50, headers.len() as u16 - 1);
+
+        out
+    }
+}
+
+// ---- Util ----
+
+struct StringTable {
+    bytes: Vec<u8>,
+}
+
+impl StringTable {
+    /// Offset 0 is always the empty string
+    fn new() -> Self {
+        Self { bytes: vec![0] }
+    }
+
+    fn add(&mut self, name: &str) -> u32 {
+        if name.is_empty() {
+            return 0;
+        }
+
+        let offset = self.bytes.len() as u32;
+        self.bytes.extend_from_slice(name.as_bytes());
+        self.bytes.push(0);
+        offset
+    }
+}
+
+fn align(out: &mut Vec<u8>, to: usize) {
+    out.resize(out.len().next_multiple_of(to), 0);
+}
+
+fn put_u16(out: &mut [u8], at: usize, value: u16) {
+    out[at..at + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(out: &mut [u8], at: usize, value: u32) {
+    out[at..at + 4].copy_from_slice(&value.to_le_bytes());
+}
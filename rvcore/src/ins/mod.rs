@@ -14,7 +14,7 @@ pub const OPCODE_BRANCH: u32 = 0b1100011;
 pub const OPCODE_LOAD: u32 = 0b0000011;
 pub const OPCODE_STORE: u32 = 0b0100011;
 pub const OPCODE_SYSTEM: u32 = 0b1110011;
-pub const OPCODE_MISCMEM: u32 = 0b0000110;
+pub const OPCODE_MISCMEM: u32 = 0b0001111;
 
 pub type TypeOpImm = IType;
 pub type TypeLui = UType;
@@ -2,6 +2,7 @@
 
 use crate::util::sign_extend;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct RType {
     pub funct7: u8,
     pub rs2: u8,
@@ -32,6 +33,7 @@ impl RType {
 
 // ---- I-Type ----
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct IType {
     pub imm: i32,
     pub rs1: u8,
@@ -61,6 +63,7 @@ impl IType {
 
 // ---- U-Type ----
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct UType {
     pub imm: i32,
     pub rd: u8,
@@ -68,7 +71,7 @@ pub struct UType {
 
 impl UType {
     pub fn decode(ins: u32) -> Self {
-        let imm31_12 = ins & (0b11111111111111111111 << 12);
+        let imm31_12 = ins >> 12;
 
         Self {
             imm: sign_extend(imm31_12, 20),
@@ -77,12 +80,13 @@ impl UType {
     }
 
     pub fn encode(&self) -> u32 {
-        self.imm as u32 | ((self.rd as u32) << 7)
+        ((self.imm as u32) << 12) | ((self.rd as u32) << 7)
     }
 }
 
 // ---- S-Type ----
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct SType {
     pub imm: i32,
     pub rs2: u8,
@@ -92,11 +96,11 @@ pub struct SType {
 
 impl SType {
     pub fn decode(ins: u32) -> Self {
-        let imm11_5 = (ins >> 20) & (0b111111 << 5);
+        let imm11_5 = (ins >> 20) & (0b1111111 << 5);
         let imm4_0 = (ins >> 7) & 0b11111;
 
         Self {
-            imm: sign_extend(imm11_5 | imm4_0, 11),
+            imm: sign_extend(imm11_5 | imm4_0, 12),
             rs2: ((ins >> 20) & 0b11111) as u8,
             rs1: ((ins >> 15) & 0b11111) as u8,
             funct3: ((ins >> 12) & 0b111) as u8,
@@ -104,7 +108,7 @@ impl SType {
     }
 
     pub fn encode(&self) -> u32 {
-        let imm11_5 = (self.imm & (0b111111 << 5)) << 20;
+        let imm11_5 = (self.imm & (0b1111111 << 5)) << 20;
         let imm4_0 = (self.imm & 0b11111) << 7;
         imm4_0 as u32
             | ((self.funct3 as u32) << 12)
@@ -116,6 +120,7 @@ impl SType {
 
 // ---- B-Type ----
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct BType {
     pub imm: i32,
     pub rs2: u8,
@@ -131,7 +136,7 @@ impl BType {
         let imm11 = (ins << 4) & (0b1 << 11);
 
         Self {
-            imm: sign_extend(imm12 | imm11 | imm10_5 | imm4_1, 12),
+            imm: sign_extend(imm12 | imm11 | imm10_5 | imm4_1, 13),
             rs2: ((ins >> 20) & 0b11111) as u8,
             rs1: ((ins >> 15) & 0b11111) as u8,
             funct3: ((ins >> 12) & 0b111) as u8,
@@ -142,7 +147,7 @@ impl BType {
         let imm12 = (self.imm & (0b1 << 12)) << 19;
         let imm10_5 = (self.imm & (0b111111 << 5)) << 20;
         let imm4_1 = (self.imm & (0b1111 << 1)) << 7;
-        let imm11 = (self.imm & (0b1 << 1)) >> 4;
+        let imm11 = (self.imm & (0b1 << 11)) >> 4;
 
         imm11 as u32
             | imm4_1 as u32
@@ -156,6 +161,7 @@ impl BType {
 
 // ---- J-Type ----
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct JType {
     pub imm: i32,
     pub rd: u8,
@@ -169,7 +175,7 @@ impl JType {
         let imm19_12 = ins & (0b11111111 << 12);
 
         Self {
-            imm: sign_extend(imm10_1 | imm11 | imm19_12 | imm20, 20),
+            imm: sign_extend(imm10_1 | imm11 | imm19_12 | imm20, 21),
             rd: ((ins >> 7) & 0b11111) as u8,
         }
     }
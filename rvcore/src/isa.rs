@@ -0,0 +1,126 @@
+use std::{error::Error, fmt::Display, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Extension {
+    I,
+    M,
+    F,
+    Zicsr,
+}
+
+impl Display for Extension {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Extension::I => write!(f, "I"),
+            Extension::M => write!(f, "M"),
+            Extension::F => write!(f, "F"),
+            Extension::Zicsr => write!(f, "Zicsr"),
+        }
+    }
+}
+
+// ---- Isa ----
+
+/// The set of enabled extensions, written as an ISA string like `rv32imf_zicsr`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Isa {
+    pub m: bool,
+    pub f: bool,
+    pub zicsr: bool,
+}
+
+impl Isa {
+    pub const RV32I: Isa = Isa {
+        m: false,
+        f: false,
+        zicsr: false,
+    };
+
+    /// Every extension this emulator supports
+    pub const ALL: Isa = Isa {
+        m: true,
+        f: true,
+        zicsr: true,
+    };
+
+    pub fn supports(&self, extension: Extension) -> bool {
+        match extension {
+            Extension::I => true,
+            Extension::M => self.m,
+            Extension::F => self.f,
+            Extension::Zicsr => self.zicsr,
+        }
+    }
+}
+
+impl Default for Isa {
+    fn default() -> Self {
+        Self::RV32I
+    }
+}
+
+impl Display for Isa {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "rv32i")?;
+        if self.m {
+            write!(f, "m")?;
+        }
+        if self.f {
+            write!(f, "f")?;
+        }
+        if self.zicsr {
+            write!(f, "_zicsr")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IsaError {
+    /// The ISA string doesn't start with `rv32i`
+    Base(String),
+    Unsupported(String),
+}
+
+impl Display for IsaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IsaError::Base(text) => write!(f, "unsupported base ISA in `{}`", text),
+            IsaError::Unsupported(ext) => write!(f, "unsupported extension `{}`", ext),
+        }
+    }
+}
+
+impl Error for IsaError {}
+
+impl FromStr for Isa {
+    type Err = IsaError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let lower = text.to_ascii_lowercase();
+        let Some(rest) = lower.strip_prefix("rv32i") else {
+            return Err(IsaError::Base(text.into()));
+        };
+
+        let mut isa = Isa::RV32I;
+        let mut parts = rest.split('_');
+        for c in parts.next().unwrap_or_default().chars() {
+            match c {
+                'm' => isa.m = true,
+                'f' => isa.f = true,
+                _ => return Err(IsaError::Unsupported(c.into())),
+            }
+        }
+
+        for part in parts {
+            match part {
+                "zicsr" => isa.zicsr = true,
+                "" => (),
+                _ => return Err(IsaError::Unsupported(part.into())),
+            }
+        }
+
+        Ok(isa)
+    }
+}
@@ -1,7 +1,16 @@
 pub mod bus;
+pub mod cache;
+pub mod devices;
 mod dram;
+pub mod elf;
 pub mod ins;
+pub mod isa;
+pub mod loader;
+pub mod predictor;
+pub mod snapshot;
+pub mod trace;
 pub mod util;
+pub mod watch;
 
 pub type QUADWORD = i128;
 pub type DOUBLEWORD = i64;
@@ -0,0 +1,64 @@
+//! Intel HEX, `:LLAAAATT<data>CC` records
+
+use super::{hex_bytes, push, Chunk, LoadError};
+
+const DATA: u8 = 0x00;
+const END_OF_FILE: u8 = 0x01;
+const EXTENDED_SEGMENT_ADDRESS: u8 = 0x02;
+const START_SEGMENT_ADDRESS: u8 = 0x03;
+const EXTENDED_LINEAR_ADDRESS: u8 = 0x04;
+const START_LINEAR_ADDRESS: u8 = 0x05;
+
+/// Returns the start address, if any, and the data
+pub fn parse(text: &str) -> Result<(Option<u32>, Vec<Chunk>), LoadError> {
+    let mut chunks = Vec::new();
+    let mut entry = None;
+    let mut base = 0u32;
+
+    for (i, line) in text.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let Some(record) = line.strip_prefix(':') else {
+            return Err(LoadError::Record(line_number, "expected `:`"));
+        };
+
+        let bytes = hex_bytes(line_number, record)?;
+        if bytes.len() < 5 || bytes.len() != bytes[0] as usize + 5 {
+            return Err(LoadError::Record(line_number, "wrong record length"));
+        }
+
+        // all bytes including the checksum sum to zero
+        if bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) != 0 {
+            return Err(LoadError::Checksum(line_number));
+        }
+
+        let offset = u16::from_be_bytes([bytes[1], bytes[2]]) as u32;
+        let data = &bytes[4..bytes.len() - 1];
+        let value = |len: usize| {
+            if data.len() == len {
+                Ok(data.iter().fold(0u32, |v, b| (v << 8) | *b as u32))
+            } else {
+                Err(LoadError::Record(line_number, "wrong record length"))
+            }
+        };
+
+        match bytes[3] {
+            DATA => push(&mut chunks, base.wrapping_add(offset), data),
+            END_OF_FILE => break,
+            EXTENDED_SEGMENT_ADDRESS => base = value(2)? << 4,
+            START_SEGMENT_ADDRESS => {
+                let cs_ip = value(4)?;
+                entry = Some(((cs_ip >> 16) << 4) + (cs_ip & 0xffff));
+            }
+            EXTENDED_LINEAR_ADDRESS => base = value(2)? << 16,
+            START_LINEAR_ADDRESS => entry = Some(value(4)?),
+            _ => return Err(LoadError::Record(line_number, "unknown record type")),
+        }
+    }
+
+    Ok((entry, chunks))
+}
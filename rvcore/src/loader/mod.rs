@@ -0,0 +1,236 @@
+//! Loads ELF, Intel HEX, Motorola S-record and raw binary images into a [`Bus`]
+
+mod ihex;
+mod srec;
+
+use std::{error::Error, fmt::Display, path::Path, str::FromStr};
+
+use crate::{
+    bus::{Bus, OutOfRange, DRAM_ADDR},
+    elf::{Elf, ElfError, SymbolTable},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Elf,
+    IHex,
+    SRec,
+    Binary,
+}
+
+impl Format {
+    /// Guesses the format from the file contents, falling back to the extension
+    pub fn detect(path: Option<&Path>, bytes: &[u8]) -> Format {
+        if Elf::is_elf(bytes) {
+            return Format::Elf;
+        }
+
+        let extension = path
+            .and_then(|p| p.extension())
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("hex" | "ihex" | "ihx") => Format::IHex,
+            Some("srec" | "s19" | "s28" | "s37" | "mot") => Format::SRec,
+            Some("bin") => Format::Binary,
+            _ if bytes.starts_with(b":") => Format::IHex,
+            _ if bytes.starts_with(b"S0")
+                || bytes.starts_with(b"S1")
+                || bytes.starts_with(b"S3") =>
+            {
+                Format::SRec
+            }
+            _ => Format::Binary,
+        }
+    }
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Format::Elf => write!(f, "elf"),
+            Format::IHex => write!(f, "ihex"),
+            Format::SRec => write!(f, "srec"),
+            Format::Binary => write!(f, "bin"),
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Ok(match text.to_ascii_lowercase().as_str() {
+            "elf" => Format::Elf,
+            "hex" | "ihex" => Format::IHex,
+            "srec" | "s19" | "s28" | "s37" => Format::SRec,
+            "bin" | "binary" | "raw" => Format::Binary,
+            _ => return Err(format!("unknown image format `{}`", text)),
+        })
+    }
+}
+
+// ---- Error ----
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadError {
+    Elf(ElfError),
+    /// A malformed record, with its 1-based line
+    Record(usize, &'static str),
+    /// A record whose checksum doesn't match, with its 1-based line
+    Checksum(usize),
+    OutOfRange(OutOfRange),
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Elf(error) => write!(f, "{}", error),
+            LoadError::Record(line, reason) => write!(f, "line {}: {}", line, reason),
+            LoadError::Checksum(line) => write!(f, "line {}: checksum mismatch", line),
+            LoadError::OutOfRange(error) => write!(f, "image doesn't fit: {}", error),
+        }
+    }
+}
+
+impl Error for LoadError {}
+
+impl From<ElfError> for LoadError {
+    fn from(error: ElfError) -> Self {
+        LoadError::Elf(error)
+    }
+}
+
+impl From<OutOfRange> for LoadError {
+    fn from(error: OutOfRange) -> Self {
+        LoadError::OutOfRange(error)
+    }
+}
+
+// ---- Image ----
+
+/// A contiguous run of bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub addr: u32,
+    pub data: Vec<u8>,
+}
+
+/// A parsed memory image, not yet placed on the bus
+#[derive(Debug, Clone)]
+pub struct Image {
+    pub format: Format,
+    pub entry: u32,
+    pub chunks: Vec<Chunk>,
+    /// Only ELF files carry symbols
+    pub symbols: SymbolTable,
+}
+
+impl Image {
+    /// Parses an image, `base` is the load address of raw binaries
+    ///
+    /// Other formats embed their own addresses.
+    pub fn parse(bytes: &[u8], format: Format, base: u32) -> Result<Self, LoadError> {
+        let (entry, chunks, symbols) = match format {
+            Format::Elf => {
+                let elf = Elf::parse(bytes)?;
+                let chunks = elf
+                    .segments
+                    .iter()
+                    .map(|segment| {
+                        // zero fill past the file size, e.g. `.bss`
+                        let mut data = segment.data.clone();
+                        data.resize(segment.size.max(data.len() as u32) as usize, 0);
+                        Chunk {
+                            addr: segment.addr,
+                            data,
+                        }
+                    })
+                    .collect();
+                (Some(elf.entry), chunks, elf.symbols)
+            }
+            Format::IHex => {
+                let (entry, chunks) = ihex::parse(&text(bytes)?)?;
+                (entry, chunks, SymbolTable::default())
+            }
+            Format::SRec => {
+                let (entry, chunks) = srec::parse(&text(bytes)?)?;
+                (entry, chunks, SymbolTable::default())
+            }
+            Format::Binary => {
+                let chunk = Chunk {
+                    addr: base,
+                    data: bytes.to_vec(),
+                };
+                (Some(base), vec![chunk], SymbolTable::default())
+            }
+        };
+
+        // without a start address, start at the lowest one
+        let entry = entry
+            .or_else(|| chunks.iter().map(|c| c.addr).min())
+            .unwrap_or(DRAM_ADDR as u32);
+
+        Ok(Self {
+            format,
+            entry,
+            chunks,
+            symbols,
+        })
+    }
+
+    /// Copies every chunk onto the bus, nothing is written if a chunk doesn't fit
+    pub fn load(&self, bus: &mut Bus) -> Result<(), LoadError> {
+        for chunk in &self.chunks {
+            bus.load_bytes(chunk.addr as usize, chunk.data.len())?;
+        }
+
+        for chunk in &self.chunks {
+            bus.store_bytes(chunk.addr as usize, &chunk.data)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Detects the format of `path`, then parses and loads it
+pub fn load_file(bus: &mut Bus, path: &Path, base: u32) -> Result<Image, Box<dyn Error>> {
+    let bytes = std::fs::read(path)?;
+    let image = Image::parse(&bytes, Format::detect(Some(path), &bytes), base)?;
+    image.load(bus)?;
+    Ok(image)
+}
+
+fn text(bytes: &[u8]) -> Result<String, LoadError> {
+    String::from_utf8(bytes.to_vec()).map_err(|_| LoadError::Record(1, "not a text file"))
+}
+
+/// Parses a pair of hex digits per byte
+fn hex_bytes(line: usize, text: &str) -> Result<Vec<u8>, LoadError> {
+    if !text.is_ascii() {
+        return Err(LoadError::Record(line, "invalid hex digit"));
+    } else if !text.len().is_multiple_of(2) {
+        return Err(LoadError::Record(line, "odd number of hex digits"));
+    }
+
+    (0..text.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&text[i..i + 2], 16)
+                .map_err(|_| LoadError::Record(line, "invalid hex digit"))
+        })
+        .collect()
+}
+
+/// Appends `data` to the last chunk if it continues it
+fn push(chunks: &mut Vec<Chunk>, addr: u32, data: &[u8]) {
+    match chunks.last_mut() {
+        Some(last) if last.addr.wrapping_add(last.data.len() as u32) == addr => {
+            last.data.extend_from_slice(data)
+        }
+        _ => chunks.push(Chunk {
+            addr,
+            data: data.to_vec(),
+        }),
+    }
+}
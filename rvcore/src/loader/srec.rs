@@ -0,0 +1,54 @@
+//! Motorola S-records, `S<type><count><address><data><checksum>`
+
+use super::{hex_bytes, push, Chunk, LoadError};
+
+/// Returns the start address, if any, and the data
+pub fn parse(text: &str) -> Result<(Option<u32>, Vec<Chunk>), LoadError> {
+    let mut chunks = Vec::new();
+    let mut entry = None;
+
+    for (i, line) in text.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut chars = line.chars();
+        let (Some('S'), Some(kind)) = (chars.next(), chars.next()) else {
+            return Err(LoadError::Record(line_number, "expected `S`"));
+        };
+
+        let address_len = match kind {
+            '0' | '1' | '5' | '9' => 2,
+            '2' | '6' | '8' => 3,
+            '3' | '7' => 4,
+            _ => return Err(LoadError::Record(line_number, "unknown record type")),
+        };
+
+        let bytes = hex_bytes(line_number, chars.as_str())?;
+        if bytes.len() < address_len + 2 || bytes.len() != bytes[0] as usize + 1 {
+            return Err(LoadError::Record(line_number, "wrong record length"));
+        }
+
+        // the ones' complement of the sum of everything but the checksum
+        let (checksum, body) = bytes.split_last().unwrap();
+        if !body.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) != *checksum {
+            return Err(LoadError::Checksum(line_number));
+        }
+
+        let address = body[1..=address_len]
+            .iter()
+            .fold(0u32, |v, b| (v << 8) | *b as u32);
+        let data = &body[address_len + 1..];
+
+        match kind {
+            '1' | '2' | '3' => push(&mut chunks, address, data),
+            '7' | '8' | '9' => entry = Some(address),
+            // header and record counts
+            _ => (),
+        }
+    }
+
+    Ok((entry, chunks))
+}
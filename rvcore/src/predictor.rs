@@ -0,0 +1,344 @@
+//! Branch predictor models, fed the control flow of retired instructions
+//!
+//! Conditional branches go through every direction predictor side by side, so their
+//! mispredictions can be compared on the same run. Jumps go through a branch target buffer,
+//! returns through a return address stack.
+
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
+
+use crate::ins::{TypeJal, TypeJalR, OPCODE_JAL, OPCODE_JALR, OPCODE_MASK};
+
+/// Predicts whether conditional branches are taken
+pub trait BranchPredictor {
+    fn predict(&self, pc: u32) -> bool;
+
+    /// Learns the outcome of the branch at `pc`, after it was predicted
+    fn update(&mut self, pc: u32, taken: bool);
+}
+
+/// Always predicts not taken, what a core without a predictor falls through to
+pub struct NotTaken;
+
+impl BranchPredictor for NotTaken {
+    fn predict(&self, _pc: u32) -> bool {
+        false
+    }
+
+    fn update(&mut self, _pc: u32, _taken: bool) {}
+}
+
+/// A table of 2-bit saturating counters, indexed by the pc
+pub struct Bimodal {
+    counters: Vec<u8>,
+}
+
+impl Bimodal {
+    /// A table of `1 << bits` counters, all weakly not taken
+    pub fn new(bits: u32) -> Self {
+        Self {
+            counters: vec![1; 1 << bits],
+        }
+    }
+
+    fn index(&self, pc: u32) -> usize {
+        (pc >> 2) as usize & (self.counters.len() - 1)
+    }
+}
+
+impl BranchPredictor for Bimodal {
+    fn predict(&self, pc: u32) -> bool {
+        self.counters[self.index(pc)] >= 2
+    }
+
+    fn update(&mut self, pc: u32, taken: bool) {
+        let i = self.index(pc);
+        self.counters[i] = count(self.counters[i], taken);
+    }
+}
+
+/// 2-bit counters indexed by the pc xor the outcomes of the last branches
+pub struct Gshare {
+    counters: Vec<u8>,
+    /// The most recent outcome in the lowest bit
+    history: u32,
+}
+
+impl Gshare {
+    /// A table of `1 << bits` counters and `bits` of global history
+    pub fn new(bits: u32) -> Self {
+        Self {
+            counters: vec![1; 1 << bits],
+            history: 0,
+        }
+    }
+
+    fn index(&self, pc: u32) -> usize {
+        ((pc >> 2) ^ self.history) as usize & (self.counters.len() - 1)
+    }
+}
+
+impl BranchPredictor for Gshare {
+    fn predict(&self, pc: u32) -> bool {
+        self.counters[self.index(pc)] >= 2
+    }
+
+    fn update(&mut self, pc: u32, taken: bool) {
+        let i = self.index(pc);
+        self.counters[i] = count(self.counters[i], taken);
+        self.history = ((self.history << 1) | taken as u32) & (self.counters.len() as u32 - 1);
+    }
+}
+
+/// Moves a 2-bit saturating counter towards the outcome
+fn count(counter: u8, taken: bool) -> u8 {
+    if taken {
+        (counter + 1).min(3)
+    } else {
+        counter.saturating_sub(1)
+    }
+}
+
+/// A direction predictor and its size, parsed from `not-taken`, `bimodal:BITS` or `gshare:BITS`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PredictorConfig {
+    NotTaken,
+    /// Bits of pc that index the counters
+    Bimodal(u32),
+    /// Bits of pc and history that index the counters
+    Gshare(u32),
+}
+
+/// Index bits when the size is left out
+const DEFAULT_BITS: u32 = 10;
+
+impl PredictorConfig {
+    pub fn build(self) -> Box<dyn BranchPredictor> {
+        match self {
+            PredictorConfig::NotTaken => Box::new(NotTaken),
+            PredictorConfig::Bimodal(bits) => Box::new(Bimodal::new(bits)),
+            PredictorConfig::Gshare(bits) => Box::new(Gshare::new(bits)),
+        }
+    }
+}
+
+impl FromStr for PredictorConfig {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (name, bits) = match text.split_once(':') {
+            Some((name, bits)) => {
+                let bits = bits
+                    .parse()
+                    .ok()
+                    .filter(|bits| (1..=24).contains(bits))
+                    .ok_or(format!(
+                        "invalid predictor size `{}`, expected 1 to 24 bits",
+                        bits
+                    ))?;
+                (name, Some(bits))
+            }
+            None => (text, None),
+        };
+
+        match (name, bits) {
+            ("not-taken" | "static", None) => Ok(PredictorConfig::NotTaken),
+            ("bimodal", bits) => Ok(PredictorConfig::Bimodal(bits.unwrap_or(DEFAULT_BITS))),
+            ("gshare", bits) => Ok(PredictorConfig::Gshare(bits.unwrap_or(DEFAULT_BITS))),
+            _ => Err(format!(
+                "unknown predictor `{}`, expected not-taken, bimodal:BITS or gshare:BITS",
+                text
+            )),
+        }
+    }
+}
+
+impl Display for PredictorConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PredictorConfig::NotTaken => write!(f, "not-taken"),
+            PredictorConfig::Bimodal(bits) => write!(f, "bimodal:{}", bits),
+            PredictorConfig::Gshare(bits) => write!(f, "gshare:{}", bits),
+        }
+    }
+}
+
+/// A direct-mapped cache of jump targets, tagged with the whole pc
+pub struct Btb {
+    entries: Vec<Option<(u32, u32)>>,
+}
+
+impl Btb {
+    pub fn new(bits: u32) -> Self {
+        Self {
+            entries: vec![None; 1 << bits],
+        }
+    }
+
+    fn index(&self, pc: u32) -> usize {
+        (pc >> 2) as usize & (self.entries.len() - 1)
+    }
+
+    pub fn predict(&self, pc: u32) -> Option<u32> {
+        match self.entries[self.index(pc)] {
+            Some((tag, target)) if tag == pc => Some(target),
+            _ => None,
+        }
+    }
+
+    pub fn update(&mut self, pc: u32, target: u32) {
+        let i = self.index(pc);
+        self.entries[i] = Some((pc, target));
+    }
+}
+
+/// Return addresses of the calls in flight, the oldest is lost when it overflows
+pub struct Ras {
+    stack: Vec<u32>,
+    depth: usize,
+}
+
+impl Ras {
+    pub fn new(depth: usize) -> Self {
+        Self {
+            stack: Vec::with_capacity(depth),
+            depth,
+        }
+    }
+
+    pub fn push(&mut self, addr: u32) {
+        if self.depth == 0 {
+            return;
+        }
+        if self.stack.len() == self.depth {
+            self.stack.remove(0);
+        }
+        self.stack.push(addr);
+    }
+
+    pub fn pop(&mut self) -> Option<u32> {
+        self.stack.pop()
+    }
+}
+
+/// Executions of one conditional branch
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BranchRecord {
+    pub executed: u64,
+    pub taken: u64,
+    /// Mispredictions of each direction predictor, in the order they were configured
+    pub mispredicted: Vec<u64>,
+}
+
+/// Predictions and misses of jumps, through the BTB or the RAS
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JumpStats {
+    pub jumps: u64,
+    /// Jumps the BTB had no entry or the wrong target for
+    pub btb_misses: u64,
+    pub returns: u64,
+    /// Returns the RAS was empty or wrong for
+    pub ras_misses: u64,
+}
+
+/// Direction predictors side by side, a BTB and a RAS, and how well each of them did
+pub struct Predictors {
+    pub configs: Vec<PredictorConfig>,
+    predictors: Vec<Box<dyn BranchPredictor>>,
+    btb: Btb,
+    ras: Ras,
+
+    /// Every conditional branch that ran, by pc
+    pub branches: BTreeMap<u32, BranchRecord>,
+    pub jumps: JumpStats,
+}
+
+impl Predictors {
+    pub fn new(configs: Vec<PredictorConfig>, btb_bits: u32, ras_depth: usize) -> Self {
+        Self {
+            predictors: configs.iter().map(|config| config.build()).collect(),
+            configs,
+            btb: Btb::new(btb_bits),
+            ras: Ras::new(ras_depth),
+
+            branches: BTreeMap::new(),
+            jumps: JumpStats::default(),
+        }
+    }
+
+    /// Predicts the instruction `ins` at `pc` if it's a branch or jump, then learns where it went
+    ///
+    /// `taken` is the outcome of a branch, None for other instructions. It comes from the branch
+    /// condition, as a branch to `pc + 4` has the same `next_pc` taken or not.
+    pub fn retire(&mut self, pc: u32, ins: u32, next_pc: u32, taken: Option<bool>) {
+        if let Some(taken) = taken {
+            self.branch(pc, taken);
+            return;
+        }
+
+        match ins & OPCODE_MASK {
+            OPCODE_JAL => {
+                let rd = TypeJal::decode(ins).rd;
+                self.jump(pc, next_pc, rd, None);
+            }
+            OPCODE_JALR => {
+                let data = TypeJalR::decode(ins);
+                self.jump(pc, next_pc, data.rd, Some(data.rs1));
+            }
+            _ => (),
+        }
+    }
+
+    fn branch(&mut self, pc: u32, taken: bool) {
+        let record = self.branches.entry(pc).or_insert_with(|| BranchRecord {
+            mispredicted: vec![0; self.predictors.len()],
+            ..Default::default()
+        });
+        record.executed += 1;
+        record.taken += taken as u64;
+
+        for (predictor, mispredicted) in self.predictors.iter_mut().zip(&mut record.mispredicted) {
+            if predictor.predict(pc) != taken {
+                *mispredicted += 1;
+            }
+            predictor.update(pc, taken);
+        }
+    }
+
+    /// A `jal`, or a `jalr` with its `rs1`, using the RISC-V hints for calls and returns
+    fn jump(&mut self, pc: u32, target: u32, rd: u8, rs1: Option<u8>) {
+        let link = |reg: u8| reg == 1 || reg == 5;
+
+        if rs1.is_some_and(|rs1| link(rs1) && rs1 != rd) {
+            self.jumps.returns += 1;
+            if self.ras.pop() != Some(target) {
+                self.jumps.ras_misses += 1;
+            }
+        } else {
+            self.jumps.jumps += 1;
+            if self.btb.predict(pc) != Some(target) {
+                self.jumps.btb_misses += 1;
+            }
+            self.btb.update(pc, target);
+        }
+
+        if link(rd) {
+            self.ras.push(pc.wrapping_add(4));
+        }
+    }
+
+    /// Mispredictions of each direction predictor over every branch
+    pub fn mispredicted(&self) -> Vec<u64> {
+        let mut total = vec![0; self.predictors.len()];
+        for record in self.branches.values() {
+            for (total, count) in total.iter_mut().zip(&record.mispredicted) {
+                *total += count;
+            }
+        }
+        total
+    }
+
+    /// Conditional branches executed
+    pub fn executed(&self) -> u64 {
+        self.branches.values().map(|record| record.executed).sum()
+    }
+}
@@ -0,0 +1,212 @@
+//! The complete state of a machine, saved to and restored from a file
+
+use std::{error::Error, fmt::Display};
+
+/// Marks a snapshot file, followed by the format version
+const MAGIC: &[u8; 6] = b"RVSNAP";
+const VERSION: u16 = 1;
+
+/// Memory is stored in pages, all-zero pages are left out
+const PAGE: usize = 4096;
+/// Ends the list of pages
+const LAST_PAGE: u32 = u32::MAX;
+
+/// Registers, memory and device state
+///
+/// Each part of the machine saves itself into a snapshot and restores itself from one, so a
+/// snapshot can be taken of any combination of extensions.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    pub pc: u32,
+    pub registers: [u32; 32],
+    /// Bits of the float registers
+    pub float_registers: [u32; 32],
+    pub fcsr: u32,
+    /// CSRs with a non-zero value
+    pub csrs: Vec<(u16, u32)>,
+    /// Instructions retired so far
+    pub retired: u64,
+
+    /// Address of the first byte of `memory`
+    pub memory_base: u32,
+    pub memory: Vec<u8>,
+    /// Address and state of each attached device, in the order they were attached
+    pub devices: Vec<(u32, Vec<u8>)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// The file doesn't start with the snapshot magic
+    NotASnapshot,
+    UnsupportedVersion(u16),
+    /// The file ends in the middle of the state, or its contents don't fit together
+    Malformed,
+    /// The snapshot has more memory than the machine may have, with its size in bytes
+    MemoryTooLarge(u32),
+    /// The snapshot was taken with other devices attached
+    DeviceMismatch,
+    /// A device rejected its saved state, with the address it's attached at
+    Device(u32, &'static str),
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotError::NotASnapshot => write!(f, "not a snapshot"),
+            SnapshotError::UnsupportedVersion(version) => {
+                write!(f, "unsupported snapshot version {}", version)
+            }
+            SnapshotError::Malformed => write!(f, "snapshot is truncated or malformed"),
+            SnapshotError::MemoryTooLarge(size) => {
+                write!(
+                    f,
+                    "snapshot has {} bytes of memory, more than allowed",
+                    size
+                )
+            }
+            SnapshotError::DeviceMismatch => {
+                write!(f, "snapshot was taken with different devices attached")
+            }
+            SnapshotError::Device(addr, reason) => {
+                write!(f, "device at {:#x}: {}", addr, reason)
+            }
+        }
+    }
+}
+
+impl Error for SnapshotError {}
+
+impl Snapshot {
+    /// Encodes the snapshot, all numbers are little-endian
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(VERSION.to_le_bytes());
+
+        let word = |bytes: &mut Vec<u8>, value: u32| bytes.extend(value.to_le_bytes());
+        word(&mut bytes, self.pc);
+        for value in self.registers.iter().chain(&self.float_registers) {
+            word(&mut bytes, *value);
+        }
+        word(&mut bytes, self.fcsr);
+        bytes.extend(self.retired.to_le_bytes());
+
+        word(&mut bytes, self.csrs.len() as u32);
+        for (csr, value) in &self.csrs {
+            bytes.extend(csr.to_le_bytes());
+            word(&mut bytes, *value);
+        }
+
+        word(&mut bytes, self.memory_base);
+        word(&mut bytes, self.memory.len() as u32);
+        for (i, page) in self.memory.chunks(PAGE).enumerate() {
+            if page.iter().any(|byte| *byte != 0) {
+                word(&mut bytes, i as u32);
+                bytes.extend(page);
+            }
+        }
+        word(&mut bytes, LAST_PAGE);
+
+        word(&mut bytes, self.devices.len() as u32);
+        for (addr, state) in &self.devices {
+            word(&mut bytes, *addr);
+            word(&mut bytes, state.len() as u32);
+            bytes.extend(state);
+        }
+
+        bytes
+    }
+
+    /// Decodes a snapshot with at most `max_memory` bytes of memory
+    ///
+    /// The file is untrusted, so nothing is allocated for memory until every page was read.
+    pub fn from_bytes(bytes: &[u8], max_memory: usize) -> Result<Self, SnapshotError> {
+        if !bytes.starts_with(MAGIC) {
+            return Err(SnapshotError::NotASnapshot);
+        }
+
+        let mut reader = Reader {
+            bytes,
+            position: MAGIC.len(),
+        };
+        let version = u16::from_le_bytes(reader.array()?);
+        if version != VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let mut snapshot = Snapshot {
+            pc: reader.word()?,
+            ..Default::default()
+        };
+        for value in snapshot.registers.iter_mut() {
+            *value = reader.word()?;
+        }
+        for value in snapshot.float_registers.iter_mut() {
+            *value = reader.word()?;
+        }
+        snapshot.fcsr = reader.word()?;
+        snapshot.retired = u64::from_le_bytes(reader.array()?);
+
+        for _ in 0..reader.word()? {
+            let csr = u16::from_le_bytes(reader.array()?);
+            snapshot.csrs.push((csr, reader.word()?));
+        }
+
+        snapshot.memory_base = reader.word()?;
+        let size = reader.word()?;
+        if size as usize > max_memory {
+            return Err(SnapshotError::MemoryTooLarge(size));
+        }
+        let mut pages = Vec::new();
+        loop {
+            let page = reader.word()?;
+            if page == LAST_PAGE {
+                break;
+            }
+
+            let start = (page as usize)
+                .checked_mul(PAGE)
+                .filter(|start| *start < size as usize)
+                .ok_or(SnapshotError::Malformed)?;
+            let end = (start + PAGE).min(size as usize);
+            pages.push((start, reader.bytes(end - start)?));
+        }
+
+        for _ in 0..reader.word()? {
+            let addr = reader.word()?;
+            let len = reader.word()? as usize;
+            snapshot.devices.push((addr, reader.bytes(len)?.to_vec()));
+        }
+
+        snapshot.memory = vec![0; size as usize];
+        for (start, page) in pages {
+            snapshot.memory[start..start + page.len()].copy_from_slice(page);
+        }
+
+        Ok(snapshot)
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], SnapshotError> {
+        let end = self.position.checked_add(len);
+        let bytes = end
+            .and_then(|end| self.bytes.get(self.position..end))
+            .ok_or(SnapshotError::Malformed)?;
+
+        self.position += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], SnapshotError> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    fn word(&mut self) -> Result<u32, SnapshotError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+}
@@ -0,0 +1,220 @@
+//! Traces of the memory accesses that go through the [`Bus`](crate::bus::Bus)
+//!
+//! A trace is either text, a line per access:
+//!
+//! ```text
+//! # pc kind addr size value
+//! 00000010 X 00000010 4 00b50533
+//! 00000014 R 00002000 4 0000002a
+//! 00000018 W 00002004 1 00000007
+//! ```
+//!
+//! or binary, which [`TraceReader`] reads: `RVTRCE` and a `u16` version, then a 14-byte record
+//! per access with the pc, address and value as `u32`, the size in bytes as a `u8` and the kind
+//! as a `u8`, 0 for fetches, 1 for reads and 2 for writes. All numbers are little-endian.
+
+use std::{
+    fmt::Display,
+    io::{self, Read, Write},
+    str::FromStr,
+};
+
+/// Marks a binary trace, followed by the format version
+const MAGIC: &[u8; 6] = b"RVTRCE";
+const VERSION: u16 = 1;
+/// pc, address and value, then size and kind
+const RECORD_SIZE: usize = 14;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessKind {
+    /// An instruction fetch
+    Fetch,
+    Read,
+    Write,
+}
+
+impl AccessKind {
+    fn letter(self) -> char {
+        match self {
+            AccessKind::Fetch => 'X',
+            AccessKind::Read => 'R',
+            AccessKind::Write => 'W',
+        }
+    }
+}
+
+/// An access as the bus saw it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryAccess {
+    pub kind: AccessKind,
+    pub addr: u32,
+    /// Size in bits
+    pub size: u8,
+    /// The value read or written
+    pub value: u32,
+}
+
+/// An access and the instruction that made it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceRecord {
+    pub pc: u32,
+    pub kind: AccessKind,
+    pub addr: u32,
+    /// Size in bytes
+    pub size: u8,
+    pub value: u32,
+}
+
+impl TraceRecord {
+    pub fn new(pc: u32, access: MemoryAccess) -> Self {
+        Self {
+            pc,
+            kind: access.kind,
+            addr: access.addr,
+            size: access.size / 8,
+            value: access.value,
+        }
+    }
+
+    fn to_bytes(self) -> [u8; RECORD_SIZE] {
+        let mut bytes = [0; RECORD_SIZE];
+        bytes[0..4].copy_from_slice(&self.pc.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.addr.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.value.to_le_bytes());
+        bytes[12] = self.size;
+        bytes[13] = self.kind as u8;
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8; RECORD_SIZE]) -> io::Result<Self> {
+        let word = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        let kind = match bytes[13] {
+            0 => AccessKind::Fetch,
+            1 => AccessKind::Read,
+            2 => AccessKind::Write,
+            _ => return Err(malformed("unknown access kind")),
+        };
+
+        Ok(Self {
+            pc: word(0),
+            kind,
+            addr: word(4),
+            size: bytes[12],
+            value: word(8),
+        })
+    }
+}
+
+impl Display for TraceRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:08x} {} {:08x} {} {:08x}",
+            self.pc,
+            self.kind.letter(),
+            self.addr,
+            self.size,
+            self.value
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    Text,
+    Binary,
+}
+
+impl FromStr for TraceFormat {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_ascii_lowercase().as_str() {
+            "text" | "txt" => Ok(TraceFormat::Text),
+            "binary" | "bin" => Ok(TraceFormat::Binary),
+            _ => Err(format!("unknown trace format `{}`", text)),
+        }
+    }
+}
+
+/// Writes records in either format, wrap `out` in a `BufWriter` for files
+pub struct TraceWriter<W: Write> {
+    out: W,
+    format: TraceFormat,
+}
+
+impl<W: Write> TraceWriter<W> {
+    /// Starts a trace with its header
+    pub fn new(mut out: W, format: TraceFormat) -> io::Result<Self> {
+        match format {
+            TraceFormat::Text => writeln!(out, "# pc kind addr size value")?,
+            TraceFormat::Binary => {
+                out.write_all(MAGIC)?;
+                out.write_all(&VERSION.to_le_bytes())?;
+            }
+        }
+
+        Ok(Self { out, format })
+    }
+
+    pub fn write(&mut self, record: &TraceRecord) -> io::Result<()> {
+        match self.format {
+            TraceFormat::Text => writeln!(self.out, "{}", record),
+            TraceFormat::Binary => self.out.write_all(&record.to_bytes()),
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Reads the records of a binary trace, wrap `input` in a `BufReader` for files
+pub struct TraceReader<R: Read> {
+    input: R,
+}
+
+impl<R: Read> TraceReader<R> {
+    /// Checks the header, the records follow
+    pub fn new(mut input: R) -> io::Result<Self> {
+        let mut header = [0; MAGIC.len() + 2];
+        input.read_exact(&mut header)?;
+        if !header.starts_with(MAGIC) {
+            return Err(malformed("not a binary trace"));
+        }
+        if header[MAGIC.len()..] != VERSION.to_le_bytes() {
+            return Err(malformed("unsupported trace version"));
+        }
+
+        Ok(Self { input })
+    }
+
+    /// The next record, None at the end of the trace
+    pub fn next_record(&mut self) -> io::Result<Option<TraceRecord>> {
+        let mut bytes = [0; RECORD_SIZE];
+        let mut len = 0;
+        while len < RECORD_SIZE {
+            match self.input.read(&mut bytes[len..]) {
+                Ok(0) if len == 0 => return Ok(None),
+                Ok(0) => return Err(malformed("trace ends in the middle of a record")),
+                Ok(read) => len += read,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => (),
+                Err(error) => return Err(error),
+            }
+        }
+
+        TraceRecord::from_bytes(&bytes).map(Some)
+    }
+}
+
+impl<R: Read> Iterator for TraceReader<R> {
+    type Item = io::Result<TraceRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+fn malformed(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason)
+}
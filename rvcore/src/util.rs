@@ -3,3 +3,15 @@ pub fn sign_extend(data: u32, size: u32) -> i32 {
     assert!(size > 0 && size <= 32);
     ((data << (32 - size)) as i32) >> (32 - size)
 }
+
+/// Parses a decimal, `0x` hexadecimal or `0b` binary number
+pub fn parse_u32(text: &str) -> Option<u32> {
+    let text = text.trim().replace('_', "");
+    if let Some(hex) = text.strip_prefix("0x").or(text.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16).ok()
+    } else if let Some(bin) = text.strip_prefix("0b").or(text.strip_prefix("0B")) {
+        u32::from_str_radix(bin, 2).ok()
+    } else {
+        text.parse().ok()
+    }
+}
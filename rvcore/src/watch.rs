@@ -0,0 +1,101 @@
+//! Watchpoints over address ranges, checked by the [`Bus`](crate::bus::Bus) on every access
+
+use std::{fmt::Display, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    /// Reads and writes
+    Access,
+}
+
+impl WatchKind {
+    fn matches(self, access: Access) -> bool {
+        match self {
+            WatchKind::Read => access == Access::Read,
+            WatchKind::Write => access == Access::Write,
+            WatchKind::Access => true,
+        }
+    }
+}
+
+impl Display for WatchKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WatchKind::Read => write!(f, "r"),
+            WatchKind::Write => write!(f, "w"),
+            WatchKind::Access => write!(f, "rw"),
+        }
+    }
+}
+
+impl FromStr for WatchKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "r" => Ok(WatchKind::Read),
+            "w" => Ok(WatchKind::Write),
+            "rw" | "a" => Ok(WatchKind::Access),
+            _ => Err(format!(
+                "unknown watchpoint kind `{}`, expected r, w or rw",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+impl Display for Access {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Access::Read => write!(f, "Read"),
+            Access::Write => write!(f, "Write"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub addr: usize,
+    /// Length of the range in bytes
+    pub len: usize,
+    pub kind: WatchKind,
+}
+
+impl Watchpoint {
+    /// Returns true if an access of `size` bits at `addr` overlaps the range
+    pub fn matches(&self, addr: usize, size: u8, access: Access) -> bool {
+        let end = addr.saturating_add(size as usize / 8);
+        self.kind.matches(access) && addr < self.addr.saturating_add(self.len) && self.addr < end
+    }
+}
+
+impl Display for Watchpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {:#x}..{:#x}",
+            self.kind,
+            self.addr,
+            self.addr.saturating_add(self.len)
+        )
+    }
+}
+
+/// An access that triggered a watchpoint, reads have the same `old` and `new` value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    pub watchpoint: Watchpoint,
+    pub access: Access,
+    pub addr: usize,
+    pub size: u8,
+    pub old: u32,
+    pub new: u32,
+}
@@ -0,0 +1,30 @@
+use rvcore::{bus::Bus, DRam};
+
+#[test]
+fn find_wraps_around() {
+    let mut bus = Bus::with_base(DRam::new(0x100), 0x1000);
+    bus.store_bytes(0x1010, b"hello").unwrap();
+    bus.store_bytes(0x10fc, b"hell").unwrap();
+
+    assert_eq!(bus.find(b"hell", 0), Some(0x1010));
+    assert_eq!(bus.find(b"hell", 0x1011), Some(0x10fc));
+    assert_eq!(bus.find(b"hello", 0x1011), Some(0x1010));
+    assert_eq!(bus.find(b"world", 0x1000), None);
+}
+
+#[test]
+fn undo_recorded_writes() {
+    let mut bus = Bus::new(DRam::new(0x100));
+    bus.store(0x10, 32, 0x1111_1111);
+    bus.record_writes(true);
+
+    bus.store(0x10, 32, 0x2222_2222);
+    bus.store(0x12, 8, 0x33);
+    let writes = bus.take_writes();
+    assert_eq!(writes.len(), 2);
+    assert_eq!(bus.load(0x10, 32), 0x2233_2222);
+
+    bus.undo_writes(&writes);
+    assert_eq!(bus.load(0x10, 32), 0x1111_1111);
+    assert!(bus.take_writes().is_empty());
+}
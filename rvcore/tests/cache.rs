@@ -0,0 +1,111 @@
+use rvcore::{
+    cache::{CacheConfig, CacheHierarchy, Replacement, WritePolicy},
+    trace::{AccessKind, MemoryAccess},
+};
+
+fn read(addr: u32) -> MemoryAccess {
+    MemoryAccess {
+        kind: AccessKind::Read,
+        addr,
+        size: 32,
+        value: 0,
+    }
+}
+
+fn write(addr: u32) -> MemoryAccess {
+    MemoryAccess {
+        kind: AccessKind::Write,
+        ..read(addr)
+    }
+}
+
+/// A single set of two 16-byte lines in front of memory
+fn caches(l1d: &str) -> CacheHierarchy {
+    let l1d = format!("size=32,ways=2,line=16,{}", l1d);
+    CacheHierarchy::new(CacheConfig::default(), l1d.parse().unwrap(), None, 100)
+}
+
+#[test]
+fn parse_config() {
+    let config: CacheConfig = "size=32K, ways=8, replace=random, write=through"
+        .parse()
+        .unwrap();
+    assert_eq!(config.size, 32 * 1024);
+    assert_eq!(config.ways, 8);
+    assert_eq!(config.line, 32);
+    assert_eq!(config.replacement, Replacement::Random);
+    assert_eq!(config.write, WritePolicy::WriteThrough);
+    assert_eq!(config.sets(), 128);
+    assert_eq!("".parse::<CacheConfig>(), Ok(CacheConfig::default()));
+
+    assert!("size=48K".parse::<CacheConfig>().is_err());
+    assert!("line=24".parse::<CacheConfig>().is_err());
+    assert!("ways=0".parse::<CacheConfig>().is_err());
+    assert!("colour=blue".parse::<CacheConfig>().is_err());
+    assert!("replace=mru".parse::<CacheConfig>().is_err());
+}
+
+#[test]
+fn replacement_policies() {
+    for (policy, expected) in [("lru", 0), ("fifo", 1)] {
+        let mut caches = caches(&format!("replace={}", policy));
+        caches.access(&read(0x00));
+        caches.access(&read(0x10));
+        // LRU keeps 0x00 as it was used last, FIFO evicts it as it came first
+        caches.access(&read(0x04));
+        caches.access(&read(0x20));
+        caches.access(&read(0x00));
+        assert_eq!(caches.l1d.stats.read_misses, 3 + expected, "{}", policy);
+    }
+}
+
+#[test]
+fn write_policies() {
+    let mut back = caches("write=back");
+    back.access(&write(0x00));
+    back.access(&write(0x04));
+    back.access(&read(0x10));
+    back.access(&read(0x20));
+    let stats = back.l1d.stats;
+    assert_eq!(
+        (stats.write_misses, stats.read_misses, stats.writebacks),
+        (1, 2, 1)
+    );
+    assert_eq!((back.memory_reads, back.memory_writes), (3, 1));
+
+    let mut through = caches("write=through");
+    through.access(&write(0x00));
+    through.access(&write(0x04));
+    through.access(&read(0x00));
+    let stats = through.l1d.stats;
+    // writes don't allocate, so the read misses too
+    assert_eq!(
+        (stats.write_misses, stats.read_misses, stats.writebacks),
+        (2, 1, 0)
+    );
+    assert_eq!((through.memory_reads, through.memory_writes), (1, 2));
+}
+
+#[test]
+fn hierarchy_timing() {
+    let l1: CacheConfig = "size=1K,ways=1,line=16,latency=2".parse().unwrap();
+    let l2: CacheConfig = "size=4K,ways=4,line=64,latency=10".parse().unwrap();
+    let mut caches = CacheHierarchy::new(l1, l1, Some(l2), 100);
+
+    let fetch = MemoryAccess {
+        kind: AccessKind::Fetch,
+        ..read(0x100)
+    };
+    assert_eq!(caches.access(&fetch), 112);
+    assert_eq!(caches.access(&fetch), 2);
+    // the L2 line holds the data too
+    assert_eq!(caches.access(&read(0x110)), 12);
+    // a misaligned access that spans two lines
+    assert_eq!(caches.access(&read(0x11e)), 2 + 12);
+
+    assert_eq!(caches.l1i.stats.reads, 2);
+    assert_eq!(caches.l1d.stats.read_misses, 2);
+    assert_eq!(caches.l2.as_ref().unwrap().stats.read_misses, 1);
+    assert_eq!(caches.memory_reads, 1);
+    assert_eq!(caches.cycles, 112 + 2 + 12 + 14);
+}
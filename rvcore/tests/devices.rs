@@ -0,0 +1,51 @@
+use rvcore::{
+    bus::Bus,
+    devices::{TestFinisher, Uart},
+    DRam,
+};
+
+#[test]
+fn devices_take_priority_over_memory() {
+    let mut bus = Bus::new(DRam::new(0x2000));
+    bus.attach(0x1000, Box::<TestFinisher>::default());
+
+    bus.store(0x1000, 32, (3 << 16) | 0x3333);
+    assert_eq!(bus.load(0x1000, 32), 0);
+    assert_eq!(
+        bus.device_mut::<TestFinisher>().unwrap().take_exit(),
+        Some(3)
+    );
+    assert_eq!(bus.device_mut::<TestFinisher>().unwrap().take_exit(), None);
+
+    // memory past the device is unaffected
+    bus.store(0x0ffc, 32, 0x5555);
+    assert_eq!(bus.load(0x0ffc, 32), 0x5555);
+    assert_eq!(bus.device_mut::<TestFinisher>().unwrap().take_exit(), None);
+}
+
+#[test]
+fn accesses_past_the_end_of_memory() {
+    let mut bus = Bus::with_base(DRam::new(0x100), 0x8000_0000);
+    bus.store(0x8000_00fe, 32, 0xffff_ffff);
+    assert_eq!(bus.load(0x8000_00fc, 32), 0);
+    assert_eq!(bus.load(0x8000_00fe, 32), 0);
+    assert_eq!(bus.load(0x7fff_fffc, 32), 0);
+}
+
+#[test]
+fn uart_transmit_and_receive() {
+    let mut bus = Bus::new(DRam::new(0x100));
+    bus.attach(0x1000_0000, Box::<Uart>::default());
+
+    // transmit is always ready, nothing to receive yet
+    assert_eq!(bus.load(0x1000_0005, 8), 0x60);
+    bus.store(0x1000_0000, 8, b'h' as u32);
+    bus.store(0x1000_0000, 8, b'i' as u32);
+    assert_eq!(bus.device_mut::<Uart>().unwrap().take_output(), b"hi");
+
+    bus.device_mut::<Uart>().unwrap().push_input(b"ok");
+    assert_eq!(bus.load(0x1000_0005, 8), 0x61);
+    assert_eq!(bus.load(0x1000_0000, 8), b'o' as u32);
+    assert_eq!(bus.load(0x1000_0000, 8), b'k' as u32);
+    assert_eq!(bus.load(0x1000_0005, 8) & 1, 0);
+}
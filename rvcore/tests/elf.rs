@@ -0,0 +1,104 @@
+use rvcore::elf::{Elf, ElfError, LineTable, Symbol, SymbolKind, SymbolTable};
+
+fn symbol(name: &str, addr: u32, size: u32, kind: SymbolKind) -> Symbol {
+    Symbol {
+        name: name.into(),
+        addr,
+        size,
+        kind,
+        global: true,
+        section: 1,
+    }
+}
+
+fn table() -> SymbolTable {
+    SymbolTable::new(vec![
+        symbol("loop", 0x1010, 0, SymbolKind::NoType),
+        symbol("main", 0x1000, 0x20, SymbolKind::Func),
+        symbol("_start", 0x1000, 0, SymbolKind::NoType),
+        symbol("counter", 0x2000, 4, SymbolKind::Object),
+    ])
+}
+
+#[test]
+fn lookup_prefers_functions() {
+    let table = table();
+    let (symbol, offset) = table.lookup(0x1008).unwrap();
+    assert_eq!((symbol.name.as_str(), offset), ("main", 8));
+    assert_eq!(table.at(0x1000).count(), 2);
+}
+
+#[test]
+fn lookup_labels_and_sizes() {
+    let table = table();
+    assert_eq!(table.describe(0x1014).unwrap().to_string(), "loop+0x4");
+    assert_eq!(table.describe(0x2000).unwrap().to_string(), "counter");
+    // past the end of a sized symbol
+    assert!(table.lookup(0x2004).is_none());
+    assert!(table.lookup(0xfff).is_none());
+    assert_eq!(table.get("counter").unwrap().addr, 0x2000);
+}
+
+#[test]
+fn parse_errors() {
+    assert_eq!(Elf::parse(b"hello").unwrap_err(), ElfError::NotElf);
+    assert_eq!(
+        Elf::parse(&[0x7f, b'E', b'L', b'F', 1, 1]).unwrap_err(),
+        ElfError::Truncated
+    );
+    assert!(matches!(
+        Elf::parse(&[0x7f, b'E', b'L', b'F', 2, 1]).unwrap_err(),
+        ElfError::Unsupported(_)
+    ));
+}
+
+/// A line table unit around `program`, for `src/main.c`
+fn line_unit(version: u16, program: &[u8]) -> Vec<u8> {
+    let mut header = vec![1, 1, 1, (-5i8) as u8, 14, 13];
+    header.extend([0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1]);
+    header.extend(b"src\0\0main.c\0\x01\0\0\0");
+
+    let mut unit = version.to_le_bytes().to_vec();
+    unit.extend((header.len() as u32).to_le_bytes());
+    unit.extend(header);
+    unit.extend(program);
+
+    let mut bytes = (unit.len() as u32).to_le_bytes().to_vec();
+    bytes.extend(unit);
+    bytes
+}
+
+#[test]
+fn line_table() {
+    let program = [
+        // set_address 0x1000, line 10
+        &[0, 5, 2, 0x00, 0x10, 0, 0][..],
+        &[3, 9, 1],
+        // special opcode, 8 bytes and a line on
+        &[131],
+        // 4 bytes on and back to line 10
+        &[2, 4, 3, 0x7f, 1],
+        // end the sequence at 0x1010
+        &[2, 4, 0, 1, 1],
+    ]
+    .concat();
+    let lines = LineTable::parse(&line_unit(4, &program), &[], &[]).unwrap();
+
+    assert_eq!(lines.files(), ["src/main.c"]);
+    assert_eq!(lines.ranges().len(), 3);
+    assert_eq!(lines.lookup(0x1004), Some(("src/main.c", 10)));
+    assert_eq!(lines.lookup(0x1008), Some(("src/main.c", 11)));
+    assert_eq!(lines.lookup(0x100c), Some(("src/main.c", 10)));
+    assert_eq!(lines.lookup(0x1010), None);
+    assert_eq!(lines.lookup(0xffc), None);
+
+    assert!(matches!(
+        LineTable::parse(&line_unit(6, &program), &[], &[]),
+        Err(ElfError::Unsupported(_))
+    ));
+    let bytes = line_unit(4, &program);
+    assert_eq!(
+        LineTable::parse(&bytes[..bytes.len() - 1], &[], &[]).unwrap_err(),
+        ElfError::Truncated
+    );
+}
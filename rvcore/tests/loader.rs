@@ -0,0 +1,86 @@
+use rvcore::{
+    bus::Bus,
+    loader::{Format, Image, LoadError},
+    DRam,
+};
+
+// `addi x5, x6, 1; ebreak` followed by `.word 0x11223344`, from GNU objcopy
+const IHEX: &str = ":0200000480007A
+:080000009302130073001000CD
+:04000800443322114A
+:040000058000000077
+:00000001FF
+";
+
+const SREC: &str = "S0090000732E73726563A8
+S3118000000093021300730010004433221199
+S705800000007A
+";
+
+const PROGRAM: [u8; 12] = [
+    0x93, 0x02, 0x13, 0x00, 0x73, 0x00, 0x10, 0x00, 0x44, 0x33, 0x22, 0x11,
+];
+
+#[test]
+fn intel_hex() {
+    let image = Image::parse(IHEX.as_bytes(), Format::IHex, 0).unwrap();
+    assert_eq!(image.entry, 0x8000_0000);
+    assert_eq!(image.chunks.len(), 1);
+    assert_eq!(image.chunks[0].addr, 0x8000_0000);
+    assert_eq!(image.chunks[0].data, PROGRAM);
+}
+
+#[test]
+fn srec() {
+    let image = Image::parse(SREC.as_bytes(), Format::SRec, 0).unwrap();
+    assert_eq!(image.entry, 0x8000_0000);
+    assert_eq!(image.chunks[0].addr, 0x8000_0000);
+    assert_eq!(image.chunks[0].data, PROGRAM);
+}
+
+#[test]
+fn checksums() {
+    let bad = IHEX.replace("CD", "CE");
+    assert_eq!(
+        Image::parse(bad.as_bytes(), Format::IHex, 0).unwrap_err(),
+        LoadError::Checksum(2)
+    );
+
+    let bad = SREC.replace("99", "98");
+    assert_eq!(
+        Image::parse(bad.as_bytes(), Format::SRec, 0).unwrap_err(),
+        LoadError::Checksum(2)
+    );
+}
+
+#[test]
+fn binary_at_base() {
+    let mut bus = Bus::new(DRam::new(0x100));
+    let image = Image::parse(&PROGRAM, Format::Binary, 0x40).unwrap();
+    image.load(&mut bus).unwrap();
+
+    assert_eq!(image.entry, 0x40);
+    assert_eq!(bus.load(0x40, 32), 0x00130293);
+    assert_eq!(bus.load_bytes(0x48, 4).unwrap(), &PROGRAM[8..]);
+}
+
+#[test]
+fn out_of_range_writes_nothing() {
+    let mut bus = Bus::new(DRam::new(0x100));
+    let image = Image::parse(IHEX.as_bytes(), Format::IHex, 0).unwrap();
+    assert!(matches!(image.load(&mut bus), Err(LoadError::OutOfRange(_))));
+
+    let mut image = Image::parse(&PROGRAM, Format::Binary, 0).unwrap();
+    image.chunks.push(image.chunks[0].clone());
+    image.chunks[1].addr = 0xfc;
+    assert!(image.load(&mut bus).is_err());
+    assert_eq!(bus.load(0, 32), 0);
+}
+
+#[test]
+fn detect() {
+    assert_eq!(Format::detect(None, IHEX.as_bytes()), Format::IHex);
+    assert_eq!(Format::detect(None, SREC.as_bytes()), Format::SRec);
+    assert_eq!(Format::detect(None, &PROGRAM), Format::Binary);
+    assert_eq!("srec".parse(), Ok(Format::SRec));
+}
@@ -0,0 +1,97 @@
+use rvcore::predictor::{PredictorConfig, Predictors};
+
+/// `beq x0, x0, 0`, only the opcode matters
+const BRANCH: u32 = 0x0000_0063;
+/// `jal ra, 0`
+const CALL: u32 = 0x0000_00ef;
+/// `jalr x0, 0(ra)`
+const RET: u32 = 0x0000_8067;
+/// `jal x0, 0`
+const JUMP: u32 = 0x0000_006f;
+
+#[test]
+fn parse_config() {
+    assert_eq!("not-taken".parse(), Ok(PredictorConfig::NotTaken));
+    assert_eq!("static".parse(), Ok(PredictorConfig::NotTaken));
+    assert_eq!("bimodal".parse(), Ok(PredictorConfig::Bimodal(10)));
+    assert_eq!("gshare:12".parse(), Ok(PredictorConfig::Gshare(12)));
+    assert_eq!(PredictorConfig::Gshare(12).to_string(), "gshare:12");
+
+    assert!("gshare:0".parse::<PredictorConfig>().is_err());
+    assert!("bimodal:25".parse::<PredictorConfig>().is_err());
+    assert!("not-taken:4".parse::<PredictorConfig>().is_err());
+    assert!("perceptron".parse::<PredictorConfig>().is_err());
+}
+
+#[test]
+fn loop_branches() {
+    let configs = vec![
+        PredictorConfig::NotTaken,
+        PredictorConfig::Bimodal(4),
+        PredictorConfig::Gshare(4),
+    ];
+    let mut predictors = Predictors::new(configs, 4, 4);
+
+    // a loop of three iterations, run ten times: taken, taken, not taken
+    for _ in 0..10 {
+        for taken in [true, true, false] {
+            let next_pc = if taken { 0x40 } else { 0x54 };
+            predictors.retire(0x50, BRANCH, next_pc, Some(taken));
+        }
+    }
+
+    let record = &predictors.branches[&0x50];
+    assert_eq!(record.executed, 30);
+    assert_eq!(record.taken, 20);
+    assert_eq!(predictors.executed(), 30);
+
+    let [not_taken, bimodal, gshare] = predictors.mispredicted()[..] else {
+        panic!("expected three predictors");
+    };
+    assert_eq!(not_taken, 20);
+    // misses the first taken and every exit
+    assert_eq!(bimodal, 11);
+    // learns the pattern from the history
+    assert!(gshare < bimodal, "gshare mispredicted {}", gshare);
+}
+
+#[test]
+fn branch_to_next_instruction() {
+    let mut predictors = Predictors::new(vec![PredictorConfig::NotTaken], 4, 4);
+
+    // `beq x0, x0, 4` is always taken, though it goes on to pc + 4 like a fall through
+    for _ in 0..3 {
+        predictors.retire(0x50, BRANCH, 0x54, Some(true));
+    }
+
+    let record = &predictors.branches[&0x50];
+    assert_eq!((record.executed, record.taken), (3, 3));
+    assert_eq!(predictors.mispredicted(), [3]);
+}
+
+#[test]
+fn calls_and_returns() {
+    let mut predictors = Predictors::new(vec![], 4, 2);
+
+    for _ in 0..3 {
+        predictors.retire(0x10, CALL, 0x100, None);
+        predictors.retire(0x100, JUMP, 0x108, None);
+        predictors.retire(0x108, RET, 0x14, None);
+    }
+
+    let jumps = predictors.jumps;
+    assert_eq!(jumps.jumps, 6);
+    // the first call and jump, after that the targets are in the BTB
+    assert_eq!(jumps.btb_misses, 2);
+    assert_eq!(jumps.returns, 3);
+    assert_eq!(jumps.ras_misses, 0);
+
+    // three nested calls overflow a RAS of two, the outermost return misses
+    predictors.retire(0x20, CALL, 0x100, None);
+    predictors.retire(0x100, CALL, 0x200, None);
+    predictors.retire(0x200, CALL, 0x300, None);
+    predictors.retire(0x300, RET, 0x204, None);
+    predictors.retire(0x204, RET, 0x104, None);
+    predictors.retire(0x104, RET, 0x24, None);
+    assert_eq!(predictors.jumps.ras_misses, 1);
+}
@@ -0,0 +1,107 @@
+use rvcore::{
+    bus::Bus,
+    devices::{TestFinisher, Uart},
+    snapshot::{Snapshot, SnapshotError},
+    DRam,
+};
+
+fn machine() -> Bus {
+    let mut bus = Bus::with_base(DRam::new(0x3000), 0x8000_0000);
+    bus.attach(0x1000_0000, Box::<Uart>::default());
+    bus.attach(0x100000, Box::<TestFinisher>::default());
+    bus
+}
+
+#[test]
+fn round_trip_through_bytes() {
+    let mut bus = machine();
+    bus.store(0x8000_0010, 32, 0xdead_beef);
+    bus.store(0x8000_2ffc, 32, 0x1234_5678);
+    bus.device_mut::<Uart>().unwrap().push_input(b"typed");
+
+    let mut snapshot = Snapshot {
+        pc: 0x8000_0010,
+        fcsr: 0x21,
+        csrs: vec![(0x300, 8), (0xc02, 100)],
+        retired: 1 << 40,
+        ..Default::default()
+    };
+    snapshot.registers[2] = 0x8000_3000;
+    snapshot.float_registers[1] = 1.5f32.to_bits();
+    bus.save(&mut snapshot);
+
+    let bytes = snapshot.to_bytes();
+    // the middle page is all zeros and left out
+    assert!(bytes.len() < 2 * 4096 + 1024);
+    assert_eq!(Snapshot::from_bytes(&bytes, 0x3000), Ok(snapshot));
+}
+
+#[test]
+fn restore_memory_and_devices() {
+    let mut bus = machine();
+    bus.store(0x8000_0100, 32, 42);
+    bus.device_mut::<Uart>().unwrap().push_input(b"ab");
+
+    let mut snapshot = Snapshot::default();
+    bus.save(&mut snapshot);
+
+    bus.store(0x8000_0100, 32, 7);
+    assert_eq!(bus.load(0x1000_0000, 8), b'a' as u32);
+
+    bus.restore(&snapshot).unwrap();
+    assert_eq!(bus.load(0x8000_0100, 32), 42);
+    assert_eq!(bus.load(0x1000_0000, 8), b'a' as u32);
+    assert_eq!(bus.load(0x1000_0000, 8), b'b' as u32);
+
+    // memory takes the snapshot's size and address
+    let mut other = Bus::new(DRam::new(0x100));
+    other.attach(0x1000_0000, Box::<Uart>::default());
+    other.attach(0x100000, Box::<TestFinisher>::default());
+    other.restore(&snapshot).unwrap();
+    assert_eq!(other.base(), 0x8000_0000);
+    assert_eq!(other.dram.size(), 0x3000);
+    assert_eq!(other.load(0x8000_0100, 32), 42);
+}
+
+#[test]
+fn reject_mismatches() {
+    let mut snapshot = Snapshot::default();
+    machine().save(&mut snapshot);
+
+    let mut bus = Bus::new(DRam::new(0x100));
+    bus.attach(0x1000_0000, Box::<Uart>::default());
+    assert_eq!(bus.restore(&snapshot), Err(SnapshotError::DeviceMismatch));
+
+    let bytes = snapshot.to_bytes();
+    assert_eq!(
+        Snapshot::from_bytes(b"\x7fELF", 0x3000),
+        Err(SnapshotError::NotASnapshot)
+    );
+    assert_eq!(
+        Snapshot::from_bytes(&bytes[..bytes.len() - 1], 0x3000),
+        Err(SnapshotError::Malformed)
+    );
+}
+
+#[test]
+fn reject_bogus_memory_sizes() {
+    let mut snapshot = Snapshot::default();
+    machine().save(&mut snapshot);
+    let mut bytes = snapshot.to_bytes();
+    assert_eq!(
+        Snapshot::from_bytes(&bytes, 0x2000),
+        Err(SnapshotError::MemoryTooLarge(0x3000))
+    );
+
+    // after the magic, version, pc, registers, fcsr, retired, no CSRs and the memory base
+    let size = 6 + 2 + 4 * 66 + 8 + 4 + 4;
+    assert_eq!(bytes[size..size + 4], 0x3000u32.to_le_bytes());
+
+    // a size of 4 GiB with nothing after it fails before memory is allocated
+    bytes.truncate(size + 4);
+    bytes[size..].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert_eq!(
+        Snapshot::from_bytes(&bytes, usize::MAX),
+        Err(SnapshotError::Malformed)
+    );
+}
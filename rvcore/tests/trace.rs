@@ -0,0 +1,88 @@
+use rvcore::{
+    bus::Bus,
+    trace::{AccessKind, MemoryAccess, TraceFormat, TraceReader, TraceRecord, TraceWriter},
+    DRam,
+};
+
+#[test]
+fn bus_records_accesses() {
+    let mut bus = Bus::new(DRam::new(0x100));
+    bus.store(0x10, 32, 0x1234_5678);
+    assert!(bus.take_accesses().is_empty());
+
+    bus.trace_accesses(true, false);
+    bus.store(0x20, 8, 0xffff_ff80);
+    assert_eq!(bus.load(0x10, 16), 0x5678);
+    bus.fetch(0x10);
+    assert_eq!(
+        bus.take_accesses(),
+        [
+            MemoryAccess {
+                kind: AccessKind::Write,
+                addr: 0x20,
+                size: 8,
+                value: 0x80,
+            },
+            MemoryAccess {
+                kind: AccessKind::Read,
+                addr: 0x10,
+                size: 16,
+                value: 0x5678,
+            },
+        ]
+    );
+    assert!(bus.take_accesses().is_empty());
+
+    bus.trace_accesses(true, true);
+    bus.fetch(0x10);
+    assert_eq!(bus.take_accesses()[0].kind, AccessKind::Fetch);
+}
+
+#[test]
+fn binary_round_trip() {
+    let records = [
+        TraceRecord {
+            pc: 0x8000_0000,
+            kind: AccessKind::Fetch,
+            addr: 0x8000_0000,
+            size: 4,
+            value: 0x0000_0513,
+        },
+        TraceRecord {
+            pc: 0x8000_0004,
+            kind: AccessKind::Write,
+            addr: 0x8000_1000,
+            size: 1,
+            value: 0x7f,
+        },
+    ];
+
+    let mut bytes = Vec::new();
+    let mut writer = TraceWriter::new(&mut bytes, TraceFormat::Binary).unwrap();
+    for record in &records {
+        writer.write(record).unwrap();
+    }
+
+    let read: Vec<TraceRecord> = TraceReader::new(bytes.as_slice())
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(read, records);
+
+    let mut reader = TraceReader::new(&bytes[..bytes.len() - 1]).unwrap();
+    assert!(reader.next().unwrap().is_ok());
+    assert!(reader.next().unwrap().is_err());
+    assert!(TraceReader::new(&b"# pc kind addr size value\n"[..]).is_err());
+}
+
+#[test]
+fn text_records() {
+    let record = TraceRecord {
+        pc: 0x14,
+        kind: AccessKind::Read,
+        addr: 0x2000,
+        size: 4,
+        value: 42,
+    };
+    assert_eq!(record.to_string(), "00000014 R 00002000 4 0000002a");
+}
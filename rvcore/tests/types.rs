@@ -0,0 +1,321 @@
+use proptest::prelude::*;
+use rvcore::ins::{
+    BType, IType, JType, RType, SType, UType, OPCODE_AUIPC, OPCODE_BRANCH, OPCODE_JAL, OPCODE_LOAD,
+    OPCODE_LUI, OPCODE_MASK, OPCODE_OP, OPCODE_OPIMM, OPCODE_STORE,
+};
+
+// ---- Strategies ----
+
+fn register() -> impl Strategy<Value = u8> {
+    0u8..32
+}
+
+fn funct3() -> impl Strategy<Value = u8> {
+    0u8..8
+}
+
+fn rtype() -> impl Strategy<Value = RType> {
+    (0u8..128, register(), register(), funct3(), register()).prop_map(
+        |(funct7, rs2, rs1, funct3, rd)| RType {
+            funct7,
+            rs2,
+            rs1,
+            funct3,
+            rd,
+        },
+    )
+}
+
+fn itype() -> impl Strategy<Value = IType> {
+    (-2048i32..2048, register(), funct3(), register()).prop_map(|(imm, rs1, funct3, rd)| IType {
+        imm,
+        rs1,
+        funct3,
+        rd,
+    })
+}
+
+fn utype() -> impl Strategy<Value = UType> {
+    (-(1i32 << 19)..(1 << 19), register()).prop_map(|(imm, rd)| UType { imm, rd })
+}
+
+fn stype() -> impl Strategy<Value = SType> {
+    (-2048i32..2048, register(), register(), funct3()).prop_map(|(imm, rs2, rs1, funct3)| SType {
+        imm,
+        rs2,
+        rs1,
+        funct3,
+    })
+}
+
+fn btype() -> impl Strategy<Value = BType> {
+    (-2048i32..2048, register(), register(), funct3()).prop_map(|(imm, rs2, rs1, funct3)| BType {
+        imm: imm * 2,
+        rs2,
+        rs1,
+        funct3,
+    })
+}
+
+fn jtype() -> impl Strategy<Value = JType> {
+    (-(1i32 << 19)..(1 << 19), register()).prop_map(|(imm, rd)| JType { imm: imm * 2, rd })
+}
+
+// ---- Round Trips ----
+
+proptest! {
+    #[test]
+    fn rtype_round_trip(data in rtype()) {
+        prop_assert_eq!(data.encode() & OPCODE_MASK, 0);
+        prop_assert_eq!(RType::decode(data.encode()), data);
+    }
+
+    #[test]
+    fn itype_round_trip(data in itype()) {
+        prop_assert_eq!(data.encode() & OPCODE_MASK, 0);
+        prop_assert_eq!(IType::decode(data.encode()), data);
+    }
+
+    #[test]
+    fn utype_round_trip(data in utype()) {
+        prop_assert_eq!(data.encode() & OPCODE_MASK, 0);
+        prop_assert_eq!(UType::decode(data.encode()), data);
+    }
+
+    #[test]
+    fn stype_round_trip(data in stype()) {
+        prop_assert_eq!(data.encode() & OPCODE_MASK, 0);
+        prop_assert_eq!(SType::decode(data.encode()), data);
+    }
+
+    #[test]
+    fn btype_round_trip(data in btype()) {
+        prop_assert_eq!(data.encode() & OPCODE_MASK, 0);
+        prop_assert_eq!(BType::decode(data.encode()), data);
+    }
+
+    #[test]
+    fn jtype_round_trip(data in jtype()) {
+        prop_assert_eq!(data.encode() & OPCODE_MASK, 0);
+        prop_assert_eq!(JType::decode(data.encode()), data);
+    }
+
+    /// Every bit outside of the opcode belongs to exactly one field,
+    /// so re-encoding a decoded word must reproduce it
+    #[test]
+    fn decode_is_lossless(ins in any::<u32>()) {
+        let expected = ins & !OPCODE_MASK;
+        prop_assert_eq!(RType::decode(ins).encode(), expected);
+        prop_assert_eq!(IType::decode(ins).encode(), expected);
+        prop_assert_eq!(UType::decode(ins).encode(), expected);
+        prop_assert_eq!(SType::decode(ins).encode(), expected);
+        prop_assert_eq!(BType::decode(ins).encode(), expected);
+        prop_assert_eq!(JType::decode(ins).encode(), expected);
+    }
+}
+
+// ---- Reference Encodings ----
+
+#[test]
+fn rtype_reference() {
+    // add x3, x1, x2
+    let add = RType {
+        funct7: 0,
+        rs2: 2,
+        rs1: 1,
+        funct3: 0,
+        rd: 3,
+    };
+    assert_eq!(add.encode() | OPCODE_OP, 0x002081b3);
+
+    // sub x5, x6, x7
+    let sub = RType {
+        funct7: 32,
+        rs2: 7,
+        rs1: 6,
+        funct3: 0,
+        rd: 5,
+    };
+    assert_eq!(RType::decode(0x407302b3), sub);
+    assert_eq!(sub.encode() | OPCODE_OP, 0x407302b3);
+}
+
+#[test]
+fn itype_reference() {
+    let cases = [
+        // addi x5, x6, 1
+        (
+            0x00130293,
+            OPCODE_OPIMM,
+            IType {
+                imm: 1,
+                rs1: 6,
+                funct3: 0,
+                rd: 5,
+            },
+        ),
+        // addi x1, x1, -1
+        (
+            0xfff08093,
+            OPCODE_OPIMM,
+            IType {
+                imm: -1,
+                rs1: 1,
+                funct3: 0,
+                rd: 1,
+            },
+        ),
+        // lw x10, -4(x2)
+        (
+            0xffc12503,
+            OPCODE_LOAD,
+            IType {
+                imm: -4,
+                rs1: 2,
+                funct3: 2,
+                rd: 10,
+            },
+        ),
+    ];
+
+    for (ins, opcode, data) in cases {
+        assert_eq!(IType::decode(ins), data);
+        assert_eq!(data.encode() | opcode, ins);
+    }
+}
+
+#[test]
+fn utype_reference() {
+    let cases = [
+        // lui x5, 0x12345
+        (
+            0x123452b7,
+            OPCODE_LUI,
+            UType {
+                imm: 0x12345,
+                rd: 5,
+            },
+        ),
+        // lui x1, 0xfffff
+        (0xfffff0b7, OPCODE_LUI, UType { imm: -1, rd: 1 }),
+        // auipc x10, 0x80000
+        (
+            0x80000517,
+            OPCODE_AUIPC,
+            UType {
+                imm: -0x80000,
+                rd: 10,
+            },
+        ),
+    ];
+
+    for (ins, opcode, data) in cases {
+        assert_eq!(UType::decode(ins), data);
+        assert_eq!(data.encode() | opcode, ins);
+    }
+}
+
+#[test]
+fn stype_reference() {
+    let cases = [
+        // sw x1, 12(x2)
+        (
+            0x00112623,
+            SType {
+                imm: 12,
+                rs2: 1,
+                rs1: 2,
+                funct3: 2,
+            },
+        ),
+        // sb x5, -2048(x10)
+        (
+            0x80550023,
+            SType {
+                imm: -2048,
+                rs2: 5,
+                rs1: 10,
+                funct3: 0,
+            },
+        ),
+        // sw x8, 2047(x9)
+        (
+            0x7e84afa3,
+            SType {
+                imm: 2047,
+                rs2: 8,
+                rs1: 9,
+                funct3: 2,
+            },
+        ),
+    ];
+
+    for (ins, data) in cases {
+        assert_eq!(SType::decode(ins), data);
+        assert_eq!(data.encode() | OPCODE_STORE, ins);
+    }
+}
+
+#[test]
+fn btype_reference() {
+    let cases = [
+        // beq x1, x2, 2048
+        (
+            0x002080e3,
+            BType {
+                imm: 2048,
+                rs2: 2,
+                rs1: 1,
+                funct3: 0,
+            },
+        ),
+        // bne x10, x0, -8
+        (
+            0xfe051ce3,
+            BType {
+                imm: -8,
+                rs2: 0,
+                rs1: 10,
+                funct3: 1,
+            },
+        ),
+        // bge x3, x4, -4096
+        (
+            0x8041d063,
+            BType {
+                imm: -4096,
+                rs2: 4,
+                rs1: 3,
+                funct3: 5,
+            },
+        ),
+    ];
+
+    for (ins, data) in cases {
+        assert_eq!(BType::decode(ins), data);
+        assert_eq!(data.encode() | OPCODE_BRANCH, ins);
+    }
+}
+
+#[test]
+fn jtype_reference() {
+    let cases = [
+        // jal x1, -12
+        (0xff5ff0ef, JType { imm: -12, rd: 1 }),
+        // jal x0, 2048
+        (0x0010006f, JType { imm: 2048, rd: 0 }),
+        // jal x1, -1048576
+        (
+            0x800000ef,
+            JType {
+                imm: -1048576,
+                rd: 1,
+            },
+        ),
+    ];
+
+    for (ins, data) in cases {
+        assert_eq!(JType::decode(ins), data);
+        assert_eq!(data.encode() | OPCODE_JAL, ins);
+    }
+}
@@ -0,0 +1,64 @@
+use rvcore::{
+    bus::Bus,
+    watch::{Access, WatchKind, Watchpoint},
+    DRam,
+};
+
+#[test]
+fn write_watchpoints() {
+    let mut bus = Bus::new(DRam::new(0x100));
+    let watchpoint = Watchpoint {
+        addr: 0x40,
+        len: 8,
+        kind: WatchKind::Write,
+    };
+    bus.add_watchpoint(watchpoint);
+
+    // reads and writes outside the range are ignored
+    bus.store(0x48, 32, 1);
+    bus.load(0x40, 32);
+    assert_eq!(bus.take_watch_hit(), None);
+
+    bus.store(0x44, 32, 0x1234);
+    bus.store(0x3e, 32, 0xffff_ffff);
+    let hit = bus.take_watch_hit().unwrap();
+    assert_eq!(hit.access, Access::Write);
+    assert_eq!((hit.addr, hit.old, hit.new), (0x44, 0, 0x1234));
+
+    // only the first hit is kept until it's taken
+    assert_eq!(bus.take_watch_hit(), None);
+
+    assert!(bus.remove_watchpoint(&watchpoint));
+    assert!(!bus.remove_watchpoint(&watchpoint));
+}
+
+#[test]
+fn read_and_access_watchpoints() {
+    let mut bus = Bus::new(DRam::new(0x100));
+    bus.store(0x10, 32, 0xaabb_ccdd);
+    bus.add_watchpoint(Watchpoint {
+        addr: 0x13,
+        len: 1,
+        kind: WatchKind::Read,
+    });
+
+    assert_eq!(bus.load(0x10, 8), 0xdd);
+    assert_eq!(bus.take_watch_hit(), None);
+    bus.load(0x12, 16);
+    let hit = bus.take_watch_hit().unwrap();
+    assert_eq!((hit.access, hit.new), (Access::Read, 0xaabb));
+
+    bus.add_watchpoint(Watchpoint {
+        addr: 0x20,
+        len: 4,
+        kind: WatchKind::Access,
+    });
+    bus.store(0x20, 8, 5);
+    assert_eq!(bus.take_watch_hit().unwrap().access, Access::Write);
+    bus.load(0x20, 8);
+    assert_eq!(bus.take_watch_hit().unwrap().access, Access::Read);
+    // fetching code is not a data read
+    bus.fetch(0x20);
+    bus.fetch(0x10);
+    assert_eq!(bus.take_watch_hit(), None);
+}